@@ -0,0 +1,399 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{self, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use owlchess::{Board, Coord};
+use relm::Widget;
+
+use crate::engine::UciEngine;
+use crate::epd::EpdRecord;
+use crate::exercises::filter::{generate_filtered_position, GenerationFilter};
+use crate::exercises::{generate_exercise, generate_position, random_side, Exercise, Goal};
+use crate::packs::{GeneratorConstraints, PackExercise, Side};
+use crate::rendering::{Arrow, Diagram, Theme};
+use crate::scheduling::next_due_drill;
+use crate::settings::Settings;
+use crate::statistics::Statistics;
+use crate::tui;
+use crate::widgets::mainwindow::MainWindow;
+use crate::worksheets;
+
+#[derive(Parser)]
+#[clap(
+    name = "basic-chess-endgames",
+    version,
+    about = "Practice the basic chess endgames against an engine."
+)]
+pub struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Launches the graphical interface (the default).
+    Gui(GameOptions),
+    /// Prints random positions of an endgame.
+    Generate(GenerateOptions),
+    /// Draws a position as a PNG or SVG image.
+    Render(RenderOptions),
+    /// Plays an exercise in the terminal.
+    PlayTui(PlayTuiOptions),
+    /// Writes random positions of an endgame as a PDF worksheet, with an answer key.
+    Worksheet(WorksheetOptions),
+}
+
+#[derive(Args, Default)]
+struct GameOptions {
+    /// Starts with this position.
+    #[clap(long, conflicts_with = "endgame")]
+    fen: Option<String>,
+    /// Starts with a random position of this endgame, given by the pieces of
+    /// each side, the player side first (e.g. KQ-K or KBN-K).
+    #[clap(long)]
+    endgame: Option<String>,
+    /// Side played by the user (by default the side to move of the FEN, or a random side).
+    #[clap(long, value_enum)]
+    side: Option<SideArg>,
+}
+
+#[derive(Args)]
+struct GenerateOptions {
+    /// Pieces of each side, the side to move first (e.g. KQ-K or KBN-K).
+    endgame: String,
+    /// Number of positions.
+    #[clap(short = 'n', long, default_value_t = 1)]
+    count: usize,
+    /// Side to move (random for each position by default).
+    #[clap(long, value_enum)]
+    side: Option<SideArg>,
+    #[clap(long, value_enum, default_value_t = PositionFormat::Fen)]
+    format: PositionFormat,
+    #[clap(flatten)]
+    filter: FilterOptions,
+    /// UCI engine checking the positions for the filter options (by default the one
+    /// selected in the graphical interface).
+    #[clap(long)]
+    engine: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct RenderOptions {
+    /// Position to draw.
+    #[clap(long)]
+    fen: String,
+    /// Image file, its extension giving the format (.png or .svg).
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Width and height of the image.
+    #[clap(long, default_value_t = 400)]
+    size: i32,
+    /// Shows the board from the black side.
+    #[clap(long)]
+    reversed: bool,
+    /// Cell to highlight (e.g. e4), can be repeated.
+    #[clap(long = "highlight", value_name = "CELL")]
+    highlights: Vec<String>,
+    /// Arrow between two cells (e.g. e2e4), can be repeated.
+    #[clap(long = "arrow", value_name = "MOVE")]
+    arrows: Vec<String>,
+    /// Colors of the board : classic, green or print.
+    #[clap(long, default_value = "classic")]
+    theme: String,
+}
+
+#[derive(Args)]
+struct PlayTuiOptions {
+    #[clap(flatten)]
+    game: GameOptions,
+    /// UCI engine to play against (by default the one selected in the graphical interface).
+    #[clap(long)]
+    engine: Option<PathBuf>,
+    /// Skill level of the engine, from 0 to 20 (when it supports the Skill Level option).
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..=20))]
+    skill_level: Option<u8>,
+    /// Elo rating the engine plays at (when it supports the UCI_Elo option).
+    #[clap(long)]
+    elo: Option<u32>,
+    /// Percentage of the computer moves picked among the lines keeping the result of the best one.
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    suboptimal_rate: Option<u8>,
+}
+
+#[derive(Args)]
+struct WorksheetOptions {
+    /// Pieces of each side, the side to move first (e.g. KQ-K or KBN-K).
+    endgame: String,
+    /// Number of positions.
+    #[clap(short = 'n', long, default_value_t = 6)]
+    count: usize,
+    /// Side to move (random for each position by default).
+    #[clap(long, value_enum)]
+    side: Option<SideArg>,
+    /// PDF file.
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Title of the pages (by default the endgame name).
+    #[clap(long)]
+    title: Option<String>,
+    /// Colors of the board : classic, green or print.
+    #[clap(long, default_value = "print")]
+    theme: String,
+    #[clap(flatten)]
+    filter: FilterOptions,
+    /// UCI engine giving the answers (by default the one selected in the graphical interface).
+    #[clap(long)]
+    engine: Option<PathBuf>,
+}
+
+/// Keeps only the positions the engine finds winning for the side to move, without
+/// stalemate trick, when one of these options is given.
+#[derive(Args)]
+struct FilterOptions {
+    /// Minimum moves to mate.
+    #[clap(long)]
+    min_mate: Option<u32>,
+    /// Maximum moves to mate.
+    #[clap(long)]
+    max_mate: Option<u32>,
+    /// Keeps only the positions with a single winning move.
+    #[clap(long)]
+    unique: bool,
+    /// Filters the positions even without the options above.
+    #[clap(long)]
+    winning: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SideArg {
+    White,
+    Black,
+}
+
+impl From<SideArg> for Side {
+    fn from(side: SideArg) -> Self {
+        match side {
+            SideArg::White => Side::White,
+            SideArg::Black => Side::Black,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PositionFormat {
+    Fen,
+    Epd,
+}
+
+impl FilterOptions {
+    fn to_filter(&self) -> Option<GenerationFilter> {
+        if self.min_mate.is_none() && self.max_mate.is_none() && !self.unique && !self.winning {
+            return None;
+        }
+        Some(GenerationFilter {
+            min_mate_moves: self.min_mate,
+            max_mate_moves: self.max_mate,
+            unique_winning_move: self.unique,
+        })
+    }
+}
+
+impl Cli {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self.command {
+            None => run_gui(GameOptions::default()),
+            Some(Command::Gui(options)) => run_gui(options),
+            Some(Command::Generate(options)) => generate(options),
+            Some(Command::Render(options)) => render(options),
+            Some(Command::PlayTui(options)) => play_tui(options),
+            Some(Command::Worksheet(options)) => write_worksheet(options),
+        }
+    }
+}
+
+impl GameOptions {
+    /// The exercise asked for, if any.
+    fn to_exercise(&self) -> anyhow::Result<Option<Exercise>> {
+        let generator = match &self.endgame {
+            Some(endgame) => Some(GeneratorConstraints::from_spec(endgame)?),
+            None => None,
+        };
+        if self.fen.is_none() && generator.is_none() {
+            return Ok(None);
+        }
+
+        let pack_exercise = PackExercise {
+            title: String::new(),
+            fen: self.fen.clone(),
+            endgame: generator.as_ref().and_then(|generator| generator.endgame()),
+            generator,
+            goal: Goal::Win,
+            player_side: self.side.map(Side::from),
+            hints: vec![],
+            best_moves: vec![],
+            avoid_moves: vec![],
+            computer_strength: None,
+        };
+        pack_exercise.to_exercise().map(Some)
+    }
+}
+
+fn run_gui(options: GameOptions) -> anyhow::Result<()> {
+    let exercise = options.to_exercise()?;
+    MainWindow::run(exercise).map_err(|_| anyhow::anyhow!("Failed to launch the window."))
+}
+
+fn generate(options: GenerateOptions) -> anyhow::Result<()> {
+    let generator = GeneratorConstraints::from_spec(&options.endgame)?;
+    let positions = match options.filter.to_filter() {
+        Some(filter) => {
+            let mut engine = UciEngine::new(&get_engine_path(options.engine)?)?;
+            generate_filtered_positions(
+                &generator,
+                options.count,
+                options.side,
+                &filter,
+                &mut engine,
+            )?
+        }
+        None => generate_positions(&generator, options.count, options.side)?,
+    };
+    for (index, board) in positions.into_iter().enumerate() {
+        match options.format {
+            PositionFormat::Fen => println!("{}", board.as_fen()),
+            PositionFormat::Epd => {
+                let mut record = EpdRecord::new(board);
+                record.id = Some(format!("{}.{}", options.endgame, index + 1));
+                println!("{}", record.to_line());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render(options: RenderOptions) -> anyhow::Result<()> {
+    let mut diagram = Diagram::from_fen(&options.fen)?;
+    diagram.reversed = options.reversed;
+    diagram.theme = Theme::from_name(&options.theme)
+        .with_context(|| format!("Unknown theme {}.", options.theme))?;
+    for cell in &options.highlights {
+        diagram.highlight(parse_cell(cell)?);
+    }
+    for arrow in &options.arrows {
+        diagram.arrows.push(parse_arrow(arrow)?);
+    }
+    diagram.write(&options.output, options.size)
+}
+
+fn play_tui(options: PlayTuiOptions) -> anyhow::Result<()> {
+    let engine_path = get_engine_path(options.engine)?;
+    let mut exercise = match options.game.to_exercise()? {
+        Some(exercise) => exercise,
+        None => generate_exercise(next_due_drill(Statistics::load()?.results()))?,
+    };
+
+    let mut strength = Settings::load()?.computer_strength;
+    if options.skill_level.is_some() {
+        strength.skill_level = options.skill_level;
+    }
+    if options.elo.is_some() {
+        strength.elo = options.elo;
+    }
+    if let Some(suboptimal_rate) = options.suboptimal_rate {
+        strength.suboptimal_rate = suboptimal_rate;
+    }
+    exercise.computer_strength = Some(strength);
+
+    tui::play(exercise, &engine_path)
+}
+
+fn write_worksheet(options: WorksheetOptions) -> anyhow::Result<()> {
+    let generator = GeneratorConstraints::from_spec(&options.endgame)?;
+    let theme = Theme::from_name(&options.theme)
+        .with_context(|| format!("Unknown theme {}.", options.theme))?;
+    let title = match (options.title, generator.endgame()) {
+        (Some(title), _) => title,
+        (None, Some(kind)) => kind.title(),
+        (None, None) => options.endgame.clone(),
+    };
+
+    let mut engine = UciEngine::new(&get_engine_path(options.engine)?)?;
+    let positions = match options.filter.to_filter() {
+        Some(filter) => generate_filtered_positions(
+            &generator,
+            options.count,
+            options.side,
+            &filter,
+            &mut engine,
+        )?,
+        None => generate_positions(&generator, options.count, options.side)?,
+    };
+    let entries = worksheets::build_entries(positions, &mut engine)?;
+    worksheets::write_worksheet(&options.output, &title, &entries, theme)
+}
+
+fn generate_positions(
+    generator: &GeneratorConstraints,
+    count: usize,
+    side: Option<SideArg>,
+) -> anyhow::Result<Vec<Board>> {
+    (0..count)
+        .map(|_| {
+            let side = match side {
+                Some(side) => Side::from(side).into(),
+                None => random_side(),
+            };
+            generate_position(&generator.player_pieces, &generator.defender_pieces, side)
+        })
+        .collect()
+}
+
+fn generate_filtered_positions(
+    generator: &GeneratorConstraints,
+    count: usize,
+    side: Option<SideArg>,
+    filter: &GenerationFilter,
+    engine: &mut UciEngine,
+) -> anyhow::Result<Vec<Board>> {
+    (0..count)
+        .map(|_| {
+            let side = match side {
+                Some(side) => Side::from(side).into(),
+                None => random_side(),
+            };
+            generate_filtered_position(
+                &generator.player_pieces,
+                &generator.defender_pieces,
+                side,
+                filter,
+                engine,
+                &AtomicBool::new(false),
+            )
+        })
+        .collect()
+}
+
+fn get_engine_path(engine_path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    match engine_path {
+        Some(engine_path) => Ok(engine_path),
+        None => Settings::load()?
+            .engine_path
+            .with_context(|| "No engine has been selected (use --engine)."),
+    }
+}
+
+fn parse_cell(cell: &str) -> anyhow::Result<Coord> {
+    Coord::from_str(cell).map_err(|err| anyhow::anyhow!("Bad cell {} ({}).", cell, err))
+}
+
+fn parse_arrow(arrow: &str) -> anyhow::Result<Arrow> {
+    if arrow.len() != 4 || !arrow.is_ascii() {
+        return Err(anyhow::anyhow!("Bad arrow {} (expected e.g. e2e4).", arrow));
+    }
+    Ok(Arrow {
+        start: parse_cell(&arrow[0..2])?,
+        target: parse_cell(&arrow[2..4])?,
+    })
+}
@@ -0,0 +1,132 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use owlchess::MoveChain;
+
+use super::{parse_pv_line, EngineInput, PvLine, UciEngine};
+
+pub struct AnalysisUpdate {
+    pub analysis_id: u64,
+    pub line: PvLine,
+}
+
+enum AnalysisCommand {
+    Start {
+        id: u64,
+        moves_chain: Box<MoveChain>,
+    },
+    Stop,
+}
+
+/// Runs an engine in infinite mode in a thread, giving each principal variation it
+/// sends to a callback, from the worker thread.
+pub struct AnalysisWorker {
+    commands: Option<mpsc::Sender<AnalysisCommand>>,
+    input: EngineInput,
+    analysis_id: u64,
+}
+
+impl AnalysisWorker {
+    pub fn new<F>(path: &Path, lines_count: usize, on_update: F) -> anyhow::Result<Self>
+    where
+        F: Fn(AnalysisUpdate) + Send + 'static,
+    {
+        let mut engine = UciEngine::new(path)?;
+        let input = engine.input();
+        let (commands, receiver) = mpsc::channel::<AnalysisCommand>();
+
+        thread::spawn(move || {
+            let mut pending_command = None;
+            loop {
+                let command = match pending_command.take() {
+                    Some(command) => command,
+                    None => match receiver.recv() {
+                        Ok(command) => command,
+                        Err(_) => break,
+                    },
+                };
+                // Only the last position asked for matters.
+                let (id, moves_chain) = match receiver.try_iter().last().unwrap_or(command) {
+                    AnalysisCommand::Start { id, moves_chain } => (id, moves_chain),
+                    AnalysisCommand::Stop => continue,
+                };
+
+                if engine
+                    .start_infinite_search(&moves_chain, lines_count)
+                    .is_err()
+                {
+                    break;
+                }
+                // A command sent before the search started could not stop it.
+                if let Ok(command) = receiver.try_recv() {
+                    pending_command = Some(command);
+                    if engine.send_command("stop").is_err() {
+                        break;
+                    }
+                }
+
+                loop {
+                    let line = match engine.read_line() {
+                        Ok(line) => line,
+                        Err(_) => return,
+                    };
+                    match line.split_whitespace().next() {
+                        Some("info") => {
+                            if let Some(pv_line) = parse_pv_line(&line, moves_chain.last()) {
+                                on_update(AnalysisUpdate {
+                                    analysis_id: id,
+                                    line: pv_line,
+                                });
+                            }
+                        }
+                        Some("bestmove") => break,
+                        _ => {}
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            commands: Some(commands),
+            input,
+            analysis_id: 0,
+        })
+    }
+
+    /// Analyses the last position of the chain, stopping the previous analysis.
+    pub fn start(&mut self, moves_chain: &MoveChain) {
+        self.analysis_id += 1;
+        self.send(AnalysisCommand::Start {
+            id: self.analysis_id,
+            moves_chain: Box::new(moves_chain.clone()),
+        });
+    }
+
+    pub fn stop(&mut self) {
+        self.analysis_id += 1;
+        self.send(AnalysisCommand::Stop);
+    }
+
+    /// Id of the updates of the current analysis : the others are late ones.
+    pub fn analysis_id(&self) -> u64 {
+        self.analysis_id
+    }
+
+    /// The command is queued before stopping the search, so that the thread sees it
+    /// once the engine gives its best move.
+    fn send(&mut self, command: AnalysisCommand) {
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(command);
+        }
+        let _ = self.input.stop_search();
+    }
+}
+
+impl Drop for AnalysisWorker {
+    /// The thread ends, quitting the engine, once its search is stopped.
+    fn drop(&mut self) {
+        self.commands = None;
+        let _ = self.input.stop_search();
+    }
+}
@@ -0,0 +1,691 @@
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{self, Context};
+use owlchess::{Board, Move, MoveChain};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+pub mod analysis;
+pub mod worker;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+pub struct Analysis {
+    pub best_move: Move,
+    pub score: Option<Score>,
+}
+
+/// A principal variation of an infinite search, as given by an info line.
+#[derive(Clone, Debug)]
+pub struct PvLine {
+    /// From 0, the best line first.
+    pub index: usize,
+    pub depth: u32,
+    /// From the point of view of the side to move.
+    pub score: Option<Score>,
+    pub nodes_per_second: Option<u64>,
+    pub moves: Vec<Move>,
+}
+
+/// How well the computer side plays. The UCI options are only sent to the engines supporting them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineStrength {
+    /// UCI `Skill Level`, from 0 to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skill_level: Option<u8>,
+    /// UCI `UCI_Elo`, with `UCI_LimitStrength`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elo: Option<u32>,
+    /// Percentage of the moves picked at random among the best lines keeping the
+    /// result of the best one (e.g. a longer mate, a slower defence).
+    #[serde(default)]
+    pub suboptimal_rate: u8,
+}
+
+impl EngineStrength {
+    pub fn is_limited(&self) -> bool {
+        self.skill_level.is_some() || self.elo.is_some() || self.suboptimal_rate > 0
+    }
+}
+
+/// Expected result, from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExpectedResult {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl ExpectedResult {
+    /// The same result, from the point of view of the other side.
+    pub(crate) fn for_opponent(self) -> Self {
+        match self {
+            ExpectedResult::Win => ExpectedResult::Loss,
+            ExpectedResult::Draw => ExpectedResult::Draw,
+            ExpectedResult::Loss => ExpectedResult::Win,
+        }
+    }
+}
+
+/// Commands input of the engine, which can be shared with another thread to stop a search.
+#[derive(Clone)]
+pub struct EngineInput {
+    stdin: Arc<Mutex<ChildStdin>>,
+}
+
+impl EngineInput {
+    /// Ends the current search, the engine then giving its best move so far.
+    pub fn stop_search(&self) -> anyhow::Result<()> {
+        self.send("stop")
+    }
+
+    fn send(&self, command: &str) -> anyhow::Result<()> {
+        let mut stdin = self
+            .stdin
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Engine input is not available."))?;
+        writeln!(stdin, "{}", command).with_context(|| "Failed to send command to engine.")?;
+        stdin
+            .flush()
+            .with_context(|| "Failed to send command to engine.")
+    }
+}
+
+pub struct UciEngine {
+    process: Child,
+    input: EngineInput,
+    output: BufReader<ChildStdout>,
+    /// Names of the options declared by the engine.
+    options: Vec<String>,
+    /// Given by the engine after the `uci` command (e.g. "Stockfish 16").
+    name: Option<String>,
+}
+
+impl UciEngine {
+    pub const DEFAULT_MOVE_TIME_MS: u64 = 1000;
+    pub const COMPUTER_MOVE_TIME_MS: u64 = 500;
+    pub const OPTIMUM_SEARCH_TIME_MS: u64 = 2000;
+    /// Search of each position of a reviewed game.
+    pub const REVIEW_SEARCH_TIME_MS: u64 = 300;
+    /// Lines searched to pick a suboptimal move.
+    const SUBOPTIMAL_LINES: usize = 4;
+
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let mut process = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to launch engine {}.", path.display()))?;
+
+        let input = process
+            .stdin
+            .take()
+            .with_context(|| "Failed to get engine input.")?;
+        let output = process
+            .stdout
+            .take()
+            .with_context(|| "Failed to get engine output.")?;
+
+        let mut engine = Self {
+            process,
+            input: EngineInput {
+                stdin: Arc::new(Mutex::new(input)),
+            },
+            output: BufReader::new(output),
+            options: vec![],
+            name: None,
+        };
+
+        engine.send_command("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if line.split_whitespace().next() == Some("uciok") {
+                break;
+            }
+            if let Some(option) = parse_option_name(&line) {
+                engine.options.push(option);
+            } else if let Some(name) = parse_engine_name(&line) {
+                engine.name = Some(name);
+            }
+        }
+        engine.wait_ready()?;
+
+        Ok(engine)
+    }
+
+    pub fn best_move(
+        &mut self,
+        moves_chain: &MoveChain,
+        move_time_ms: u64,
+    ) -> anyhow::Result<Move> {
+        Ok(self.analyse(moves_chain, move_time_ms)?.best_move)
+    }
+
+    /// Move of the computer side, played with the given strength, with the score of the
+    /// position when known. The engine plays at full strength again afterwards, for the
+    /// hints and analyses.
+    pub fn computer_move(
+        &mut self,
+        moves_chain: &MoveChain,
+        move_time_ms: u64,
+        strength: &EngineStrength,
+    ) -> anyhow::Result<Analysis> {
+        if !strength.is_limited() {
+            return self.analyse(moves_chain, move_time_ms);
+        }
+
+        self.set_strength(strength)?;
+        let suboptimal = rand::thread_rng().gen_range(0..100) < strength.suboptimal_rate;
+        let computer_move = if suboptimal {
+            self.analyse_lines(moves_chain, move_time_ms, UciEngine::SUBOPTIMAL_LINES)
+                .map(|lines| Analysis {
+                    best_move: *get_result_preserving_moves(&lines)
+                        .choose(&mut rand::thread_rng())
+                        .unwrap_or(&lines[0].best_move),
+                    score: lines[0].score,
+                })
+        } else {
+            self.analyse(moves_chain, move_time_ms)
+        };
+        self.set_strength(&EngineStrength::default())?;
+        computer_move
+    }
+
+    pub fn input(&self) -> EngineInput {
+        self.input.clone()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn supports_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option == name)
+    }
+
+    fn set_strength(&mut self, strength: &EngineStrength) -> anyhow::Result<()> {
+        if self.supports_option("Skill Level") {
+            let skill_level = strength.skill_level.unwrap_or(20).min(20);
+            self.set_option("Skill Level", &skill_level.to_string())?;
+        }
+        if self.supports_option("UCI_LimitStrength") && self.supports_option("UCI_Elo") {
+            match strength.elo {
+                Some(elo) => {
+                    self.set_option("UCI_LimitStrength", "true")?;
+                    self.set_option("UCI_Elo", &elo.to_string())?;
+                }
+                None => self.set_option("UCI_LimitStrength", "false")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Searches the last position of the chain, the score being given
+    /// from the point of view of the side to move.
+    pub fn analyse(
+        &mut self,
+        moves_chain: &MoveChain,
+        move_time_ms: u64,
+    ) -> anyhow::Result<Analysis> {
+        self.set_position(moves_chain)?;
+        self.send_command(&format!("go movetime {}", move_time_ms))?;
+
+        let mut score = None;
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    if let Some(info_score) = parse_score(&line) {
+                        score = Some(info_score);
+                    }
+                }
+                Some("bestmove") => {
+                    let move_uci = tokens
+                        .next()
+                        .with_context(|| "Missing move in engine answer.")?;
+                    let best_move = Move::from_uci_legal(move_uci, moves_chain.last())
+                        .with_context(|| {
+                            format!("Engine played an illegal move : {}.", move_uci)
+                        })?;
+                    return Ok(Analysis { best_move, score });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Searches the given count of best lines of the last position of the chain, the best
+    /// first, the scores being given from the point of view of the side to move.
+    pub fn analyse_lines(
+        &mut self,
+        moves_chain: &MoveChain,
+        move_time_ms: u64,
+        lines_count: usize,
+    ) -> anyhow::Result<Vec<Analysis>> {
+        if !self.supports_option("MultiPV") {
+            return self.search_lines(moves_chain, move_time_ms, 1);
+        }
+        self.set_option("MultiPV", &lines_count.to_string())?;
+        let lines = self.search_lines(moves_chain, move_time_ms, lines_count);
+        self.set_option("MultiPV", "1")?;
+        lines
+    }
+
+    fn search_lines(
+        &mut self,
+        moves_chain: &MoveChain,
+        move_time_ms: u64,
+        lines_count: usize,
+    ) -> anyhow::Result<Vec<Analysis>> {
+        self.set_position(moves_chain)?;
+        self.send_command(&format!("go movetime {}", move_time_ms))?;
+
+        let mut lines: Vec<Option<Analysis>> = (0..lines_count).map(|_| None).collect();
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    if let Some((index, analysis)) = parse_line(&line, moves_chain) {
+                        if index < lines_count {
+                            lines[index] = Some(analysis);
+                        }
+                    }
+                }
+                Some("bestmove") => {
+                    let lines: Vec<Analysis> = lines.into_iter().flatten().collect();
+                    if lines.is_empty() {
+                        let move_uci = tokens
+                            .next()
+                            .with_context(|| "Missing move in engine answer.")?;
+                        let best_move = Move::from_uci_legal(move_uci, moves_chain.last())
+                            .with_context(|| {
+                                format!("Engine played an illegal move : {}.", move_uci)
+                            })?;
+                        return Ok(vec![Analysis {
+                            best_move,
+                            score: None,
+                        }]);
+                    }
+                    return Ok(lines);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Searches the last position of the chain until the `stop` command, the info
+    /// lines being read by the caller.
+    fn start_infinite_search(
+        &mut self,
+        moves_chain: &MoveChain,
+        lines_count: usize,
+    ) -> anyhow::Result<()> {
+        if self.supports_option("MultiPV") {
+            self.set_option("MultiPV", &lines_count.to_string())?;
+        }
+        self.set_position(moves_chain)?;
+        self.send_command("go infinite")
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        self.send_command(&format!("setoption name {} value {}", name, value))?;
+        self.wait_ready()
+    }
+
+    fn set_position(&mut self, moves_chain: &MoveChain) -> anyhow::Result<()> {
+        let start_fen = moves_chain.startpos().as_fen();
+        let command = if moves_chain.is_empty() {
+            format!("position fen {}", start_fen)
+        } else {
+            format!("position fen {} moves {}", start_fen, moves_chain.uci())
+        };
+        self.send_command(&command)?;
+        self.wait_ready()
+    }
+
+    fn wait_ready(&mut self) -> anyhow::Result<()> {
+        self.send_command("isready")?;
+        self.wait_for("readyok")?;
+        Ok(())
+    }
+
+    fn send_command(&mut self, command: &str) -> anyhow::Result<()> {
+        self.input.send(command)
+    }
+
+    fn wait_for(&mut self, expected_token: &str) -> anyhow::Result<String> {
+        loop {
+            let line = self.read_line()?;
+            if line.split_whitespace().next() == Some(expected_token) {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> anyhow::Result<String> {
+        let mut line = String::new();
+        let read_bytes = self
+            .output
+            .read_line(&mut line)
+            .with_context(|| "Failed to read engine output.")?;
+        if read_bytes == 0 {
+            return Err(anyhow::anyhow!("Engine process has terminated."));
+        }
+        Ok(line.trim().to_string())
+    }
+}
+
+fn parse_score(info_line: &str) -> Option<Score> {
+    let mut tokens = info_line
+        .split_whitespace()
+        .skip_while(|token| *token != "score");
+    tokens.next()?;
+    let kind = tokens.next()?;
+    let value = tokens.next()?.parse::<i32>().ok()?;
+    match kind {
+        "cp" => Some(Score::Centipawns(value)),
+        "mate" => Some(Score::Mate(value)),
+        _ => None,
+    }
+}
+
+/// Name of an option declared after the `uci` command (e.g. "Skill Level").
+fn parse_option_name(line: &str) -> Option<String> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("option") || tokens.next() != Some("name") {
+        return None;
+    }
+    let name: Vec<&str> = tokens.take_while(|token| *token != "type").collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.join(" "))
+    }
+}
+
+fn parse_engine_name(line: &str) -> Option<String> {
+    let name = line.strip_prefix("id name ")?.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+pub(crate) fn get_expected_result(score: Score) -> ExpectedResult {
+    const DECISIVE_CENTIPAWNS: i32 = 400;
+    match score {
+        Score::Mate(moves) if moves > 0 => ExpectedResult::Win,
+        Score::Mate(_) => ExpectedResult::Loss,
+        Score::Centipawns(centipawns) if centipawns >= DECISIVE_CENTIPAWNS => ExpectedResult::Win,
+        Score::Centipawns(centipawns) if centipawns <= -DECISIVE_CENTIPAWNS => ExpectedResult::Loss,
+        Score::Centipawns(_) => ExpectedResult::Draw,
+    }
+}
+
+/// The computer side agrees to a draw, offered or claimable, unless it expects to win.
+/// The score is given from the point of view of the side to move.
+pub fn accepts_draw(score: Option<Score>, computer_to_move: bool) -> bool {
+    let expected_result = match score {
+        Some(score) => get_expected_result(score),
+        None => return false,
+    };
+    let computer_result = if computer_to_move {
+        expected_result
+    } else {
+        expected_result.for_opponent()
+    };
+    computer_result != ExpectedResult::Win
+}
+
+/// The computer side, to move, offers a draw when it expects one.
+pub fn offers_draw(score: Option<Score>) -> bool {
+    score.map(get_expected_result) == Some(ExpectedResult::Draw)
+}
+
+/// Moves of the lines expecting the same result as the best one, which comes first.
+fn get_result_preserving_moves(lines: &[Analysis]) -> Vec<Move> {
+    let best_score = match lines.first().and_then(|line| line.score) {
+        Some(score) => score,
+        None => return lines.iter().take(1).map(|line| line.best_move).collect(),
+    };
+    lines
+        .iter()
+        .filter(|line| {
+            line.score
+                .is_some_and(|score| preserves_result(best_score, score))
+        })
+        .map(|line| line.best_move)
+        .collect()
+}
+
+/// A forced mate is only kept by another mate, and a drawn score only by a close one,
+/// as the whole draw band does not hold the same.
+fn preserves_result(best_score: Score, score: Score) -> bool {
+    const DRAW_MARGIN_CENTIPAWNS: i32 = 100;
+    match (best_score, score) {
+        (Score::Mate(best_moves), Score::Mate(moves)) => best_moves.signum() == moves.signum(),
+        (Score::Mate(_), Score::Centipawns(_)) => false,
+        (Score::Centipawns(best_centipawns), _) => {
+            let best_result = get_expected_result(best_score);
+            get_expected_result(score) == best_result
+                && match (best_result, score) {
+                    (ExpectedResult::Draw, Score::Centipawns(centipawns)) => {
+                        best_centipawns - centipawns <= DRAW_MARGIN_CENTIPAWNS
+                    }
+                    _ => true,
+                }
+        }
+    }
+}
+
+/// The variation of an info line, its moves being legal from the position.
+fn parse_pv_line(info_line: &str, position: &Board) -> Option<PvLine> {
+    let mut tokens = info_line
+        .split_whitespace()
+        .skip_while(|token| *token != "pv");
+    tokens.next()?;
+    let mut moves = vec![];
+    let mut board = position.clone();
+    for token in tokens {
+        let pv_move = match Move::from_uci_legal(token, &board) {
+            Ok(pv_move) => pv_move,
+            Err(_) => break,
+        };
+        board = match board.make_move(pv_move) {
+            Ok(next_board) => next_board,
+            Err(_) => break,
+        };
+        moves.push(pv_move);
+    }
+
+    let index = match get_info_value(info_line, "multipv") {
+        Some(value) => value.parse::<usize>().ok()?.checked_sub(1)?,
+        None => 0,
+    };
+    Some(PvLine {
+        index,
+        depth: get_info_value(info_line, "depth")?.parse().ok()?,
+        score: parse_score(info_line),
+        nodes_per_second: get_info_value(info_line, "nps").and_then(|value| value.parse().ok()),
+        moves,
+    })
+}
+
+/// Index (from 0) and content of a line of a multi-PV search.
+fn parse_line(info_line: &str, moves_chain: &MoveChain) -> Option<(usize, Analysis)> {
+    let index = match get_info_value(info_line, "multipv") {
+        Some(value) => value.parse::<usize>().ok()?.checked_sub(1)?,
+        None => 0,
+    };
+    let first_move = get_info_value(info_line, "pv")?;
+    let best_move = Move::from_uci_legal(first_move, moves_chain.last()).ok()?;
+    Some((
+        index,
+        Analysis {
+            best_move,
+            score: parse_score(info_line),
+        },
+    ))
+}
+
+/// The token following the given key of an info line.
+fn get_info_value<'a>(info_line: &'a str, key: &str) -> Option<&'a str> {
+    let mut tokens = info_line
+        .split_whitespace()
+        .skip_while(|token| *token != key);
+    tokens.next()?;
+    tokens.next()
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if self.send_command("quit").is_err() {
+            let _ = self.process.kill();
+        }
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlchess::Board;
+
+    fn line(board: &Board, uci: &str, score: Option<Score>) -> Analysis {
+        Analysis {
+            best_move: Move::from_uci_legal(uci, board).unwrap(),
+            score,
+        }
+    }
+
+    #[test]
+    fn options_names_are_read() {
+        assert_eq!(
+            parse_option_name("option name Skill Level type spin default 20 min 0 max 20"),
+            Some("Skill Level".to_string())
+        );
+        assert_eq!(
+            parse_option_name("option name UCI_Elo type spin default 1350 min 1350 max 2850"),
+            Some("UCI_Elo".to_string())
+        );
+        assert_eq!(parse_option_name("id name Stockfish"), None);
+        assert_eq!(parse_option_name("option name type check"), None);
+    }
+
+    #[test]
+    fn engine_name_is_read() {
+        assert_eq!(
+            parse_engine_name("id name Stockfish 16"),
+            Some("Stockfish 16".to_string())
+        );
+        assert_eq!(
+            parse_engine_name("id author the Stockfish developers"),
+            None
+        );
+        assert_eq!(parse_engine_name("id name "), None);
+    }
+
+    #[test]
+    fn variations_are_read_from_info_lines() {
+        let board = Board::from_fen("4k3/8/4K3/8/8/8/8/R7 w - - 0 1").unwrap();
+        let pv_line = parse_pv_line(
+            "info depth 18 seldepth 20 multipv 2 score mate 3 nodes 120000 nps 950000 pv a1a7 e8f8 a7b7 zz",
+            &board,
+        )
+        .unwrap();
+        assert_eq!(pv_line.index, 1);
+        assert_eq!(pv_line.depth, 18);
+        assert_eq!(pv_line.score, Some(Score::Mate(3)));
+        assert_eq!(pv_line.nodes_per_second, Some(950000));
+        let moves: Vec<String> = pv_line.moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(moves, vec!["a1a7", "e8f8", "a7b7"]);
+
+        assert!(parse_pv_line("info depth 5 currmove a1a2 currmovenumber 1", &board).is_none());
+        assert!(parse_pv_line("info string NNUE enabled", &board).is_none());
+    }
+
+    #[test]
+    fn only_moves_keeping_the_result_are_kept() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let lines = vec![
+            line(&board, "a1a7", Some(Score::Mate(9))),
+            line(&board, "e1d2", Some(Score::Mate(11))),
+            line(&board, "a1a8", Some(Score::Centipawns(0))),
+            line(&board, "e1f2", Some(Score::Centipawns(700))),
+        ];
+        let moves: Vec<String> = get_result_preserving_moves(&lines)
+            .iter()
+            .map(|kept| kept.to_string())
+            .collect();
+        assert_eq!(moves, vec!["a1a7", "e1d2"]);
+
+        let defender = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let lines = vec![
+            line(&defender, "e8d7", Some(Score::Mate(-12))),
+            line(&defender, "e8f7", Some(Score::Mate(-8))),
+        ];
+        assert_eq!(get_result_preserving_moves(&lines).len(), 2);
+
+        let lines = vec![line(&board, "a1a7", None)];
+        assert_eq!(get_result_preserving_moves(&lines).len(), 1);
+    }
+
+    #[test]
+    fn mates_and_draws_are_only_kept_by_close_scores() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let kept = |lines: &[Analysis]| -> Vec<String> {
+            get_result_preserving_moves(lines)
+                .iter()
+                .map(|kept| kept.to_string())
+                .collect()
+        };
+
+        let lines = vec![
+            line(&board, "a1a7", Some(Score::Centipawns(900))),
+            line(&board, "e1d2", Some(Score::Centipawns(450))),
+            line(&board, "a1a8", Some(Score::Centipawns(350))),
+        ];
+        assert_eq!(kept(&lines), vec!["a1a7", "e1d2"]);
+
+        let lines = vec![
+            line(&board, "a1a7", Some(Score::Centipawns(20))),
+            line(&board, "e1d2", Some(Score::Centipawns(-60))),
+            line(&board, "a1a8", Some(Score::Centipawns(-300))),
+            line(&board, "e1f2", Some(Score::Mate(-5))),
+        ];
+        assert_eq!(kept(&lines), vec!["a1a7", "e1d2"]);
+
+        let lines = vec![
+            line(&board, "a1a7", Some(Score::Mate(3))),
+            line(&board, "e1d2", Some(Score::Centipawns(2000))),
+            line(&board, "a1a8", Some(Score::Mate(7))),
+            line(&board, "e1f2", Some(Score::Centipawns(450))),
+        ];
+        assert_eq!(kept(&lines), vec!["a1a7", "a1a8"]);
+    }
+
+    #[test]
+    fn draws_are_accepted_unless_the_computer_expects_to_win() {
+        assert!(accepts_draw(Some(Score::Centipawns(30)), true));
+        assert!(accepts_draw(Some(Score::Mate(-4)), true));
+        assert!(!accepts_draw(Some(Score::Mate(4)), true));
+        assert!(!accepts_draw(Some(Score::Centipawns(-700)), false));
+        assert!(accepts_draw(Some(Score::Centipawns(700)), false));
+        assert!(!accepts_draw(None, true));
+
+        assert!(offers_draw(Some(Score::Centipawns(0))));
+        assert!(!offers_draw(Some(Score::Mate(-4))));
+        assert!(!offers_draw(None));
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use owlchess::MoveChain;
+
+use super::{Analysis, EngineInput, EngineStrength, UciEngine};
+
+/// What the search is asked for, given back with its answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineTask {
+    ComputerMove(EngineStrength),
+    Hint,
+    /// Mate length of the start position, the player being to move or not.
+    OptimumMoves {
+        player_turn: bool,
+    },
+    /// Score of the position, for the computer side to answer a draw offer of the player.
+    DrawOffer,
+    /// Score of a position of the reviewed game, given by its ply.
+    Review {
+        ply: usize,
+    },
+}
+
+pub struct EngineAnswer {
+    pub job_id: u64,
+    pub task: EngineTask,
+    pub result: anyhow::Result<Analysis>,
+}
+
+struct EngineJob {
+    id: u64,
+    moves_chain: MoveChain,
+    task: EngineTask,
+}
+
+/// Runs the engine searches in a thread, so that the caller is never blocked :
+/// the answers are given to a callback, from the worker thread.
+pub struct EngineWorker {
+    jobs: Option<mpsc::Sender<EngineJob>>,
+    input: EngineInput,
+    last_job_id: u64,
+    /// The jobs up to this one are cancelled : they are skipped, or their answer ignored.
+    cancelled_job_id: Arc<AtomicU64>,
+    name: Option<String>,
+}
+
+impl EngineWorker {
+    pub fn new<F>(path: &Path, on_answer: F) -> anyhow::Result<Self>
+    where
+        F: Fn(EngineAnswer) + Send + 'static,
+    {
+        let mut engine = UciEngine::new(path)?;
+        let input = engine.input();
+        let name = engine.name().map(str::to_string);
+        let cancelled_job_id = Arc::new(AtomicU64::new(0));
+        let (jobs, receiver) = mpsc::channel::<EngineJob>();
+
+        let thread_cancelled_job_id = cancelled_job_id.clone();
+        thread::spawn(move || {
+            for job in receiver {
+                if job.id <= thread_cancelled_job_id.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let result = run_job(&mut engine, &job);
+                on_answer(EngineAnswer {
+                    job_id: job.id,
+                    task: job.task,
+                    result,
+                });
+            }
+        });
+
+        Ok(Self {
+            jobs: Some(jobs),
+            input,
+            last_job_id: 0,
+            cancelled_job_id,
+            name,
+        })
+    }
+
+    /// The name given by the engine, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Queues the search, returns the id given back with its answer.
+    pub fn submit(&mut self, moves_chain: &MoveChain, task: EngineTask) -> anyhow::Result<u64> {
+        self.last_job_id += 1;
+        let job = EngineJob {
+            id: self.last_job_id,
+            moves_chain: moves_chain.clone(),
+            task,
+        };
+        self.jobs
+            .as_ref()
+            .and_then(|jobs| jobs.send(job).ok())
+            .ok_or_else(|| anyhow::anyhow!("Engine process has terminated."))?;
+        Ok(self.last_job_id)
+    }
+
+    /// Cancels all the jobs submitted so far, stopping the current search.
+    pub fn cancel_all(&mut self) {
+        self.cancelled_job_id
+            .store(self.last_job_id, Ordering::SeqCst);
+        let _ = self.input.stop_search();
+    }
+
+    /// The answer belongs to a job which has not been cancelled.
+    pub fn is_valid(&self, answer: &EngineAnswer) -> bool {
+        answer.job_id > self.cancelled_job_id.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for EngineWorker {
+    /// The thread ends, quitting the engine, once its current search is stopped.
+    fn drop(&mut self) {
+        self.cancel_all();
+        self.jobs = None;
+    }
+}
+
+fn run_job(engine: &mut UciEngine, job: &EngineJob) -> anyhow::Result<Analysis> {
+    match &job.task {
+        EngineTask::ComputerMove(strength) => {
+            engine.computer_move(&job.moves_chain, UciEngine::COMPUTER_MOVE_TIME_MS, strength)
+        }
+        EngineTask::Hint | EngineTask::DrawOffer => {
+            engine.analyse(&job.moves_chain, UciEngine::DEFAULT_MOVE_TIME_MS)
+        }
+        EngineTask::OptimumMoves { .. } => {
+            engine.analyse(&job.moves_chain, UciEngine::OPTIMUM_SEARCH_TIME_MS)
+        }
+        EngineTask::Review { .. } => {
+            engine.analyse(&job.moves_chain, UciEngine::REVIEW_SEARCH_TIME_MS)
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{self, Context};
+use owlchess::{Board, Move};
+
+/// A position of an EPD file, with the operations we use.
+#[derive(Clone)]
+pub struct EpdRecord {
+    pub position: Board,
+    pub id: Option<String>,
+    /// Moves of the `bm` operation.
+    pub best_moves: Vec<Move>,
+    /// Moves of the `am` operation.
+    pub avoid_moves: Vec<Move>,
+    /// The `c0` operation.
+    pub comment: Option<String>,
+    /// Moves count of the `dm` operation.
+    pub direct_mate: Option<u32>,
+}
+
+impl EpdRecord {
+    pub fn new(position: Board) -> Self {
+        Self {
+            position,
+            id: None,
+            best_moves: vec![],
+            avoid_moves: vec![],
+            comment: None,
+            direct_mate: None,
+        }
+    }
+
+    /// Reads a line : the four first fields of a FEN, then the operations,
+    /// each ending with a semicolon. Unknown operations are ignored.
+    pub fn parse(line: &str) -> anyhow::Result<Self> {
+        let fields: Vec<&str> = line.trim().splitn(5, char::is_whitespace).collect();
+        if fields.len() < 4 {
+            return Err(anyhow::anyhow!("Missing position fields."));
+        }
+        let fen = format!("{} 0 1", fields[..4].join(" "));
+        let position = Board::from_fen(&fen).with_context(|| format!("Bad position {}.", fen))?;
+        let mut record = EpdRecord::new(position);
+
+        let operations = fields.get(4).copied().unwrap_or_default();
+        for operation in split_operations(operations)? {
+            let (opcode, operands) = match operation.split_first() {
+                Some(parts) => parts,
+                None => continue,
+            };
+            match opcode.as_str() {
+                "id" => record.id = operands.first().cloned(),
+                "c0" => record.comment = operands.first().cloned(),
+                "bm" => record.best_moves = parse_moves(operands, &record.position)?,
+                "am" => record.avoid_moves = parse_moves(operands, &record.position)?,
+                "dm" => {
+                    let moves_count = operands.first().with_context(|| "Missing dm value.")?;
+                    record.direct_mate = Some(
+                        moves_count
+                            .parse()
+                            .with_context(|| format!("Bad dm value {}.", moves_count))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Writes the record as an EPD line, the moves in SAN.
+    pub fn to_line(&self) -> String {
+        let fen = self.position.as_fen();
+        let mut line = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+
+        if let Some(id) = &self.id {
+            line.push_str(&format!(" id {};", quote(id)));
+        }
+        if !self.best_moves.is_empty() {
+            line.push_str(&format!(" bm {};", self.format_moves(&self.best_moves)));
+        }
+        if !self.avoid_moves.is_empty() {
+            line.push_str(&format!(" am {};", self.format_moves(&self.avoid_moves)));
+        }
+        if let Some(direct_mate) = self.direct_mate {
+            line.push_str(&format!(" dm {};", direct_mate));
+        }
+        if let Some(comment) = &self.comment {
+            line.push_str(&format!(" c0 {};", quote(comment)));
+        }
+
+        line
+    }
+
+    fn format_moves(&self, moves: &[Move]) -> String {
+        moves
+            .iter()
+            .map(|epd_move| match epd_move.san(&self.position) {
+                Ok(san) => san.to_string(),
+                Err(_) => epd_move.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Reads all the records, skipping the empty lines.
+pub fn parse_epd(text: &str) -> anyhow::Result<Vec<EpdRecord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            EpdRecord::parse(line).with_context(|| format!("Bad EPD at line {}.", index + 1))
+        })
+        .collect()
+}
+
+pub fn read_epd(path: &Path) -> anyhow::Result<Vec<EpdRecord>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}.", path.display()))?;
+    parse_epd(&content)
+}
+
+pub fn write_epd(path: &Path, records: &[EpdRecord]) -> anyhow::Result<()> {
+    let content: String = records
+        .iter()
+        .map(|record| format!("{}\n", record.to_line()))
+        .collect();
+    fs::write(path, content).with_context(|| format!("Failed to write {}.", path.display()))
+}
+
+/// Splits the operations into their opcode and operands, removing the quotes.
+fn split_operations(operations: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut result = vec![];
+    let mut current_operation = vec![];
+    let mut current_token = String::new();
+    let mut chars = operations.chars();
+
+    while let Some(current) = chars.next() {
+        match current {
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(other) => quoted.push(other),
+                        None => return Err(anyhow::anyhow!("Missing closing quote.")),
+                    }
+                }
+                current_operation.push(quoted);
+            }
+            ';' => {
+                if !current_token.is_empty() {
+                    current_operation.push(std::mem::take(&mut current_token));
+                }
+                result.push(std::mem::take(&mut current_operation));
+            }
+            other if other.is_whitespace() => {
+                if !current_token.is_empty() {
+                    current_operation.push(std::mem::take(&mut current_token));
+                }
+            }
+            other => current_token.push(other),
+        }
+    }
+
+    if !current_token.is_empty() || !current_operation.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Missing semicolon after the last operation."
+        ));
+    }
+
+    Ok(result)
+}
+
+/// The moves are in SAN, but some suites use UCI notation.
+fn parse_moves(operands: &[String], position: &Board) -> anyhow::Result<Vec<Move>> {
+    operands
+        .iter()
+        .map(|operand| {
+            Move::from_san(operand, position)
+                .ok()
+                .or_else(|| Move::from_uci_legal(operand, position).ok())
+                .with_context(|| format!("Bad move {}.", operand))
+        })
+        .collect()
+}
+
+/// EPD strings cannot hold double quotes.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_are_parsed() {
+        let record = EpdRecord::parse(
+            "1K1k4/1P6/8/8/8/8/r7/2R5 w - - bm Rd1+; am Rc8+ c1c2; id \"Lucena 1\"; c0 \"Build a bridge; later\"; dm 12; acd 20;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            record.position.as_fen(),
+            "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1"
+        );
+        assert_eq!(record.id.as_deref(), Some("Lucena 1"));
+        assert_eq!(record.comment.as_deref(), Some("Build a bridge; later"));
+        assert_eq!(record.direct_mate, Some(12));
+        let best_moves: Vec<String> = record.best_moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(best_moves, vec!["c1d1"]);
+        let avoid_moves: Vec<String> = record.avoid_moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(avoid_moves, vec!["c1c8", "c1c2"]);
+    }
+
+    #[test]
+    fn position_without_operations_is_accepted() {
+        let record = EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 b - -").unwrap();
+        assert_eq!(record.position.as_fen(), "4k3/8/8/8/8/8/8/R3K3 b - - 0 1");
+        assert!(record.id.is_none());
+        assert!(record.best_moves.is_empty());
+    }
+
+    #[test]
+    fn bad_records_are_rejected() {
+        assert!(EpdRecord::parse("4k3/8/8/8 w").is_err());
+        assert!(EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 w - - bm Ra9;").is_err());
+        assert!(EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 w - - id \"open;").is_err());
+        assert!(EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 w - - bm Ra8").is_err());
+        assert!(EpdRecord::parse("4k3/8/8/8/8/8/8/R3K3 w - - dm many;").is_err());
+    }
+
+    #[test]
+    fn record_is_written_back() {
+        let line = "1K1k4/1P6/8/8/8/8/r7/2R5 w - - id \"Lucena 1\"; bm Rd1+; am Rc8+; dm 12; c0 \"Build a bridge\";";
+        let record = EpdRecord::parse(line).unwrap();
+        assert_eq!(record.to_line(), line);
+
+        let mut record = EpdRecord::new(Board::initial());
+        record.id = Some("Say \"hi\"".to_string());
+        assert_eq!(
+            record.to_line(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - id \"Say 'hi'\";"
+        );
+    }
+
+    #[test]
+    fn file_is_parsed_line_by_line() {
+        let records = parse_epd(
+            "4k3/8/8/8/8/8/8/R3K3 w - - id \"1\";\n\n4k3/8/8/8/8/8/8/R3K3 b - - id \"2\";\n",
+        )
+        .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].id.as_deref(), Some("2"));
+
+        let error = parse_epd("4k3/8/8/8/8/8/8/R3K3 w - -\nbad\n")
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", error).contains("line 2"));
+    }
+}
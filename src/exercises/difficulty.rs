@@ -0,0 +1,253 @@
+use std::sync::atomic::AtomicBool;
+
+use owlchess::{Board, Color, Coord, MoveChain};
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+use super::filter::{check_cancelled, generate_filtered_position, is_winning, GenerationFilter};
+use super::{build_generated_exercise, random_side, EndgameKind, Exercise};
+use crate::engine::{Analysis, Score, UciEngine};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn title(&self) -> String {
+        match self {
+            Difficulty::Easy => tr!("Easy"),
+            Difficulty::Medium => tr!("Medium"),
+            Difficulty::Hard => tr!("Hard"),
+        }
+    }
+
+    pub fn from_score(score: u32) -> Self {
+        if score < 35 {
+            Difficulty::Easy
+        } else if score < 65 {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+
+    /// Middle of the scores of the difficulty, to pick the closest position.
+    fn target_score(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 20,
+            Difficulty::Medium => 50,
+            Difficulty::Hard => 80,
+        }
+    }
+}
+
+/// What makes a position hard to win, for the side to move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DifficultyFactors {
+    /// Moves to mate, None if the engine found no mate.
+    pub mate_moves: Option<u32>,
+    /// Moves of the best line being the only winning one.
+    pub only_moves: u32,
+    /// Largest distance between two pieces of the side to move.
+    pub pieces_spread: u32,
+    pub kings_distance: u32,
+}
+
+impl DifficultyFactors {
+    const LONGEST_MATE: u32 = 25;
+    const MOST_ONLY_MOVES: u32 = 6;
+
+    /// From 0 to 100 : half of it for the mate length, 30 for the only moves,
+    /// and 10 for each of the distances.
+    pub fn score(&self) -> u32 {
+        let mate_score = self
+            .mate_moves
+            .unwrap_or(DifficultyFactors::LONGEST_MATE)
+            .min(DifficultyFactors::LONGEST_MATE)
+            * 50
+            / DifficultyFactors::LONGEST_MATE;
+        let only_moves_score = self.only_moves.min(DifficultyFactors::MOST_ONLY_MOVES) * 30
+            / DifficultyFactors::MOST_ONLY_MOVES;
+        let spread_score = self.pieces_spread.min(7) * 10 / 7;
+        let kings_score = self.kings_distance.min(7) * 10 / 7;
+        mate_score + only_moves_score + spread_score + kings_score
+    }
+}
+
+/// Plies of the best line searched for only moves.
+const BEST_LINE_PLIES: usize = 10;
+const SEARCH_TIME_MS: u64 = 100;
+/// Positions rated before giving the closest one to the difficulty asked.
+const RATING_ATTEMPTS: usize = 10;
+
+/// Follows the best line of the engine from the position, counting the moves of the
+/// side to move which are the only winning ones.
+pub fn rate_position(
+    position: &Board,
+    engine: &mut UciEngine,
+) -> anyhow::Result<DifficultyFactors> {
+    let attacker = position.side();
+    let mut chain = MoveChain::new(position.clone());
+    let mut factors = DifficultyFactors {
+        pieces_spread: get_pieces_spread(position, attacker),
+        kings_distance: get_distance(
+            position.king_pos(Color::White),
+            position.king_pos(Color::Black),
+        ),
+        ..DifficultyFactors::default()
+    };
+
+    for ply in 0..BEST_LINE_PLIES {
+        if !chain.last().has_legal_moves() {
+            break;
+        }
+        let attacker_turn = chain.last().side() == attacker;
+        let lines_count = if attacker_turn { 2 } else { 1 };
+        let lines = engine.analyse_lines(&chain, SEARCH_TIME_MS, lines_count)?;
+        if ply == 0 {
+            factors.mate_moves = match lines[0].score {
+                Some(Score::Mate(moves)) if moves > 0 => Some(moves as u32),
+                _ => None,
+            };
+        }
+        if attacker_turn && is_only_move(&lines) {
+            factors.only_moves += 1;
+        }
+        chain
+            .push(lines[0].best_move)
+            .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?;
+    }
+
+    Ok(factors)
+}
+
+/// The best line wins, and the second one does not (or there is no other move).
+fn is_only_move(lines: &[Analysis]) -> bool {
+    let best_wins = lines[0].score.is_some_and(is_winning);
+    let second_wins = lines
+        .get(1)
+        .and_then(|line| line.score)
+        .is_some_and(is_winning);
+    best_wins && !second_wins
+}
+
+fn get_pieces_spread(position: &Board, side: Color) -> u32 {
+    let cells: Vec<Coord> = position.color(side).into_iter().collect();
+    cells
+        .iter()
+        .flat_map(|first| {
+            cells
+                .iter()
+                .map(move |second| get_distance(*first, *second))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Moves a king would need between the cells.
+fn get_distance(first: Coord, second: Coord) -> u32 {
+    let files = (first.file().index() as i32 - second.file().index() as i32).unsigned_abs();
+    let ranks = (first.rank().index() as i32 - second.rank().index() as i32).unsigned_abs();
+    files.max(ranks)
+}
+
+/// Rates winning positions of the endgame until one has the difficulty asked, or else
+/// gives the closest one. The exercise holds its difficulty score.
+/// Fails as soon as `cancelled` is set.
+pub fn generate_rated_exercise(
+    kind: EndgameKind,
+    difficulty: Difficulty,
+    filter: &GenerationFilter,
+    engine: &mut UciEngine,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<Exercise> {
+    let player_side = random_side();
+    let (player_pieces, defender_pieces) = kind.pieces();
+    let mut closest: Option<(Board, u32)> = None;
+
+    for _ in 0..RATING_ATTEMPTS {
+        let position = generate_filtered_position(
+            player_pieces,
+            defender_pieces,
+            player_side,
+            filter,
+            engine,
+            cancelled,
+        )?;
+        check_cancelled(cancelled)?;
+        let score = rate_position(&position, engine)?.score();
+        if Difficulty::from_score(score) == difficulty {
+            closest = Some((position, score));
+            break;
+        }
+        let is_closer = match &closest {
+            Some((_, closest_score)) => {
+                score.abs_diff(difficulty.target_score())
+                    < closest_score.abs_diff(difficulty.target_score())
+            }
+            None => true,
+        };
+        if is_closer {
+            closest = Some((position, score));
+        }
+    }
+
+    let (start_position, score) =
+        closest.ok_or_else(|| anyhow::anyhow!("No position has been generated."))?;
+    let mut exercise = build_generated_exercise(kind, start_position, player_side);
+    exercise.difficulty_score = Some(score);
+    Ok(exercise)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_grows_with_the_factors() {
+        let easy = DifficultyFactors {
+            mate_moves: Some(2),
+            only_moves: 0,
+            pieces_spread: 1,
+            kings_distance: 2,
+        };
+        let hard = DifficultyFactors {
+            mate_moves: Some(20),
+            only_moves: 4,
+            pieces_spread: 6,
+            kings_distance: 6,
+        };
+        assert_eq!(Difficulty::from_score(easy.score()), Difficulty::Easy);
+        assert_eq!(Difficulty::from_score(hard.score()), Difficulty::Hard);
+
+        let longest = DifficultyFactors {
+            mate_moves: None,
+            only_moves: 10,
+            pieces_spread: 7,
+            kings_distance: 7,
+        };
+        assert_eq!(longest.score(), 100);
+        // Without mate found, the position counts as a longest one.
+        assert_eq!(DifficultyFactors::default().score(), 50);
+    }
+
+    #[test]
+    fn distances_are_counted_in_king_moves() {
+        let position = Board::from_fen("k7/8/8/8/8/8/1Q6/4K2R w - - 0 1").unwrap();
+        assert_eq!(get_pieces_spread(&position, Color::White), 6);
+        assert_eq!(get_pieces_spread(&position, Color::Black), 0);
+        assert_eq!(
+            get_distance(
+                position.king_pos(Color::White),
+                position.king_pos(Color::Black)
+            ),
+            7
+        );
+    }
+}
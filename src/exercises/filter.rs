@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use owlchess::{movegen::legal, Board, Color, MoveChain};
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+use super::{build_generated_exercise, generate_position, random_side, EndgameKind, Exercise};
+use crate::engine::{Score, UciEngine};
+
+/// Keeps only the generated positions the engine finds instructive : the side to move
+/// must be winning, without leaving a stalemate to the opponent on its first move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerationFilter {
+    /// Difficulty band, in moves to mate : a mate must be found within it when given.
+    #[serde(default)]
+    pub min_mate_moves: Option<u32>,
+    #[serde(default)]
+    pub max_mate_moves: Option<u32>,
+    /// Only one move must keep the win.
+    #[serde(default)]
+    pub unique_winning_move: bool,
+}
+
+impl GenerationFilter {
+    pub const SEARCH_TIME_MS: u64 = 300;
+    pub const MAX_ATTEMPTS: usize = 100;
+    /// Difficulty bands proposed in the new game dialog, in moves to mate.
+    pub const MATE_BANDS: [(Option<u32>, Option<u32>); 4] = [
+        (None, None),
+        (Some(1), Some(10)),
+        (Some(10), Some(20)),
+        (Some(20), None),
+    ];
+
+    /// Scores above this one are counted as winning when no mate is found.
+    const WINNING_CENTIPAWNS: i32 = 400;
+
+    /// Such as "Mate in 10 to 20 moves".
+    pub fn title(&self) -> String {
+        let band = match (self.min_mate_moves, self.max_mate_moves) {
+            (None, None) => tr!("Winning positions"),
+            (Some(min), Some(max)) => tr!("Mate in {} to {} moves", min, max),
+            (Some(min), None) => tr!("Mate in {} moves or more", min),
+            (None, Some(max)) => tr!("Mate in {} moves or less", max),
+        };
+        if self.unique_winning_move {
+            tr!("{}, with a single winning move", band)
+        } else {
+            band
+        }
+    }
+
+    pub fn accepts(&self, position: &Board, engine: &mut UciEngine) -> anyhow::Result<bool> {
+        if has_stalemate_trick(position) {
+            return Ok(false);
+        }
+        let lines_count = if self.unique_winning_move { 2 } else { 1 };
+        let chain = MoveChain::new(position.clone());
+        let scores: Vec<Option<Score>> = engine
+            .analyse_lines(&chain, GenerationFilter::SEARCH_TIME_MS, lines_count)?
+            .into_iter()
+            .map(|line| line.score)
+            .collect();
+        Ok(self.accepts_scores(&scores))
+    }
+
+    /// Scores of the best lines, the best first, from the point of view of the side to move.
+    fn accepts_scores(&self, scores: &[Option<Score>]) -> bool {
+        let best_score = match scores.first() {
+            Some(Some(score)) => *score,
+            _ => return false,
+        };
+        if !is_winning(best_score) {
+            return false;
+        }
+
+        if self.min_mate_moves.is_some() || self.max_mate_moves.is_some() {
+            let mate_moves = match best_score {
+                Score::Mate(moves) => moves as u32,
+                Score::Centipawns(_) => return false,
+            };
+            if self.min_mate_moves.is_some_and(|min| mate_moves < min)
+                || self.max_mate_moves.is_some_and(|max| mate_moves > max)
+            {
+                return false;
+            }
+        }
+
+        if self.unique_winning_move {
+            if let Some(Some(second_score)) = scores.get(1) {
+                if is_winning(*second_score) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+pub(super) fn is_winning(score: Score) -> bool {
+    match score {
+        Score::Mate(moves) => moves > 0,
+        Score::Centipawns(centipawns) => centipawns >= GenerationFilter::WINNING_CENTIPAWNS,
+    }
+}
+
+/// Some legal move of the side to move leaves the opponent without legal move, but not in check.
+pub fn has_stalemate_trick(position: &Board) -> bool {
+    legal::gen_all(position)
+        .iter()
+        .any(|legal_move| match position.make_move(*legal_move) {
+            Ok(next_position) => !next_position.has_legal_moves() && !next_position.is_check(),
+            Err(_) => false,
+        })
+}
+
+/// Generates positions until the filter accepts one, failing as soon as `cancelled` is set.
+pub fn generate_filtered_position(
+    side_pieces: &str,
+    opponent_pieces: &str,
+    side: Color,
+    filter: &GenerationFilter,
+    engine: &mut UciEngine,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<Board> {
+    for _ in 0..GenerationFilter::MAX_ATTEMPTS {
+        check_cancelled(cancelled)?;
+        let position = generate_position(side_pieces, opponent_pieces, side)?;
+        if filter.accepts(&position, engine)? {
+            return Ok(position);
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No position matching the filter found in {} attempts.",
+        GenerationFilter::MAX_ATTEMPTS
+    ))
+}
+
+pub fn generate_filtered_exercise(
+    kind: EndgameKind,
+    filter: &GenerationFilter,
+    engine: &mut UciEngine,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<Exercise> {
+    let player_side = random_side();
+    let (player_pieces, defender_pieces) = kind.pieces();
+    let start_position = generate_filtered_position(
+        player_pieces,
+        defender_pieces,
+        player_side,
+        filter,
+        engine,
+        cancelled,
+    )?;
+    Ok(build_generated_exercise(kind, start_position, player_side))
+}
+
+/// Fails when the generation has been cancelled, to be called between two attempts.
+pub fn check_cancelled(cancelled: &AtomicBool) -> anyhow::Result<()> {
+    if cancelled.load(Ordering::SeqCst) {
+        Err(anyhow::anyhow!("The generation has been cancelled."))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stalemate_tricks_are_found() {
+        let trick = Board::from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        assert!(has_stalemate_trick(&trick));
+
+        let no_trick = Board::from_fen("k7/8/8/3K4/8/8/8/7R w - - 0 1").unwrap();
+        assert!(!has_stalemate_trick(&no_trick));
+    }
+
+    #[test]
+    fn cancelled_generation_stops_before_any_attempt() {
+        let cancelled = AtomicBool::new(false);
+        assert!(check_cancelled(&cancelled).is_ok());
+        cancelled.store(true, Ordering::SeqCst);
+        assert!(check_cancelled(&cancelled).is_err());
+    }
+
+    #[test]
+    fn filters_are_titled_by_their_mate_band() {
+        let mut filter = GenerationFilter::default();
+        assert_eq!(filter.title(), "Winning positions");
+        filter.min_mate_moves = Some(10);
+        assert_eq!(filter.title(), "Mate in 10 moves or more");
+        filter.max_mate_moves = Some(20);
+        filter.unique_winning_move = true;
+        assert_eq!(
+            filter.title(),
+            "Mate in 10 to 20 moves, with a single winning move"
+        );
+    }
+
+    #[test]
+    fn only_winning_positions_are_accepted() {
+        let filter = GenerationFilter::default();
+        assert!(filter.accepts_scores(&[Some(Score::Mate(8))]));
+        assert!(filter.accepts_scores(&[Some(Score::Centipawns(900))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Centipawns(50))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Mate(-3))]));
+        assert!(!filter.accepts_scores(&[None]));
+        assert!(!filter.accepts_scores(&[]));
+    }
+
+    #[test]
+    fn mate_length_must_be_in_the_band() {
+        let filter = GenerationFilter {
+            min_mate_moves: Some(5),
+            max_mate_moves: Some(10),
+            unique_winning_move: false,
+        };
+        assert!(filter.accepts_scores(&[Some(Score::Mate(5))]));
+        assert!(filter.accepts_scores(&[Some(Score::Mate(10))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Mate(4))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Mate(11))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Centipawns(900))]));
+    }
+
+    #[test]
+    fn unique_winning_move_is_checked_on_the_second_line() {
+        let filter = GenerationFilter {
+            unique_winning_move: true,
+            ..GenerationFilter::default()
+        };
+        assert!(filter.accepts_scores(&[Some(Score::Mate(6)), Some(Score::Centipawns(0))]));
+        assert!(filter.accepts_scores(&[Some(Score::Mate(6))]));
+        assert!(!filter.accepts_scores(&[Some(Score::Mate(6)), Some(Score::Mate(9))]));
+    }
+}
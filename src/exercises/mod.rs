@@ -0,0 +1,238 @@
+use owlchess::{Board, Cell, Color, Coord, Outcome, Piece, RawBoard};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+use crate::engine::EngineStrength;
+
+pub mod difficulty;
+pub mod filter;
+
+const MAX_PLACEMENT_ATTEMPTS: u32 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EndgameKind {
+    QueenVsKing,
+    RookVsKing,
+    TwoBishopsVsKing,
+    BishopKnightVsKing,
+    QueenVsRook,
+}
+
+impl EndgameKind {
+    pub const ALL: [EndgameKind; 5] = [
+        EndgameKind::QueenVsKing,
+        EndgameKind::RookVsKing,
+        EndgameKind::TwoBishopsVsKing,
+        EndgameKind::BishopKnightVsKing,
+        EndgameKind::QueenVsRook,
+    ];
+
+    pub fn title(&self) -> String {
+        match self {
+            EndgameKind::QueenVsKing => tr!("King and queen against king"),
+            EndgameKind::RookVsKing => tr!("King and rook against king"),
+            EndgameKind::TwoBishopsVsKing => tr!("King and two bishops against king"),
+            EndgameKind::BishopKnightVsKing => {
+                tr!("King, bishop and knight against king")
+            }
+            EndgameKind::QueenVsRook => tr!("King and queen against king and rook"),
+        }
+    }
+
+    /// Pieces of the side played by the user, then pieces of the defending side.
+    pub fn pieces(&self) -> (&'static str, &'static str) {
+        match self {
+            EndgameKind::QueenVsKing => ("KQ", "K"),
+            EndgameKind::RookVsKing => ("KR", "K"),
+            EndgameKind::TwoBishopsVsKing => ("KBB", "K"),
+            EndgameKind::BishopKnightVsKing => ("KBN", "K"),
+            EndgameKind::QueenVsRook => ("KQ", "KR"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    #[default]
+    Win,
+    Draw,
+}
+
+impl Goal {
+    pub fn is_reached(&self, outcome: &Outcome, player_side: Color) -> bool {
+        match self {
+            Goal::Win => outcome.winner() == Some(player_side),
+            Goal::Draw => outcome.winner() != Some(player_side.inv()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Exercise {
+    /// None for the exercises which are not one of the built-in endgames.
+    pub kind: Option<EndgameKind>,
+    pub start_position: Board,
+    pub player_side: Color,
+    pub goal: Goal,
+    pub hints: Vec<String>,
+    /// Moves in SAN, one of them being expected as first move of the player.
+    pub best_moves: Vec<String>,
+    /// Moves in SAN the player should not start with.
+    pub avoid_moves: Vec<String>,
+    /// From 0 (easiest) to 100, when the position has been rated.
+    pub difficulty_score: Option<u32>,
+    /// None to use the strength of the settings.
+    pub computer_strength: Option<EngineStrength>,
+}
+
+pub fn generate_exercise(kind: EndgameKind) -> anyhow::Result<Exercise> {
+    let player_side = random_side();
+    let (player_pieces, defender_pieces) = kind.pieces();
+    let start_position = generate_position(player_pieces, defender_pieces, player_side)?;
+    Ok(build_generated_exercise(kind, start_position, player_side))
+}
+
+fn build_generated_exercise(
+    kind: EndgameKind,
+    start_position: Board,
+    player_side: Color,
+) -> Exercise {
+    Exercise {
+        kind: Some(kind),
+        start_position,
+        player_side,
+        goal: Goal::Win,
+        hints: vec![],
+        best_moves: vec![],
+        avoid_moves: vec![],
+        difficulty_score: None,
+        computer_strength: None,
+    }
+}
+
+pub fn random_side() -> Color {
+    if rand::thread_rng().gen_bool(0.5) {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
+/// Randomly places the given pieces, the side to move being `side`,
+/// until the position is legal, not in check and not already over.
+/// Fails after `MAX_PLACEMENT_ATTEMPTS` placements, when the pieces can hardly give such a position.
+pub fn generate_position(
+    side_pieces: &str,
+    opponent_pieces: &str,
+    side: Color,
+) -> anyhow::Result<Board> {
+    let mut rng = rand::thread_rng();
+    let mut squares: Vec<usize> = (0..64).collect();
+
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+        squares.shuffle(&mut rng);
+
+        let mut raw = RawBoard::empty();
+        raw.side = side;
+
+        let pieces = side_pieces
+            .chars()
+            .map(|piece| (side, piece))
+            .chain(opponent_pieces.chars().map(|piece| (side.inv(), piece)));
+
+        for ((color, piece), square) in pieces.zip(squares.iter()) {
+            raw.put(
+                Coord::from_index(*square),
+                Cell::from_parts(color, parse_piece(piece)),
+            );
+        }
+
+        if !has_valid_bishops(&raw) {
+            continue;
+        }
+
+        if let Ok(board) = Board::try_from(raw) {
+            if !board.is_check() && board.calc_outcome().is_none() {
+                return Ok(board);
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Failed to place {} against {} in a playable position.",
+        side_pieces,
+        opponent_pieces
+    ))
+}
+
+/// Whether the pieces of both sides can ever give a mate : the only non king piece
+/// must not be a lone knight or bishop.
+pub fn has_sufficient_material(side_pieces: &str, opponent_pieces: &str) -> bool {
+    let mut non_king_pieces = side_pieces
+        .chars()
+        .chain(opponent_pieces.chars())
+        .map(|piece| piece.to_ascii_uppercase())
+        .filter(|piece| *piece != 'K');
+    !matches!(
+        (non_king_pieces.next(), non_king_pieces.next()),
+        (None, _) | (Some('N' | 'B'), None)
+    )
+}
+
+pub fn parse_piece(piece: char) -> Piece {
+    match piece.to_ascii_uppercase() {
+        'P' => Piece::Pawn,
+        'N' => Piece::Knight,
+        'B' => Piece::Bishop,
+        'R' => Piece::Rook,
+        'Q' => Piece::Queen,
+        'K' => Piece::King,
+        _ => panic!("Forbidden piece value : {}.", piece),
+    }
+}
+
+/// Two bishops of the same side must stand on cells of different colors.
+fn has_valid_bishops(raw: &RawBoard) -> bool {
+    for color in [Color::White, Color::Black] {
+        let bishop = Cell::from_parts(color, Piece::Bishop);
+        let bishops_cells_colors: Vec<usize> = (0..64)
+            .map(Coord::from_index)
+            .filter(|coord| raw.get(*coord) == bishop)
+            .map(|coord| (coord.file().index() + coord.rank().index()) % 2)
+            .collect();
+        if bishops_cells_colors.len() == 2 && bishops_cells_colors[0] == bishops_cells_colors[1] {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lone_minor_pieces_can_not_mate() {
+        assert!(!has_sufficient_material("K", "K"));
+        assert!(!has_sufficient_material("KB", "K"));
+        assert!(!has_sufficient_material("K", "KN"));
+        assert!(has_sufficient_material("KBN", "K"));
+        assert!(has_sufficient_material("KB", "KN"));
+        assert!(has_sufficient_material("KP", "K"));
+    }
+
+    #[test]
+    fn generated_positions_are_playable() {
+        let board = generate_position("KBN", "K", Color::Black).unwrap();
+        assert_eq!(board.side(), Color::Black);
+        assert!(!board.is_check());
+        assert!(board.calc_outcome().is_none());
+    }
+
+    #[test]
+    fn generation_gives_up_on_drawn_material() {
+        assert!(generate_position("KB", "K", Color::White).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// The thinking time left to a side, counted down while the clock runs.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    /// The time left when the clock was last stopped.
+    remaining: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(time: Duration) -> Self {
+        Self {
+            remaining: time,
+            running_since: None,
+        }
+    }
+
+    /// Does nothing if the clock is already running.
+    pub fn start(&mut self, now: Instant) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+        }
+    }
+
+    /// Does nothing if the clock is already stopped.
+    pub fn stop(&mut self, now: Instant) {
+        self.remaining = self.remaining(now);
+        self.running_since = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        match self.running_since {
+            Some(since) => self
+                .remaining
+                .saturating_sub(now.saturating_duration_since(since)),
+            None => self.remaining,
+        }
+    }
+
+    pub fn is_out(&self, now: Instant) -> bool {
+        self.remaining(now).is_zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_only_counts_the_running_time() {
+        let start = Instant::now();
+        let mut clock = Clock::new(Duration::from_secs(10));
+        assert!(!clock.is_running());
+        assert_eq!(
+            clock.remaining(start + Duration::from_secs(5)),
+            Duration::from_secs(10)
+        );
+
+        clock.start(start);
+        clock.start(start + Duration::from_secs(2));
+        assert!(clock.is_running());
+        assert_eq!(
+            clock.remaining(start + Duration::from_secs(4)),
+            Duration::from_secs(6)
+        );
+
+        clock.stop(start + Duration::from_secs(4));
+        clock.stop(start + Duration::from_secs(8));
+        assert_eq!(
+            clock.remaining(start + Duration::from_secs(20)),
+            Duration::from_secs(6)
+        );
+
+        clock.start(start + Duration::from_secs(20));
+        assert!(!clock.is_out(start + Duration::from_secs(25)));
+        assert!(clock.is_out(start + Duration::from_secs(26)));
+        assert_eq!(
+            clock.remaining(start + Duration::from_secs(30)),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,902 @@
+use std::time::{Duration, Instant};
+
+use owlchess::moves::{uci, PromotePiece};
+use owlchess::types::OutcomeFilter;
+use owlchess::{
+    Board, Color, Coord, DrawReason, File, Move, MoveChain, Outcome, Piece, Rank, WinReason,
+};
+use tr::tr;
+
+use self::clock::Clock;
+use self::tree::{GameTree, NodeId};
+
+pub mod clock;
+pub mod review;
+pub mod tree;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Idle,
+    InProgress,
+    Over(Outcome),
+    Stopped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    Started,
+    MovePlayed {
+        san: String,
+        white_player: bool,
+    },
+    /// The side ran out of time, the game being over just after.
+    TimeUp(Color),
+    Over(Outcome),
+    Stopped,
+    Resumed,
+}
+
+/// The state of a game, independent of any widget : the owner plays the moves,
+/// then takes the events to show them.
+pub struct GameSession {
+    board: Board,
+    /// The moves from the start position to the current node of the tree.
+    moves_chain: MoveChain,
+    tree: GameTree,
+    current_node: NodeId,
+    /// The node the game stands at : the other nodes are only browsed, until a move
+    /// is played from one of them.
+    game_node: NodeId,
+    player_side: Color,
+    /// Only given when the thinking time of the player is limited.
+    player_clock: Option<Clock>,
+    status: GameStatus,
+    events: Vec<GameEvent>,
+}
+
+impl Default for GameSession {
+    fn default() -> Self {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        Self {
+            moves_chain: MoveChain::new(board.clone()),
+            tree: GameTree::new(board.clone()),
+            current_node: GameTree::ROOT,
+            game_node: GameTree::ROOT,
+            board,
+            player_side: Color::White,
+            player_clock: None,
+            status: GameStatus::Idle,
+            events: vec![],
+        }
+    }
+}
+
+impl GameSession {
+    pub fn start(&mut self, start_position: Board, player_side: Color) {
+        self.moves_chain = MoveChain::new(start_position.clone());
+        self.tree = GameTree::new(start_position.clone());
+        self.current_node = GameTree::ROOT;
+        self.game_node = GameTree::ROOT;
+        self.board = start_position;
+        self.player_side = player_side;
+        self.player_clock = None;
+        self.status = GameStatus::InProgress;
+        self.events.push(GameEvent::Started);
+    }
+
+    /// Gives the player this time for the rest of the game, their clock only
+    /// running on their turn.
+    pub fn limit_player_time(&mut self, time: Duration) {
+        self.player_clock = Some(Clock::new(time));
+        self.update_player_clock();
+    }
+
+    pub fn player_time_left(&self, now: Instant) -> Option<Duration> {
+        self.player_clock.map(|clock| clock.remaining(now))
+    }
+
+    /// Starts from the end of the main line of the tree, as if its moves had been played.
+    pub fn load(&mut self, tree: GameTree, player_side: Color) -> anyhow::Result<()> {
+        let end = tree.main_line_end();
+        self.start(tree.position(GameTree::ROOT).clone(), player_side);
+        self.tree = tree;
+        self.go_to(end)?;
+        self.game_node = end;
+        self.update_status()
+    }
+
+    /// Returns false if there was no game in progress.
+    pub fn stop(&mut self) -> bool {
+        if !self.is_in_progress() {
+            return false;
+        }
+        self.status = GameStatus::Stopped;
+        self.events.push(GameEvent::Stopped);
+        self.update_player_clock();
+        true
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn moves_chain(&self) -> &MoveChain {
+        &self.moves_chain
+    }
+
+    pub fn tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    pub fn current_node(&self) -> NodeId {
+        self.current_node
+    }
+
+    /// The shown position is not the one the game stands at.
+    pub fn is_browsing(&self) -> bool {
+        self.current_node != self.game_node
+    }
+
+    /// Browsing a position where the computer side is to move : no move can be played
+    /// from it, the computer only moving from the position the game stands at.
+    pub fn is_input_locked(&self) -> bool {
+        self.is_browsing() && !self.is_player_turn()
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.board.side()
+    }
+
+    pub fn player_side(&self) -> Color {
+        self.player_side
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.status() == GameStatus::InProgress
+    }
+
+    pub fn is_player_turn(&self) -> bool {
+        self.side_to_move() == self.player_side()
+    }
+
+    /// The legal move between the given squares, if any (ranks and files from 0 to 7).
+    pub fn move_from_squares(
+        &self,
+        start_file: u8,
+        start_rank: u8,
+        target_file: u8,
+        target_rank: u8,
+        promotion: Option<char>,
+    ) -> Option<Move> {
+        let uci_move =
+            get_uci_move_for(start_file, start_rank, target_file, target_rank, promotion);
+        let matching_move = uci_move.into_move(&self.board).ok()?;
+        self.board.make_move(matching_move).ok()?;
+        Some(matching_move)
+    }
+
+    /// Plays the move if it is legal, then checks whether the game is over.
+    /// Played from a previous position, the move starts a variation.
+    pub fn play_move(&mut self, move_to_play: Move) -> anyhow::Result<String> {
+        if !self.is_in_progress() {
+            return Err(anyhow::anyhow!("No game in progress."));
+        }
+
+        let node = self.tree.add_move(self.current_node, move_to_play)?;
+        self.moves_chain
+            .push(move_to_play)
+            .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?;
+        self.board = self.tree.position(node).clone();
+        self.current_node = node;
+        self.game_node = node;
+        let san = self.tree.san(node).to_string();
+
+        let white_player = self.board.side() == Color::Black;
+        self.events.push(GameEvent::MovePlayed {
+            san: san.clone(),
+            white_player,
+        });
+
+        if let Some(outcome) = self.calc_outcome() {
+            self.status = GameStatus::Over(outcome);
+            self.events.push(GameEvent::Over(outcome));
+        }
+        self.update_player_clock();
+
+        Ok(san)
+    }
+
+    /// Shows the position of the node, without changing the status of the game : a finished
+    /// game is only browsed, while a game in progress goes on from the node if the player
+    /// moves there.
+    pub fn go_to(&mut self, node: NodeId) -> anyhow::Result<()> {
+        if !matches!(self.status, GameStatus::InProgress | GameStatus::Over(_)) {
+            return Err(anyhow::anyhow!("No game to browse."));
+        }
+        self.moves_chain = self.tree.moves_chain(node)?;
+        self.board = self.tree.position(node).clone();
+        self.current_node = node;
+        self.update_player_clock();
+        Ok(())
+    }
+
+    /// The draw the side to move may claim (threefold repetition or 50 moves), if any.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if !self.is_in_progress() {
+            return None;
+        }
+        match self.moves_chain.calc_outcome()? {
+            Outcome::Draw(reason @ (DrawReason::Repeat3 | DrawReason::Moves50)) => Some(reason),
+            _ => None,
+        }
+    }
+
+    pub fn claim_draw(&mut self) -> anyhow::Result<Outcome> {
+        let reason = self
+            .claimable_draw()
+            .ok_or_else(|| anyhow::anyhow!("No draw can be claimed."))?;
+        self.declare_outcome(Outcome::Draw(reason))
+    }
+
+    /// The draw offered by a side has been accepted by the other one.
+    pub fn agree_draw(&mut self) -> anyhow::Result<Outcome> {
+        self.declare_outcome(Outcome::Draw(DrawReason::Agreement))
+    }
+
+    /// The player resigns, the computer side winning.
+    pub fn resign(&mut self) -> anyhow::Result<Outcome> {
+        self.declare_outcome(Outcome::Win {
+            side: self.player_side.inv(),
+            reason: WinReason::Resign,
+        })
+    }
+
+    /// Ends the game on time if the player clock is out.
+    pub fn check_time(&mut self, now: Instant) -> Option<Outcome> {
+        let clock = self.player_clock?;
+        if !self.is_in_progress() || !clock.is_out(now) {
+            return None;
+        }
+        self.forfeit_on_time(self.player_side).ok()
+    }
+
+    /// The side ran out of time : it loses, unless its opponent could not checkmate it anymore.
+    pub fn forfeit_on_time(&mut self, side: Color) -> anyhow::Result<Outcome> {
+        if !self.is_in_progress() {
+            return Err(anyhow::anyhow!("No game in progress."));
+        }
+        let outcome = if has_mating_material(&self.board, side.inv()) {
+            Outcome::Win {
+                side: side.inv(),
+                reason: WinReason::TimeForfeit,
+            }
+        } else {
+            Outcome::Draw(DrawReason::InsufficientMaterial)
+        };
+        self.events.push(GameEvent::TimeUp(side));
+        self.declare_outcome(outcome)
+    }
+
+    /// Ends the game whatever its position, until the move before the end is retried.
+    fn declare_outcome(&mut self, outcome: Outcome) -> anyhow::Result<Outcome> {
+        if !self.is_in_progress() {
+            return Err(anyhow::anyhow!("No game in progress."));
+        }
+        self.tree.set_declared_outcome(Some(outcome));
+        self.status = GameStatus::Over(outcome);
+        self.events.push(GameEvent::Over(outcome));
+        self.update_player_clock();
+        Ok(outcome)
+    }
+
+    /// The player clock only runs on their turn of a game in progress.
+    fn update_player_clock(&mut self) {
+        let running = self.is_in_progress() && self.is_player_turn();
+        if let Some(clock) = self.player_clock.as_mut() {
+            let now = Instant::now();
+            if running {
+                clock.start(now);
+            } else {
+                clock.stop(now);
+            }
+        }
+    }
+
+    pub fn set_comment(&mut self, node: NodeId, comment: Option<String>) {
+        self.tree.set_comment(node, comment);
+    }
+
+    pub fn toggle_nag(&mut self, node: NodeId, nag: u8) {
+        self.tree.toggle_nag(node, nag);
+    }
+
+    /// Adds the NAG if the move does not have it yet.
+    pub fn add_nag(&mut self, node: NodeId, nag: u8) {
+        if !self.tree.nags(node).contains(&nag) {
+            self.tree.toggle_nag(node, nag);
+        }
+    }
+
+    pub fn promote_variation(&mut self, node: NodeId) {
+        self.tree.promote_to_main_line(node);
+    }
+
+    /// Removes the variation of the node, going back before it if the current
+    /// position, or the one the game stands at, was in it.
+    pub fn delete_variation(&mut self, node: NodeId) -> anyhow::Result<()> {
+        let start = match self.tree.variation_start(node) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        let mut game_node_deleted = false;
+        if let Some(parent) = self.tree.parent(start) {
+            if self.tree.is_in_subtree(start, self.current_node) {
+                self.go_to(parent)?;
+            }
+            if self.tree.is_in_subtree(start, self.game_node) {
+                self.game_node = parent;
+                game_node_deleted = true;
+            }
+        }
+        self.tree.delete_variation(start);
+        if game_node_deleted {
+            self.update_status()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the move and the moves after it, then goes on from the position before it.
+    pub fn retry_from(&mut self, node: NodeId) -> anyhow::Result<()> {
+        let parent = self
+            .tree
+            .parent(node)
+            .ok_or_else(|| anyhow::anyhow!("No move to retry."))?;
+        self.tree.set_declared_outcome(None);
+        self.go_to(parent)?;
+        self.game_node = parent;
+        self.tree.truncate(node);
+        self.update_status()
+    }
+
+    /// Gives the events since the last call, the oldest first.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn calc_outcome(&self) -> Option<Outcome> {
+        get_automatic_outcome(&self.moves_chain)
+    }
+
+    /// Sets the status from the position the game stands at : a finished game is resumed
+    /// once this position is not over anymore.
+    fn update_status(&mut self) -> anyhow::Result<()> {
+        let was_over = matches!(self.status, GameStatus::Over(_));
+        let moves_chain = self.tree.moves_chain(self.game_node)?;
+        self.status = match self
+            .tree
+            .declared_outcome()
+            .or_else(|| get_automatic_outcome(&moves_chain))
+        {
+            Some(outcome) => GameStatus::Over(outcome),
+            None => GameStatus::InProgress,
+        };
+        if was_over && self.is_in_progress() {
+            self.events.push(GameEvent::Resumed);
+        }
+        self.update_player_clock();
+        Ok(())
+    }
+}
+
+/// The outcome ending the game by itself : the threefold repetition and the 50 moves
+/// rule only give a draw when claimed, unlike the fivefold repetition and the 75 moves rule.
+pub(crate) fn get_automatic_outcome(moves_chain: &MoveChain) -> Option<Outcome> {
+    moves_chain
+        .calc_outcome()
+        .filter(|outcome| outcome.passes(OutcomeFilter::Strict))
+}
+
+/// Whether the side still has enough pieces to checkmate : a pawn, a rook or a queen,
+/// two minor pieces, or a single one while the opponent king could be blocked by its own pieces.
+fn has_mating_material(board: &Board, side: Color) -> bool {
+    let mut minor_pieces = 0;
+    let mut opponent_pieces = 0;
+    for coord in Coord::iter() {
+        let cell = board.get(coord);
+        let (color, piece) = match (cell.color(), cell.piece()) {
+            (Some(color), Some(piece)) => (color, piece),
+            _ => continue,
+        };
+        match piece {
+            Piece::King => {}
+            _ if color != side => opponent_pieces += 1,
+            Piece::Knight | Piece::Bishop => minor_pieces += 1,
+            _ => return true,
+        }
+    }
+    minor_pieces >= 2 || (minor_pieces == 1 && opponent_pieces > 0)
+}
+
+/// The sentence telling how the game ended.
+pub fn describe_outcome(outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Draw(draw_type) => match draw_type {
+            DrawReason::InsufficientMaterial => tr!("Draw by missing material."),
+            DrawReason::Stalemate => tr!("Draw by stalemate."),
+            DrawReason::Moves50 => tr!("Draw by the 50 moves rule."),
+            DrawReason::Moves75 => tr!("Draw by the 75 moves rule."),
+            DrawReason::Repeat3 => tr!("Draw by three fold repetition."),
+            DrawReason::Repeat5 => tr!("Draw by five fold repetition."),
+            DrawReason::Agreement => tr!("Draw by agreement."),
+            _ => tr!("Draw by unknown reason."),
+        },
+        Outcome::Win { side, reason } => {
+            let side_text = if side == Color::White {
+                tr!("White")
+            } else {
+                tr!("Black")
+            };
+            match reason {
+                WinReason::Checkmate => {
+                    tr!("{} won by checkmate.", side_text)
+                }
+                WinReason::Resign => {
+                    tr!("{} won by resignation.", side_text)
+                }
+                WinReason::TimeForfeit => {
+                    tr!("{} won on time.", side_text)
+                }
+                _ => tr!("{} won by unknown reason.", side_text),
+            }
+        }
+    }
+}
+
+pub fn get_uci_move_for(
+    start_file: u8,
+    start_rank: u8,
+    end_file: u8,
+    end_rank: u8,
+    promotion: Option<char>,
+) -> uci::Move {
+    uci::Move::Move {
+        src: parse_square_coords(start_file, start_rank),
+        dst: parse_square_coords(end_file, end_rank),
+        promote: parse_promotion(promotion),
+    }
+}
+
+fn parse_square_coords(file: u8, rank: u8) -> Coord {
+    let file = match file {
+        0 => File::A,
+        1 => File::B,
+        2 => File::C,
+        3 => File::D,
+        4 => File::E,
+        5 => File::F,
+        6 => File::G,
+        7 => File::H,
+        _ => panic!("Forbidden file value : {}.", file),
+    };
+
+    let rank = match rank {
+        0 => Rank::R1,
+        1 => Rank::R2,
+        2 => Rank::R3,
+        3 => Rank::R4,
+        4 => Rank::R5,
+        5 => Rank::R6,
+        6 => Rank::R7,
+        7 => Rank::R8,
+        _ => panic!("Forbidden rank value : {}.", rank),
+    };
+
+    Coord::from_parts(file, rank)
+}
+
+fn parse_promotion(piece: Option<char>) -> Option<PromotePiece> {
+    Some(match piece? {
+        'q' => PromotePiece::Queen,
+        'r' => PromotePiece::Rook,
+        'b' => PromotePiece::Bishop,
+        'n' => PromotePiece::Knight,
+        other => panic!("Forbidden promote value : {}.", other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started_session(fen: &str, player_side: Color) -> GameSession {
+        let mut session = GameSession::default();
+        session.start(Board::from_fen(fen).unwrap(), player_side);
+        session.take_events();
+        session
+    }
+
+    fn play_uci(session: &mut GameSession, uci_move: &str) -> anyhow::Result<String> {
+        let move_to_play = Move::from_uci_legal(uci_move, session.board())?;
+        session.play_move(move_to_play)
+    }
+
+    #[test]
+    fn default_session_is_idle() {
+        let mut session = GameSession::default();
+        assert_eq!(session.status(), GameStatus::Idle);
+        assert!(!session.is_in_progress());
+        assert!(session.take_events().is_empty());
+        assert!(play_uci(&mut session, "e1e2").is_err());
+    }
+
+    #[test]
+    fn start_resets_the_game() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        play_uci(&mut session, "a1a2").unwrap();
+        session.take_events();
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R b - - 0 1").unwrap();
+        session.start(board.clone(), Color::White);
+
+        assert_eq!(session.take_events(), vec![GameEvent::Started]);
+        assert_eq!(session.status(), GameStatus::InProgress);
+        assert_eq!(session.board().as_fen(), board.as_fen());
+        assert_eq!(session.moves_chain().len(), 0);
+        assert_eq!(session.side_to_move(), Color::Black);
+        assert_eq!(session.player_side(), Color::White);
+        assert!(!session.is_player_turn());
+    }
+
+    #[test]
+    fn legal_move_is_played() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+
+        assert_eq!(play_uci(&mut session, "a1a7").unwrap(), "Ra7");
+        assert_eq!(session.side_to_move(), Color::Black);
+        assert_eq!(session.moves_chain().uci().to_string(), "a1a7");
+        assert_eq!(
+            session.take_events(),
+            vec![GameEvent::MovePlayed {
+                san: "Ra7".to_string(),
+                white_player: true,
+            }]
+        );
+        assert!(session.take_events().is_empty());
+
+        play_uci(&mut session, "e8d8").unwrap();
+        assert_eq!(
+            session.take_events(),
+            vec![GameEvent::MovePlayed {
+                san: "Kd8".to_string(),
+                white_player: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn illegal_move_is_rejected() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+
+        assert!(play_uci(&mut session, "a1b2").is_err());
+        assert!(play_uci(&mut session, "e8e7").is_err());
+        assert!(play_uci(&mut session, "zz").is_err());
+        assert_eq!(session.moves_chain().len(), 0);
+        assert!(session.take_events().is_empty());
+    }
+
+    #[test]
+    fn move_leaving_king_in_check_is_rejected() {
+        let mut session = started_session("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1", Color::White);
+
+        assert!(session.move_from_squares(4, 1, 4, 4, None).is_some());
+        assert!(session.move_from_squares(4, 1, 0, 1, None).is_none());
+        assert!(play_uci(&mut session, "e2a2").is_err());
+        assert!(session.take_events().is_empty());
+    }
+
+    #[test]
+    fn move_from_squares_handles_promotion() {
+        let session = started_session("8/P3k3/8/8/8/8/8/4K3 w - - 0 1", Color::White);
+
+        assert!(session.move_from_squares(0, 6, 0, 7, None).is_none());
+        for piece in ['q', 'r', 'b', 'n'] {
+            let promotion = session.move_from_squares(0, 6, 0, 7, Some(piece)).unwrap();
+            assert_eq!(promotion.to_string(), format!("a7a8{}", piece));
+        }
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut session = started_session("k7/8/1K6/8/8/8/8/7R w - - 0 1", Color::White);
+
+        assert_eq!(play_uci(&mut session, "h1h8").unwrap(), "Rh8#");
+        let outcome = Outcome::Win {
+            side: Color::White,
+            reason: WinReason::Checkmate,
+        };
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        assert!(!session.is_in_progress());
+        assert_eq!(
+            session.take_events().last(),
+            Some(&GameEvent::Over(outcome))
+        );
+        assert!(play_uci(&mut session, "a8b8").is_err());
+    }
+
+    #[test]
+    fn stalemate_ends_the_game() {
+        let mut session = started_session("k7/8/8/1K6/8/8/8/2Q5 w - - 0 1", Color::White);
+
+        play_uci(&mut session, "c1c7").unwrap();
+        assert_eq!(
+            session.status(),
+            GameStatus::Over(Outcome::Draw(DrawReason::Stalemate))
+        );
+    }
+
+    #[test]
+    fn capture_of_last_piece_ends_the_game() {
+        let mut session = started_session("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", Color::White);
+
+        play_uci(&mut session, "e1d2").unwrap();
+        assert_eq!(
+            session.status(),
+            GameStatus::Over(Outcome::Draw(DrawReason::InsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn repetitions_are_detected_from_the_chain() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+
+        for uci_move in ["a1a2", "e8d8", "a2a1", "d8e8", "a1a2", "e8d8", "a2a1"] {
+            play_uci(&mut session, uci_move).unwrap();
+        }
+        assert_eq!(session.claimable_draw(), None);
+        assert!(session.claim_draw().is_err());
+
+        play_uci(&mut session, "d8e8").unwrap();
+        assert!(session.is_in_progress());
+        assert_eq!(session.claimable_draw(), Some(DrawReason::Repeat3));
+        session.take_events();
+
+        let outcome = Outcome::Draw(DrawReason::Repeat3);
+        assert_eq!(session.claim_draw().unwrap(), outcome);
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        assert_eq!(session.take_events(), vec![GameEvent::Over(outcome)]);
+        assert_eq!(session.tree().pgn_result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+
+        for _ in 0..4 {
+            for uci_move in ["a1a2", "e8d8", "a2a1", "d8e8"] {
+                play_uci(&mut session, uci_move).unwrap();
+            }
+        }
+        assert_eq!(
+            session.status(),
+            GameStatus::Over(Outcome::Draw(DrawReason::Repeat5))
+        );
+    }
+
+    #[test]
+    fn resigned_game_stays_over_while_browsed() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::Black);
+        play_uci(&mut session, "a1a7").unwrap();
+        let first = session.current_node();
+
+        let outcome = session.resign().unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Win {
+                side: Color::White,
+                reason: WinReason::Resign,
+            }
+        );
+        assert!(session.resign().is_err());
+        assert!(session.agree_draw().is_err());
+        session.go_to(GameTree::ROOT).unwrap();
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        assert_eq!(session.tree().pgn_result(), "1-0");
+
+        session.retry_from(first).unwrap();
+        assert!(session.is_in_progress());
+        assert_eq!(session.tree().pgn_result(), "*");
+    }
+
+    #[test]
+    fn player_clock_only_runs_on_their_turn() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::Black);
+        session.limit_player_time(Duration::from_secs(60));
+        let later = Instant::now() + Duration::from_secs(61);
+        assert_eq!(session.check_time(later), None);
+        assert_eq!(
+            session.player_time_left(later),
+            Some(Duration::from_secs(60))
+        );
+
+        play_uci(&mut session, "a1a2").unwrap();
+        session.take_events();
+        let outcome = Outcome::Win {
+            side: Color::White,
+            reason: WinReason::TimeForfeit,
+        };
+        assert_eq!(session.check_time(later), Some(outcome));
+        assert_eq!(
+            session.take_events(),
+            vec![GameEvent::TimeUp(Color::Black), GameEvent::Over(outcome)]
+        );
+        assert_eq!(session.tree().pgn_result(), "1-0");
+        assert_eq!(session.check_time(later), None);
+    }
+
+    #[test]
+    fn time_forfeit_against_a_lone_king_is_a_draw() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        session.limit_player_time(Duration::ZERO);
+
+        assert_eq!(
+            session.check_time(Instant::now()),
+            Some(Outcome::Draw(DrawReason::InsufficientMaterial))
+        );
+        assert_eq!(session.tree().pgn_result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn mating_material_is_counted() {
+        let has_material =
+            |fen: &str, side| has_mating_material(&Board::from_fen(fen).unwrap(), side);
+
+        assert!(has_material(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            Color::White
+        ));
+        assert!(has_material(
+            "4k3/8/8/8/8/8/8/3BKN2 w - - 0 1",
+            Color::White
+        ));
+        assert!(!has_material(
+            "4k3/8/8/8/8/8/8/3BKN2 w - - 0 1",
+            Color::Black
+        ));
+        assert!(!has_material(
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            Color::White
+        ));
+        assert!(has_material(
+            "4k3/4p3/8/8/8/8/8/4KN2 w - - 0 1",
+            Color::White
+        ));
+    }
+
+    #[test]
+    fn stop_only_applies_to_game_in_progress() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::Black);
+
+        assert!(!session.is_player_turn());
+        assert!(session.stop());
+        assert_eq!(session.status(), GameStatus::Stopped);
+        assert_eq!(session.take_events(), vec![GameEvent::Stopped]);
+        assert!(!session.stop());
+        assert!(session.take_events().is_empty());
+        assert!(play_uci(&mut session, "a1a2").is_err());
+    }
+
+    #[test]
+    fn uci_move_is_built_from_squares() {
+        assert_eq!(get_uci_move_for(0, 0, 7, 7, None).to_string(), "a1h8");
+        assert_eq!(get_uci_move_for(4, 6, 4, 7, Some('n')).to_string(), "e7e8n");
+    }
+
+    #[test]
+    fn move_played_from_previous_position_starts_a_variation() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        play_uci(&mut session, "a1a7").unwrap();
+        let first_move = session.current_node();
+        play_uci(&mut session, "e8d8").unwrap();
+        let main_line = session.current_node();
+
+        session.go_to(first_move).unwrap();
+        assert_eq!(session.moves_chain().uci().to_string(), "a1a7");
+        assert_eq!(session.side_to_move(), Color::Black);
+        play_uci(&mut session, "e8f8").unwrap();
+        let variation = session.current_node();
+
+        assert_eq!(session.tree().children(first_move), &[main_line, variation]);
+        assert_eq!(session.moves_chain().uci().to_string(), "a1a7 e8f8");
+
+        session.delete_variation(variation).unwrap();
+        assert_eq!(session.current_node(), first_move);
+        assert_eq!(session.tree().children(first_move), &[main_line]);
+        assert!(!session.is_browsing());
+    }
+
+    #[test]
+    fn computer_turn_is_locked_while_browsing() {
+        let mut session = started_session("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        play_uci(&mut session, "a1a7").unwrap();
+        let first_move = session.current_node();
+        play_uci(&mut session, "e8d8").unwrap();
+        assert!(!session.is_browsing());
+
+        session.go_to(first_move).unwrap();
+        assert!(session.is_browsing());
+        assert!(session.is_input_locked());
+        session.go_to(GameTree::ROOT).unwrap();
+        assert!(session.is_browsing());
+        assert!(!session.is_input_locked());
+
+        play_uci(&mut session, "a1a8").unwrap();
+        assert!(!session.is_browsing());
+        assert!(!session.is_input_locked());
+    }
+
+    #[test]
+    fn finished_game_is_only_browsed() {
+        let mut session = started_session("k7/8/1K6/8/8/8/8/7R w - - 0 1", Color::White);
+        play_uci(&mut session, "h1h8").unwrap();
+        let mate = session.current_node();
+        let outcome = Outcome::Win {
+            side: Color::White,
+            reason: WinReason::Checkmate,
+        };
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        session.take_events();
+
+        session.go_to(GameTree::ROOT).unwrap();
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        assert!(play_uci(&mut session, "h1h7").is_err());
+        session.go_to(mate).unwrap();
+        assert_eq!(session.status(), GameStatus::Over(outcome));
+        assert!(session.take_events().is_empty());
+
+        session.retry_from(mate).unwrap();
+        assert!(session.is_in_progress());
+        assert_eq!(session.take_events(), vec![GameEvent::Resumed]);
+        session.stop();
+        assert!(session.go_to(GameTree::ROOT).is_err());
+    }
+
+    #[test]
+    fn loaded_tree_goes_on_from_its_main_line() {
+        let mut tree = GameTree::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap());
+        let first_move = Move::from_uci_legal("a1a7", tree.position(GameTree::ROOT)).unwrap();
+        let first = tree.add_move(GameTree::ROOT, first_move).unwrap();
+        tree.set_comment(first, Some("Cuts the king.".to_string()));
+
+        let mut session = GameSession::default();
+        session.load(tree, Color::White).unwrap();
+        assert_eq!(session.take_events(), vec![GameEvent::Started]);
+        assert_eq!(session.current_node(), first);
+        assert_eq!(session.moves_chain().uci().to_string(), "a1a7");
+        assert_eq!(session.tree().comment(first), Some("Cuts the king."));
+        assert!(!session.is_player_turn());
+    }
+
+    #[test]
+    fn retry_removes_the_mistake_and_resumes_the_game() {
+        let mut session = started_session("k7/8/8/1K6/8/8/8/2Q5 w - - 0 1", Color::White);
+        play_uci(&mut session, "c1c7").unwrap();
+        let stalemate = session.current_node();
+        assert_eq!(
+            session.status(),
+            GameStatus::Over(Outcome::Draw(DrawReason::Stalemate))
+        );
+        session.take_events();
+
+        session.retry_from(stalemate).unwrap();
+        assert!(session.is_in_progress());
+        assert_eq!(session.take_events(), vec![GameEvent::Resumed]);
+        assert_eq!(session.current_node(), GameTree::ROOT);
+        assert!(session.tree().children(GameTree::ROOT).is_empty());
+        assert_eq!(session.moves_chain().len(), 0);
+        assert!(session.retry_from(GameTree::ROOT).is_err());
+    }
+}
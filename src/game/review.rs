@@ -0,0 +1,260 @@
+use owlchess::{Color, Outcome};
+use tr::tr;
+
+use super::tree::{GameTree, NodeId};
+use crate::engine::{get_expected_result, Score};
+
+/// How much a move lowers the winning chances of its side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveQuality {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    /// From the drop of the winning chances, in percents.
+    fn from_win_drop(win_drop: f64) -> Option<Self> {
+        if win_drop >= 30.0 {
+            Some(MoveQuality::Blunder)
+        } else if win_drop >= 20.0 {
+            Some(MoveQuality::Mistake)
+        } else if win_drop >= 10.0 {
+            Some(MoveQuality::Inaccuracy)
+        } else {
+            None
+        }
+    }
+
+    /// The NAG marking the move : ?!, ? or ??.
+    pub fn nag(&self) -> u8 {
+        match self {
+            MoveQuality::Inaccuracy => 6,
+            MoveQuality::Mistake => 2,
+            MoveQuality::Blunder => 4,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            MoveQuality::Inaccuracy => tr!("Inaccuracy"),
+            MoveQuality::Mistake => tr!("Mistake"),
+            MoveQuality::Blunder => tr!("Blunder"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CriticalMove {
+    pub node: NodeId,
+    pub quality: MoveQuality,
+    /// The move turns a win into a draw or a loss, or a draw into a loss.
+    pub changes_result: bool,
+}
+
+#[derive(Clone)]
+pub struct ReviewSummary {
+    /// From 0 to 100, None for a side without move.
+    pub white_accuracy: Option<f64>,
+    pub black_accuracy: Option<f64>,
+    /// In the order of the game.
+    pub critical_moves: Vec<CriticalMove>,
+}
+
+/// The scores of the positions of the main line, filled as the engine gives them.
+pub struct GameReview {
+    /// The root first, then the nodes of the main line.
+    nodes: Vec<NodeId>,
+    /// Scores from the point of view of the side to move.
+    scores: Vec<Option<Score>>,
+    /// The positions already scored, even without score.
+    scored: Vec<bool>,
+}
+
+impl GameReview {
+    /// The finished positions are scored at once, the engine not being able to search them.
+    pub fn new(tree: &GameTree) -> Self {
+        let mut nodes = vec![GameTree::ROOT];
+        nodes.extend(tree.path(tree.main_line_end()));
+        let scores: Vec<Option<Score>> = nodes
+            .iter()
+            .map(|node| get_final_score(tree, *node))
+            .collect();
+        let scored = scores.iter().map(Option::is_some).collect();
+        Self {
+            nodes,
+            scores,
+            scored,
+        }
+    }
+
+    /// The plies (0 for the start position) to give to the engine, with their node.
+    pub fn pending_positions(&self) -> Vec<(usize, NodeId)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(ply, _)| !self.scored[*ply])
+            .map(|(ply, node)| (ply, *node))
+            .collect()
+    }
+
+    pub fn set_score(&mut self, ply: usize, score: Option<Score>) {
+        if ply < self.scores.len() {
+            self.scores[ply] = score;
+            self.scored[ply] = true;
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.scored.iter().all(|scored| *scored)
+    }
+
+    /// Compares each move with the position before it : the accuracy of a side is the
+    /// average of its moves accuracies, which fall as the winning chances drop.
+    pub fn summary(&self, tree: &GameTree) -> ReviewSummary {
+        let mut white_accuracies = vec![];
+        let mut black_accuracies = vec![];
+        let mut critical_moves = vec![];
+
+        for ply in 1..self.nodes.len() {
+            let (before, after) = match (self.scores[ply - 1], self.scores[ply]) {
+                (Some(before), Some(after)) => (before, after),
+                _ => continue,
+            };
+            // The score after the move is given for the opponent.
+            let win_drop = (get_win_percent(before) - (100.0 - get_win_percent(after))).max(0.0);
+            let node = self.nodes[ply];
+            if tree.is_white_move(node) {
+                white_accuracies.push(get_move_accuracy(win_drop));
+            } else {
+                black_accuracies.push(get_move_accuracy(win_drop));
+            }
+            if let Some(quality) = MoveQuality::from_win_drop(win_drop) {
+                let changes_result =
+                    get_expected_result(before) != get_expected_result(after).for_opponent();
+                critical_moves.push(CriticalMove {
+                    node,
+                    quality,
+                    changes_result,
+                });
+            }
+        }
+
+        ReviewSummary {
+            white_accuracy: get_average(&white_accuracies),
+            black_accuracy: get_average(&black_accuracies),
+            critical_moves,
+        }
+    }
+}
+
+/// The score of a finished position, for the side to move.
+fn get_final_score(tree: &GameTree, node: NodeId) -> Option<Score> {
+    match tree.position(node).calc_outcome()? {
+        Outcome::Win { .. } => Some(Score::Mate(0)),
+        Outcome::Draw(_) => Some(Score::Centipawns(0)),
+    }
+}
+
+/// The winning chances of the side to move, in percents, as fitted on the games of
+/// the online players.
+fn get_win_percent(score: Score) -> f64 {
+    match score {
+        Score::Centipawns(centipawns) => {
+            let centipawns = centipawns.clamp(-1000, 1000) as f64;
+            50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * centipawns).exp()) - 1.0)
+        }
+        Score::Mate(moves) if moves > 0 => 100.0,
+        Score::Mate(_) => 0.0,
+    }
+}
+
+fn get_move_accuracy(win_drop: f64) -> f64 {
+    (103.1668 * (-0.04354 * win_drop).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+fn get_average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// The side whose accuracy is given first : the one of the player.
+pub fn format_accuracies(summary: &ReviewSummary, player_side: Color) -> String {
+    let format_accuracy = |accuracy: Option<f64>| match accuracy {
+        Some(accuracy) => format!("{:.0} %", accuracy),
+        None => "-".to_string(),
+    };
+    let white = tr!(
+        "White accuracy : {}",
+        format_accuracy(summary.white_accuracy)
+    );
+    let black = tr!(
+        "Black accuracy : {}",
+        format_accuracy(summary.black_accuracy)
+    );
+    if player_side == Color::White {
+        format!("{}\n{}", white, black)
+    } else {
+        format!("{}\n{}", black, white)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use owlchess::{Board, Move};
+
+    fn play(tree: &mut GameTree, parent: NodeId, uci_move: &str) -> NodeId {
+        let played_move = Move::from_uci_legal(uci_move, tree.position(parent)).unwrap();
+        tree.add_move(parent, played_move).unwrap()
+    }
+
+    #[test]
+    fn win_percent_follows_the_score() {
+        assert_eq!(get_win_percent(Score::Centipawns(0)), 50.0);
+        assert!(get_win_percent(Score::Centipawns(300)) > 75.0);
+        assert!(get_win_percent(Score::Centipawns(-300)) < 25.0);
+        assert_eq!(get_win_percent(Score::Mate(3)), 100.0);
+        assert_eq!(get_win_percent(Score::Mate(-3)), 0.0);
+        assert!(get_move_accuracy(0.0) > 99.0);
+        assert!(get_move_accuracy(50.0) < 15.0);
+    }
+
+    #[test]
+    fn moves_throwing_the_win_away_are_blunders() {
+        let mut tree = GameTree::new(Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap());
+        // Rh7 keeps the mate in reach, then Rb7 throws it away.
+        let first = play(&mut tree, GameTree::ROOT, "h1h7");
+        let second = play(&mut tree, first, "a8b8");
+        let last = play(&mut tree, second, "h7b7");
+        let mut review = GameReview::new(&tree);
+
+        assert_eq!(review.pending_positions().len(), 4);
+        review.set_score(0, Some(Score::Mate(1)));
+        review.set_score(1, Some(Score::Mate(-2)));
+        review.set_score(2, Some(Score::Mate(1)));
+        assert!(!review.is_complete());
+        review.set_score(3, Some(Score::Centipawns(0)));
+        assert!(review.is_complete());
+
+        let summary = review.summary(&tree);
+        assert_eq!(summary.critical_moves.len(), 1);
+        assert_eq!(summary.critical_moves[0].node, last);
+        assert_eq!(summary.critical_moves[0].quality, MoveQuality::Blunder);
+        assert!(summary.critical_moves[0].changes_result);
+        assert!(summary.black_accuracy.unwrap() > 99.0);
+        assert!(summary.white_accuracy.unwrap() < 60.0);
+    }
+
+    #[test]
+    fn finished_positions_are_scored_without_engine() {
+        let mut tree = GameTree::new(Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap());
+        play(&mut tree, GameTree::ROOT, "h1h8");
+        let review = GameReview::new(&tree);
+
+        assert_eq!(review.pending_positions(), vec![(0, GameTree::ROOT)]);
+        assert_eq!(review.scores[1], Some(Score::Mate(0)));
+    }
+}
@@ -0,0 +1,647 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use owlchess::{Board, Color, DrawReason, GameStatus, Move, MoveChain, Outcome, WinReason};
+
+use super::get_automatic_outcome;
+use crate::pgn::{parse_pgn, write_pgn, PgnGame, PgnMove};
+
+/// Index of a node in its tree, which stays valid after any change of the tree.
+pub type NodeId = usize;
+
+#[derive(Clone, Debug)]
+struct Node {
+    parent: Option<NodeId>,
+    /// The move leading to the node, None for the root.
+    played_move: Option<Move>,
+    san: String,
+    position: Board,
+    /// The first child continues the line, the others are its variations.
+    children: Vec<NodeId>,
+    /// Given after the move, or before the first move for the root.
+    comment: Option<String>,
+    nags: Vec<u8>,
+}
+
+/// What the history shows, in the order of the PGN notation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeItem {
+    /// Such as "12." before a white move, or "12..." before a black one.
+    MoveNumber(String),
+    Move(NodeId),
+    /// The comment of the node, after its move.
+    Comment(NodeId),
+    VariationStart,
+    VariationEnd,
+    /// A collapsed variation, starting with the node.
+    Collapsed(NodeId),
+}
+
+/// The moves played from a start position, with their variations : the root holds
+/// the start position, and each other node a move.
+#[derive(Clone, Debug)]
+pub struct GameTree {
+    nodes: Vec<Node>,
+    /// Ends the game whatever its position : a resignation, a claimed or agreed draw.
+    declared_outcome: Option<Outcome>,
+}
+
+impl GameTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new(start_position: Board) -> Self {
+        Self {
+            nodes: vec![Node {
+                parent: None,
+                played_move: None,
+                san: String::new(),
+                position: start_position,
+                children: vec![],
+                comment: None,
+                nags: vec![],
+            }],
+            declared_outcome: None,
+        }
+    }
+
+    pub fn position(&self, node: NodeId) -> &Board {
+        &self.nodes[node].position
+    }
+
+    pub fn san(&self, node: NodeId) -> &str {
+        &self.nodes[node].san
+    }
+
+    pub fn comment(&self, node: NodeId) -> Option<&str> {
+        self.nodes[node].comment.as_deref()
+    }
+
+    pub fn nags(&self, node: NodeId) -> &[u8] {
+        &self.nodes[node].nags
+    }
+
+    /// An empty comment removes it.
+    pub fn set_comment(&mut self, node: NodeId, comment: Option<String>) {
+        self.nodes[node].comment = comment
+            .map(|comment| comment.trim().to_string())
+            .filter(|comment| !comment.is_empty());
+    }
+
+    /// Adds the NAG, replacing the other one of its kind (a move or a position assessment),
+    /// or removes it if already there.
+    pub fn toggle_nag(&mut self, node: NodeId, nag: u8) {
+        let nags = &mut self.nodes[node].nags;
+        if nags.contains(&nag) {
+            nags.retain(|other| *other != nag);
+            return;
+        }
+        let is_move_assessment = |nag: u8| nag <= 9;
+        nags.retain(|other| is_move_assessment(*other) != is_move_assessment(nag));
+        nags.push(nag);
+        nags.sort_unstable();
+    }
+
+    pub fn declared_outcome(&self) -> Option<Outcome> {
+        self.declared_outcome
+    }
+
+    pub fn set_declared_outcome(&mut self, outcome: Option<Outcome>) {
+        self.declared_outcome = outcome;
+    }
+
+    /// The declared outcome, else the one ending the game at the end of the main line.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.declared_outcome.or_else(|| {
+            let moves_chain = self.moves_chain(self.main_line_end()).ok()?;
+            get_automatic_outcome(&moves_chain)
+        })
+    }
+
+    /// Such as "1-0", or "*" while the game goes on.
+    pub fn pgn_result(&self) -> String {
+        GameStatus::from(self.outcome()).to_string()
+    }
+
+    /// The "Termination" tag of the PGN standard. Only the clock declares a draw by missing
+    /// material : the opponent of the side out of time could not checkmate anymore.
+    pub fn pgn_termination(&self) -> &'static str {
+        match self.declared_outcome {
+            Some(
+                Outcome::Win {
+                    reason: WinReason::TimeForfeit,
+                    ..
+                }
+                | Outcome::Draw(DrawReason::InsufficientMaterial),
+            ) => "time forfeit",
+            _ if self.outcome().is_none() => "unterminated",
+            _ => "normal",
+        }
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node].parent
+    }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node].children
+    }
+
+    /// The move has been played by White.
+    pub fn is_white_move(&self, node: NodeId) -> bool {
+        self.nodes[node].position.side() == Color::Black
+    }
+
+    /// Plays the move after the node : a new move is a variation if the node already
+    /// has a continuation, and a move already there is only given back.
+    pub fn add_move(&mut self, parent: NodeId, played_move: Move) -> anyhow::Result<NodeId> {
+        if let Some(existing) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|child| self.nodes[**child].played_move == Some(played_move))
+        {
+            return Ok(*existing);
+        }
+
+        let parent_position = &self.nodes[parent].position;
+        let san = played_move
+            .san(parent_position)
+            .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?
+            .to_string();
+        let position = parent_position
+            .make_move(played_move)
+            .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?;
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            parent: Some(parent),
+            played_move: Some(played_move),
+            san,
+            position,
+            children: vec![],
+            comment: None,
+            nags: vec![],
+        });
+        self.nodes[parent].children.push(node);
+        Ok(node)
+    }
+
+    /// The last node of the main line.
+    pub fn main_line_end(&self) -> NodeId {
+        let mut end = Self::ROOT;
+        while let Some(main) = self.nodes[end].children.first() {
+            end = *main;
+        }
+        end
+    }
+
+    /// The moves of the main line, a white move and the black answer counting as one.
+    pub fn main_line_moves(&self) -> usize {
+        let path = self.path(self.main_line_end());
+        let black_starts = path
+            .first()
+            .is_some_and(|first| !self.is_white_move(*first));
+        let white_moves = path
+            .iter()
+            .filter(|node| self.is_white_move(**node))
+            .count();
+        white_moves + usize::from(black_starts)
+    }
+
+    /// The nodes from the first move to the given one.
+    pub fn path(&self, node: NodeId) -> Vec<NodeId> {
+        let mut path = vec![];
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(current);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// The moves from the start position to the node.
+    pub fn moves_chain(&self, node: NodeId) -> anyhow::Result<MoveChain> {
+        let mut moves_chain = MoveChain::new(self.nodes[Self::ROOT].position.clone());
+        for node in self.path(node) {
+            if let Some(played_move) = self.nodes[node].played_move {
+                moves_chain
+                    .push(played_move)
+                    .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?;
+            }
+        }
+        Ok(moves_chain)
+    }
+
+    /// The node follows the ancestor, or is the ancestor itself.
+    pub fn is_in_subtree(&self, ancestor: NodeId, node: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.nodes[node].parent;
+        }
+        false
+    }
+
+    /// First move of the innermost variation holding the node, None on the main line.
+    pub fn variation_start(&self, node: NodeId) -> Option<NodeId> {
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            if self.nodes[parent].children[0] != current {
+                return Some(current);
+            }
+            current = parent;
+        }
+        None
+    }
+
+    /// The line of the node becomes the main line, the lines it replaces becoming variations.
+    pub fn promote_to_main_line(&mut self, node: NodeId) {
+        for node in self.path(node) {
+            if let Some(parent) = self.nodes[node].parent {
+                let children = &mut self.nodes[parent].children;
+                if let Some(index) = children.iter().position(|child| *child == node) {
+                    let promoted = children.remove(index);
+                    children.insert(0, promoted);
+                }
+            }
+        }
+    }
+
+    /// Removes the innermost variation holding the node, and gives the node before it.
+    /// Nothing is removed on the main line.
+    pub fn delete_variation(&mut self, node: NodeId) -> Option<NodeId> {
+        let start = self.variation_start(node)?;
+        let parent = self.nodes[start].parent?;
+        self.nodes[parent].children.retain(|child| *child != start);
+        Some(parent)
+    }
+
+    /// Removes the move with the moves following it, and gives the node before it.
+    /// The next variation, if any, becomes the continuation.
+    pub fn truncate(&mut self, node: NodeId) -> Option<NodeId> {
+        let parent = self.nodes[node].parent?;
+        self.nodes[parent].children.retain(|child| *child != node);
+        Some(parent)
+    }
+
+    /// The moves and variations, the collapsed variations (given by their first node)
+    /// being shown without their moves.
+    pub fn items(&self, collapsed: &HashSet<NodeId>) -> Vec<TreeItem> {
+        let mut items = vec![];
+        if self.nodes[Self::ROOT].comment.is_some() {
+            items.push(TreeItem::Comment(Self::ROOT));
+        }
+        self.push_line(Self::ROOT, true, collapsed, &mut items);
+        items
+    }
+
+    /// Pushes the line continuing the node, each move being followed by its variations.
+    /// A black move only has its number first in its line or after a variation.
+    fn push_line(
+        &self,
+        start: NodeId,
+        mut needs_number: bool,
+        collapsed: &HashSet<NodeId>,
+        items: &mut Vec<TreeItem>,
+    ) {
+        let mut current = start;
+        while let Some((main, variations)) = self.nodes[current].children.split_first() {
+            if needs_number || self.is_white_move(*main) {
+                items.push(TreeItem::MoveNumber(self.move_number_text(*main)));
+            }
+            items.push(TreeItem::Move(*main));
+            let has_comment = self.nodes[*main].comment.is_some();
+            if has_comment {
+                items.push(TreeItem::Comment(*main));
+            }
+            needs_number = has_comment || !variations.is_empty();
+
+            for variation in variations {
+                if collapsed.contains(variation) {
+                    items.push(TreeItem::Collapsed(*variation));
+                    continue;
+                }
+                items.push(TreeItem::VariationStart);
+                items.push(TreeItem::MoveNumber(self.move_number_text(*variation)));
+                items.push(TreeItem::Move(*variation));
+                let has_comment = self.nodes[*variation].comment.is_some();
+                if has_comment {
+                    items.push(TreeItem::Comment(*variation));
+                }
+                self.push_line(*variation, has_comment, collapsed, items);
+                items.push(TreeItem::VariationEnd);
+            }
+            current = *main;
+        }
+    }
+
+    /// The moves as PGN, with the position headers if the game does not start
+    /// from the initial position.
+    pub fn to_pgn(&self) -> PgnGame {
+        let start_position = &self.nodes[Self::ROOT].position;
+        let mut headers = vec![];
+        if start_position.as_fen() != Board::initial().as_fen() {
+            headers.push(("SetUp".to_string(), "1".to_string()));
+            headers.push(("FEN".to_string(), start_position.as_fen()));
+        }
+        PgnGame {
+            headers,
+            initial_comment: self.nodes[Self::ROOT].comment.clone(),
+            moves: self.pgn_line(Self::ROOT),
+            result: Some(self.pgn_result()),
+        }
+    }
+
+    /// The line continuing the node, each move holding its variations.
+    fn pgn_line(&self, start: NodeId) -> Vec<PgnMove> {
+        let mut moves = vec![];
+        let mut current = start;
+        while let Some((main, variations)) = self.nodes[current].children.split_first() {
+            let mut pgn_move = self.pgn_move(*main);
+            for variation in variations {
+                let mut variation_moves = vec![self.pgn_move(*variation)];
+                variation_moves.extend(self.pgn_line(*variation));
+                pgn_move.variations.push(variation_moves);
+            }
+            moves.push(pgn_move);
+            current = *main;
+        }
+        moves
+    }
+
+    fn pgn_move(&self, node: NodeId) -> PgnMove {
+        PgnMove {
+            san: self.nodes[node].san.clone(),
+            nags: self.nodes[node].nags.clone(),
+            comment: self.nodes[node].comment.clone(),
+            variations: vec![],
+        }
+    }
+
+    /// Reads the moves, variations and annotations of the game.
+    pub fn from_pgn(game: &PgnGame) -> anyhow::Result<Self> {
+        let start_position = match game.header("FEN") {
+            Some(fen) => Board::from_fen(fen).with_context(|| format!("Bad FEN {}.", fen))?,
+            None => Board::initial(),
+        };
+        let mut tree = GameTree::new(start_position);
+        tree.set_comment(Self::ROOT, game.initial_comment.clone());
+        tree.add_pgn_line(Self::ROOT, &game.moves)?;
+        Ok(tree)
+    }
+
+    fn add_pgn_line(&mut self, start: NodeId, moves: &[PgnMove]) -> anyhow::Result<()> {
+        let mut current = start;
+        for pgn_move in moves {
+            // The variations replace the move, from the position before it.
+            for variation in &pgn_move.variations {
+                self.add_pgn_line(current, variation)?;
+            }
+            let played_move = Move::from_san(&pgn_move.san, &self.nodes[current].position)
+                .with_context(|| format!("Bad move {}.", pgn_move.san))?;
+            let node = self.add_move(current, played_move)?;
+            // The main line comes first, even if the variations have been added before.
+            let children = &mut self.nodes[current].children;
+            children.retain(|child| *child != node);
+            children.insert(0, node);
+
+            self.set_comment(node, pgn_move.comment.clone());
+            self.nodes[node].nags = pgn_move.nags.clone();
+            current = node;
+        }
+        Ok(())
+    }
+
+    /// The move with its number, such as "12... Kd7".
+    pub fn numbered_san(&self, node: NodeId) -> String {
+        format!("{} {}", self.move_number_text(node), self.nodes[node].san)
+    }
+
+    fn move_number_text(&self, node: NodeId) -> String {
+        let parent = self.nodes[node].parent.unwrap_or(Self::ROOT);
+        let move_number = self.nodes[parent].position.raw().move_number;
+        if self.is_white_move(node) {
+            format!("{}.", move_number)
+        } else {
+            format!("{}...", move_number)
+        }
+    }
+}
+
+/// Reads the first game of the PGN file.
+pub fn read_game(path: &Path) -> anyhow::Result<GameTree> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}.", path.display()))?;
+    let game = parse_pgn(&content).with_context(|| "Bad PGN.")?;
+    GameTree::from_pgn(&game)
+}
+
+/// Writes the game in PGN, with the given headers first.
+pub fn write_game(
+    tree: &GameTree,
+    headers: Vec<(String, String)>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let mut game = tree.to_pgn();
+    game.headers.splice(0..0, headers);
+    fs::write(path, write_pgn(&game))
+        .with_context(|| format!("Failed to write {}.", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(tree: &mut GameTree, parent: NodeId, uci_move: &str) -> NodeId {
+        let played_move = Move::from_uci_legal(uci_move, tree.position(parent)).unwrap();
+        tree.add_move(parent, played_move).unwrap()
+    }
+
+    fn items_text(tree: &GameTree, collapsed: &HashSet<NodeId>) -> String {
+        tree.items(collapsed)
+            .iter()
+            .map(|item| match item {
+                TreeItem::MoveNumber(text) => text.clone(),
+                TreeItem::Move(node) => tree.san(*node).to_string(),
+                TreeItem::VariationStart => "(".to_string(),
+                TreeItem::VariationEnd => ")".to_string(),
+                TreeItem::Comment(node) => format!("{{{}}}", tree.comment(*node).unwrap()),
+                TreeItem::Collapsed(_) => "(...)".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// 1. Ra7 Kd8 2. Rb7 (2. Ra8+ Kc7) Kc8, with the variation on the second move.
+    fn tree_with_variation() -> (GameTree, NodeId, NodeId) {
+        let mut tree = GameTree::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap());
+        let first = play(&mut tree, GameTree::ROOT, "a1a7");
+        let second = play(&mut tree, first, "e8d8");
+        let main = play(&mut tree, second, "a7b7");
+        play(&mut tree, main, "d8c8");
+        let variation = play(&mut tree, second, "a7a8");
+        let variation_end = play(&mut tree, variation, "d8c7");
+        (tree, variation, variation_end)
+    }
+
+    #[test]
+    fn new_move_makes_a_variation() {
+        let (tree, variation, variation_end) = tree_with_variation();
+
+        assert_eq!(
+            items_text(&tree, &HashSet::new()),
+            "1. Ra7 Kd8 2. Rb7 ( 2. Ra8+ Kc7 ) 2... Kc8"
+        );
+        assert!(tree.variation_start(variation_end).is_some());
+        assert_eq!(tree.variation_start(variation_end), Some(variation));
+        assert_eq!(
+            tree.moves_chain(variation_end).unwrap().uci().to_string(),
+            "a1a7 e8d8 a7a8 d8c7"
+        );
+    }
+
+    #[test]
+    fn known_move_is_not_added_again() {
+        let (mut tree, variation, _) = tree_with_variation();
+        let parent = tree.parent(variation).unwrap();
+
+        assert_eq!(play(&mut tree, parent, "a7a8"), variation);
+        assert_eq!(tree.children(parent).len(), 2);
+    }
+
+    #[test]
+    fn variation_is_promoted_to_main_line() {
+        let (mut tree, variation, variation_end) = tree_with_variation();
+
+        tree.promote_to_main_line(variation_end);
+        assert_eq!(tree.variation_start(variation_end), None);
+        assert_eq!(tree.variation_start(variation), None);
+        assert_eq!(
+            items_text(&tree, &HashSet::new()),
+            "1. Ra7 Kd8 2. Ra8+ ( 2. Rb7 Kc8 ) 2... Kc7"
+        );
+    }
+
+    #[test]
+    fn variation_is_deleted() {
+        let (mut tree, variation, variation_end) = tree_with_variation();
+        let parent = tree.parent(variation).unwrap();
+
+        assert_eq!(tree.delete_variation(variation_end), Some(parent));
+        assert_eq!(items_text(&tree, &HashSet::new()), "1. Ra7 Kd8 2. Rb7 Kc8");
+        assert_eq!(tree.delete_variation(parent), None);
+        assert!(tree.is_in_subtree(parent, variation_end));
+        assert!(!tree.is_in_subtree(variation_end, parent));
+    }
+
+    #[test]
+    fn collapsed_variation_hides_its_moves() {
+        let (tree, variation, _) = tree_with_variation();
+        let collapsed = HashSet::from([variation]);
+
+        assert_eq!(
+            items_text(&tree, &collapsed),
+            "1. Ra7 Kd8 2. Rb7 (...) 2... Kc8"
+        );
+    }
+
+    #[test]
+    fn nags_of_the_same_kind_replace_each_other() {
+        let (mut tree, variation, _) = tree_with_variation();
+
+        tree.toggle_nag(variation, 1);
+        tree.toggle_nag(variation, 16);
+        assert_eq!(tree.nags(variation), &[1, 16]);
+        tree.toggle_nag(variation, 4);
+        assert_eq!(tree.nags(variation), &[4, 16]);
+        tree.toggle_nag(variation, 16);
+        assert_eq!(tree.nags(variation), &[4]);
+    }
+
+    #[test]
+    fn comment_follows_its_move() {
+        let (mut tree, variation, _) = tree_with_variation();
+
+        tree.set_comment(variation, Some(" Check first. ".to_string()));
+        assert_eq!(
+            items_text(&tree, &HashSet::new()),
+            "1. Ra7 Kd8 2. Rb7 ( 2. Ra8+ {Check first.} 2... Kc7 ) 2... Kc8"
+        );
+        tree.set_comment(variation, Some(String::new()));
+        assert_eq!(tree.comment(variation), None);
+    }
+
+    #[test]
+    fn annotations_round_trip_through_pgn() {
+        let (mut tree, variation, variation_end) = tree_with_variation();
+        tree.set_comment(GameTree::ROOT, Some("Rook endgame.".to_string()));
+        tree.set_comment(variation, Some("Check first.".to_string()));
+        tree.toggle_nag(variation, 5);
+        tree.toggle_nag(variation_end, 10);
+
+        let text = write_pgn(&tree.to_pgn());
+        assert!(text.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 w - - 0 1\"]"));
+        let read_tree = GameTree::from_pgn(&parse_pgn(&text).unwrap()).unwrap();
+
+        assert_eq!(
+            items_text(&read_tree, &HashSet::new()),
+            items_text(&tree, &HashSet::new())
+        );
+        assert_eq!(read_tree.comment(GameTree::ROOT), Some("Rook endgame."));
+        let read_variation = read_tree.children(read_tree.parent(variation).unwrap())[1];
+        assert_eq!(read_tree.nags(read_variation), &[5]);
+        assert_eq!(read_tree.nags(read_tree.children(read_variation)[0]), &[10]);
+    }
+
+    #[test]
+    fn truncated_move_gives_way_to_its_variation() {
+        let (mut tree, variation, _) = tree_with_variation();
+        let parent = tree.parent(variation).unwrap();
+        let main = tree.children(parent)[0];
+
+        assert_eq!(tree.truncate(main), Some(parent));
+        assert_eq!(tree.children(parent), &[variation]);
+        assert_eq!(items_text(&tree, &HashSet::new()), "1. Ra7 Kd8 2. Ra8+ Kc7");
+        assert_eq!(tree.truncate(GameTree::ROOT), None);
+    }
+
+    #[test]
+    fn main_line_moves_are_counted_by_number() {
+        let (tree, _, _) = tree_with_variation();
+        assert_eq!(tree.main_line_moves(), 2);
+
+        let mut tree = GameTree::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 12").unwrap());
+        assert_eq!(tree.main_line_moves(), 0);
+        let first = play(&mut tree, GameTree::ROOT, "e8d8");
+        assert_eq!(tree.main_line_moves(), 1);
+        let second = play(&mut tree, first, "a1a7");
+        assert_eq!(tree.main_line_moves(), 2);
+        play(&mut tree, second, "d8c8");
+        assert_eq!(tree.main_line_moves(), 2);
+    }
+
+    #[test]
+    fn pgn_result_follows_the_outcome() {
+        let mut tree = GameTree::new(Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap());
+        assert_eq!(tree.pgn_result(), "*");
+        tree.set_declared_outcome(Some(Outcome::Draw(DrawReason::Agreement)));
+        assert_eq!(tree.pgn_result(), "1/2-1/2");
+        assert_eq!(tree.to_pgn().result.as_deref(), Some("1/2-1/2"));
+
+        assert_eq!(tree.pgn_termination(), "normal");
+
+        tree.set_declared_outcome(Some(Outcome::Draw(DrawReason::InsufficientMaterial)));
+        assert_eq!(tree.pgn_result(), "1/2-1/2");
+        assert_eq!(tree.pgn_termination(), "time forfeit");
+
+        tree.set_declared_outcome(None);
+        assert_eq!(tree.pgn_termination(), "unterminated");
+        play(&mut tree, GameTree::ROOT, "h1h8");
+        assert_eq!(tree.pgn_result(), "1-0");
+        assert_eq!(tree.pgn_termination(), "normal");
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{self, Context};
+use owlchess::{Board, Move};
+
+use crate::pgn::{parse_pgn, PgnGame};
+
+/// Marker, inside a comment, asking the learner to find the next move.
+pub const QUESTION_MARKER: &str = "[%ask]";
+
+pub struct LessonStep {
+    pub expected_move: Move,
+    pub san: String,
+    /// Other moves accepted as answers, given as variations of a question move.
+    pub alternatives: Vec<Move>,
+    /// True if the learner must find this move.
+    pub is_question: bool,
+    /// Explanation shown once the move has been played.
+    pub comment: Option<String>,
+}
+
+pub struct Lesson {
+    pub title: String,
+    pub start_position: Board,
+    pub introduction: Option<String>,
+    pub steps: Vec<LessonStep>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Expected,
+    Alternative,
+    Wrong,
+}
+
+impl Lesson {
+    pub fn from_pgn(game: &PgnGame) -> anyhow::Result<Self> {
+        let start_position = match game.header("FEN") {
+            Some(fen) => Board::from_fen(fen).with_context(|| format!("Bad FEN {}.", fen))?,
+            None => Board::initial(),
+        };
+
+        let mut board = start_position.clone();
+        let mut steps: Vec<LessonStep> = vec![];
+        let mut is_question = has_question_marker(&game.initial_comment);
+
+        for pgn_move in &game.moves {
+            let expected_move = Move::from_san(&pgn_move.san, &board)
+                .with_context(|| format!("Bad move {}.", pgn_move.san))?;
+            let alternatives = if is_question {
+                pgn_move
+                    .variations
+                    .iter()
+                    .filter_map(|variation| variation.first())
+                    .map(|alternative| {
+                        Move::from_san(&alternative.san, &board)
+                            .with_context(|| format!("Bad move {}.", alternative.san))
+                    })
+                    .collect::<anyhow::Result<Vec<Move>>>()?
+            } else {
+                vec![]
+            };
+
+            board = board
+                .make_move(expected_move)
+                .with_context(|| format!("Illegal move {}.", pgn_move.san))?;
+
+            steps.push(LessonStep {
+                expected_move,
+                san: pgn_move.san.clone(),
+                alternatives,
+                is_question,
+                comment: strip_question_marker(&pgn_move.comment),
+            });
+            is_question = has_question_marker(&pgn_move.comment);
+        }
+
+        if steps.is_empty() {
+            return Err(anyhow::anyhow!("The lesson has no move."));
+        }
+
+        Ok(Lesson {
+            title: game.header("Event").unwrap_or_default().to_string(),
+            start_position,
+            introduction: strip_question_marker(&game.initial_comment),
+            steps,
+        })
+    }
+}
+
+/// Steps through a lesson, waiting for the learner at the question moves.
+pub struct LessonPlayer {
+    lesson: Lesson,
+    next_step: usize,
+}
+
+impl LessonPlayer {
+    pub fn new(lesson: Lesson) -> Self {
+        Self {
+            lesson,
+            next_step: 0,
+        }
+    }
+
+    pub fn next_step(&self) -> Option<&LessonStep> {
+        self.lesson.steps.get(self.next_step)
+    }
+
+    pub fn is_waiting_answer(&self) -> bool {
+        self.next_step().is_some_and(|step| step.is_question)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_step >= self.lesson.steps.len()
+    }
+
+    pub fn check_answer(&self, answer: Move) -> Answer {
+        match self.next_step() {
+            Some(step) if step.expected_move == answer => Answer::Expected,
+            Some(step) if step.alternatives.contains(&answer) => Answer::Alternative,
+            _ => Answer::Wrong,
+        }
+    }
+
+    /// Goes to the next step, returning the one to play on the board.
+    pub fn advance(&mut self) -> Option<&LessonStep> {
+        let step = self.lesson.steps.get(self.next_step)?;
+        self.next_step += 1;
+        Some(step)
+    }
+}
+
+pub fn read_lesson(path: &Path) -> anyhow::Result<Lesson> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}.", path.display()))?;
+    let game = parse_pgn(&content).with_context(|| "Bad PGN.")?;
+    Lesson::from_pgn(&game)
+}
+
+fn has_question_marker(comment: &Option<String>) -> bool {
+    comment
+        .as_ref()
+        .is_some_and(|comment| comment.contains(QUESTION_MARKER))
+}
+
+fn strip_question_marker(comment: &Option<String>) -> Option<String> {
+    let comment = comment.as_ref()?.replace(QUESTION_MARKER, "");
+    let comment = comment.trim();
+    if comment.is_empty() {
+        None
+    } else {
+        Some(comment.to_string())
+    }
+}
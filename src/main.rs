@@ -1,6 +1,21 @@
-use relm::Widget;
+mod cli;
+mod engine;
+mod epd;
+mod exercises;
+mod game;
+mod lessons;
+mod packs;
+mod pgn;
+mod rendering;
+mod scheduling;
+mod settings;
+mod statistics;
+mod storage;
+mod tui;
 mod widgets;
+mod worksheets;
 
+use clap::Parser;
 use i18n_embed::{gettext::gettext_language_loader, DesktopLanguageRequester};
 use rust_embed::RustEmbed;
 
@@ -17,5 +32,8 @@ fn main() {
     i18n_embed::select(&language_loader, &translations, &requested_languages)
         .expect("Failed to embed translations.");
 
-    widgets::mainwindow::MainWindow::run(()).unwrap();
+    if let Err(err) = cli::Cli::parse().run() {
+        eprintln!("{:#}", err);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,505 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{self, Context};
+use owlchess::{Board, Color, Move};
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+use crate::engine::EngineStrength;
+use crate::epd::{read_epd, write_epd, EpdRecord};
+use crate::exercises::{
+    generate_position, has_sufficient_material, random_side, EndgameKind, Exercise, Goal,
+};
+use crate::storage;
+
+const PIECES_LETTERS: &str = "KQRBNP";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    White,
+    Black,
+}
+
+impl From<Side> for Color {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::White => Color::White,
+            Side::Black => Color::Black,
+        }
+    }
+}
+
+/// Pieces to place randomly, each one given by its uppercase letter (e.g. "KRP").
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GeneratorConstraints {
+    pub player_pieces: String,
+    pub defender_pieces: String,
+}
+
+impl GeneratorConstraints {
+    /// Reads the pieces of each side separated by a dash, the player side first (e.g. "KBN-K").
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        let (player_pieces, defender_pieces) = spec
+            .split_once('-')
+            .with_context(|| format!("Bad endgame {} (expected e.g. KQ-K).", spec))?;
+        let constraints = Self {
+            player_pieces: player_pieces.to_uppercase(),
+            defender_pieces: defender_pieces.to_uppercase(),
+        };
+
+        let errors = validate_pieces(&constraints.player_pieces, &constraints.defender_pieces);
+        if errors.is_empty() {
+            Ok(constraints)
+        } else {
+            Err(anyhow::anyhow!(
+                "Bad endgame {} : {}.",
+                spec,
+                errors.join(", ")
+            ))
+        }
+    }
+
+    /// The built-in endgame with the same pieces, if any.
+    pub fn endgame(&self) -> Option<EndgameKind> {
+        EndgameKind::ALL
+            .iter()
+            .find(|kind| {
+                kind.pieces() == (self.player_pieces.as_str(), self.defender_pieces.as_str())
+            })
+            .copied()
+    }
+}
+
+/// An exercise is given either by a fixed position or by generator constraints.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PackExercise {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<GeneratorConstraints>,
+    #[serde(default)]
+    pub goal: Goal,
+    /// When missing, the user plays the side to move of the FEN,
+    /// or a random side for the generated positions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_side: Option<Side>,
+    /// Built-in endgame the results of this exercise are counted in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endgame: Option<EndgameKind>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<String>,
+    /// Moves in SAN, one of them being expected as first move of the player (e.g. from an EPD suite).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub best_moves: Vec<String>,
+    /// Moves in SAN the player should not start with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub avoid_moves: Vec<String>,
+    /// How well the computer defends, by default the strength of the settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computer_strength: Option<EngineStrength>,
+}
+
+impl PackExercise {
+    pub fn to_exercise(&self) -> anyhow::Result<Exercise> {
+        let (start_position, player_side) = match (&self.fen, &self.generator) {
+            (Some(fen), _) => {
+                let board = Board::from_fen(fen).with_context(|| format!("Bad FEN {}.", fen))?;
+                let player_side = match self.player_side {
+                    Some(side) => side.into(),
+                    None => board.side(),
+                };
+                (board, player_side)
+            }
+            (None, Some(generator)) => {
+                let player_side = match self.player_side {
+                    Some(side) => side.into(),
+                    None => random_side(),
+                };
+                let board = generate_position(
+                    &generator.player_pieces,
+                    &generator.defender_pieces,
+                    player_side,
+                )?;
+                (board, player_side)
+            }
+            (None, None) => return Err(anyhow::anyhow!("Neither FEN nor generator given.")),
+        };
+
+        Ok(Exercise {
+            kind: self.endgame,
+            start_position,
+            player_side,
+            goal: self.goal,
+            hints: self.hints.clone(),
+            best_moves: self.best_moves.clone(),
+            avoid_moves: self.avoid_moves.clone(),
+            difficulty_score: None,
+            computer_strength: self.computer_strength,
+        })
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        if self.title.trim().is_empty() {
+            errors.push(tr!("the title is empty"));
+        }
+
+        match (&self.fen, &self.generator) {
+            (Some(_), Some(_)) => errors.push(tr!("both a FEN and a generator are given")),
+            (None, None) => errors.push(tr!("neither a FEN nor a generator is given")),
+            (Some(fen), None) => match Board::from_fen(fen) {
+                Ok(board) => {
+                    if board.calc_outcome().is_some() {
+                        errors.push(tr!("the position of the FEN is already over"));
+                    }
+                    for san in self.best_moves.iter().chain(self.avoid_moves.iter()) {
+                        if Move::from_san(san, &board).is_err() {
+                            errors.push(tr!("bad move {}", san));
+                        }
+                    }
+                }
+                Err(err) => errors.push(tr!("bad FEN ({})", err)),
+            },
+            (None, Some(generator)) => {
+                if !self.best_moves.is_empty() || !self.avoid_moves.is_empty() {
+                    errors.push(tr!("moves are given for a generated position"));
+                }
+                errors.extend(validate_pieces(
+                    &generator.player_pieces,
+                    &generator.defender_pieces,
+                ));
+                let pieces_count = generator.player_pieces.len() + generator.defender_pieces.len();
+                if pieces_count > 32 {
+                    errors.push(tr!("the generator has more than 32 pieces"));
+                }
+            }
+        }
+
+        if let Some(strength) = &self.computer_strength {
+            if strength.skill_level.is_some_and(|level| level > 20) {
+                errors.push(tr!("the skill level is above 20"));
+            }
+            if strength.suboptimal_rate > 100 {
+                errors.push(tr!("the suboptimal rate is above 100"));
+            }
+        }
+
+        errors
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExercisePack {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub description: String,
+    pub exercises: Vec<PackExercise>,
+}
+
+impl ExercisePack {
+    /// The built-in endgames, as a pack of generated exercises.
+    pub fn builtin() -> Self {
+        let exercises = EndgameKind::ALL
+            .iter()
+            .map(|kind| {
+                let (player_pieces, defender_pieces) = kind.pieces();
+                PackExercise {
+                    title: kind.title(),
+                    fen: None,
+                    generator: Some(GeneratorConstraints {
+                        player_pieces: player_pieces.to_string(),
+                        defender_pieces: defender_pieces.to_string(),
+                    }),
+                    goal: Goal::Win,
+                    player_side: None,
+                    endgame: Some(*kind),
+                    hints: vec![],
+                    best_moves: vec![],
+                    avoid_moves: vec![],
+                    computer_strength: None,
+                }
+            })
+            .collect();
+
+        Self {
+            name: tr!("Basic endgames"),
+            author: String::new(),
+            description: String::new(),
+            exercises,
+        }
+    }
+
+    /// Each position of the suite is an exercise, the user playing the side to move.
+    pub fn from_epd(name: &str, records: &[EpdRecord]) -> Self {
+        let exercises = records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let title = record
+                    .id
+                    .clone()
+                    .or_else(|| record.comment.clone())
+                    .unwrap_or_else(|| tr!("Position {}", index + 1));
+                let title = match record.direct_mate {
+                    Some(moves_count) => tr!("{} (mate in {})", title, moves_count),
+                    None => title,
+                };
+                PackExercise {
+                    title,
+                    fen: Some(record.position.as_fen()),
+                    generator: None,
+                    goal: Goal::Win,
+                    player_side: None,
+                    endgame: None,
+                    hints: vec![],
+                    best_moves: get_moves_san(&record.best_moves, &record.position),
+                    avoid_moves: get_moves_san(&record.avoid_moves, &record.position),
+                    computer_strength: None,
+                }
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            author: String::new(),
+            description: String::new(),
+            exercises,
+        }
+    }
+
+    /// One position per exercise, the generated ones being drawn randomly, with the titles as ids.
+    pub fn to_epd(&self) -> anyhow::Result<Vec<EpdRecord>> {
+        self.exercises
+            .iter()
+            .map(|exercise| {
+                let position = exercise.to_exercise()?.start_position;
+                let mut record = EpdRecord::new(position);
+                record.id = Some(exercise.title.clone());
+                for (sans, moves) in [
+                    (&exercise.best_moves, &mut record.best_moves),
+                    (&exercise.avoid_moves, &mut record.avoid_moves),
+                ] {
+                    for san in sans {
+                        moves.push(
+                            Move::from_san(san, &record.position)
+                                .with_context(|| format!("Bad move {}.", san))?,
+                        );
+                    }
+                }
+                Ok(record)
+            })
+            .collect()
+    }
+
+    /// Gives all the errors of the pack at once.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+
+        if self.name.trim().is_empty() {
+            errors.push(tr!("The pack name is empty."));
+        }
+        if self.exercises.is_empty() {
+            errors.push(tr!("The pack has no exercise."));
+        }
+        for (index, exercise) in self.exercises.iter().enumerate() {
+            for error in exercise.validate() {
+                errors.push(tr!("Exercise {} : {}.", index + 1, error));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_pieces(player_pieces: &str, defender_pieces: &str) -> Vec<String> {
+    let mut errors = vec![];
+
+    for pieces in [player_pieces, defender_pieces] {
+        for piece in pieces.chars() {
+            if !PIECES_LETTERS.contains(piece) {
+                errors.push(tr!("bad piece letter '{}' in \"{}\"", piece, pieces));
+            }
+        }
+
+        let kings_count = pieces.chars().filter(|piece| *piece == 'K').count();
+        if kings_count != 1 {
+            errors.push(tr!("\"{}\" must have exactly one king", pieces));
+        }
+
+        let pawns_count = pieces.chars().filter(|piece| *piece == 'P').count();
+        if pawns_count > 8 {
+            errors.push(tr!("\"{}\" has more than 8 pawns", pieces));
+        }
+    }
+
+    if !has_sufficient_material(player_pieces, defender_pieces) {
+        errors.push(tr!(
+            "\"{}\" against \"{}\" can not be won by any side",
+            player_pieces,
+            defender_pieces
+        ));
+    }
+
+    errors
+}
+
+/// Reads and validates the pack file.
+pub fn read_pack(path: &Path) -> Result<ExercisePack, Vec<String>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| vec![tr!("Failed to read {} ({}).", path.display(), err)])?;
+    let pack: ExercisePack =
+        serde_json::from_str(&content).map_err(|err| vec![tr!("Bad pack format ({}).", err)])?;
+    pack.validate()?;
+    Ok(pack)
+}
+
+/// Validates the pack file (JSON, or EPD suite), then copies it into the imported packs.
+pub fn import_pack(path: &Path) -> Result<ExercisePack, Vec<String>> {
+    let file_stem = path
+        .file_stem()
+        .and_then(|file_stem| file_stem.to_str())
+        .ok_or_else(|| vec![tr!("Bad pack file name.")])?;
+    let pack = if is_epd_file(path) {
+        let records = read_epd(path).map_err(|err| vec![format!("{:#}", err)])?;
+        let pack = ExercisePack::from_epd(file_stem, &records);
+        pack.validate()?;
+        pack
+    } else {
+        read_pack(path)?
+    };
+    storage::save_json(&packs_folder().join(format!("{}.json", file_stem)), &pack)
+        .map_err(|err| vec![format!("{:#}", err)])?;
+    Ok(pack)
+}
+
+/// Writes the pack as JSON, or as EPD suite if the file has the epd extension.
+pub fn export_pack(pack: &ExercisePack, path: &Path) -> anyhow::Result<()> {
+    if is_epd_file(path) {
+        write_epd(path, &pack.to_epd()?)
+    } else {
+        storage::save_json(path, pack)
+    }
+}
+
+fn is_epd_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("epd"))
+}
+
+fn get_moves_san(moves: &[Move], position: &Board) -> Vec<String> {
+    moves
+        .iter()
+        .filter_map(|epd_move| epd_move.san(position).ok())
+        .map(|san| san.to_string())
+        .collect()
+}
+
+/// Loads the previously imported packs, skipping the ones which are no longer valid.
+pub fn load_imported_packs() -> Vec<ExercisePack> {
+    let entries = match fs::read_dir(packs_folder()) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut packs: Vec<ExercisePack> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| read_pack(&entry.path()).ok())
+        .collect();
+    packs.sort_by(|first, second| first.name.cmp(&second.name));
+    packs
+}
+
+fn packs_folder() -> PathBuf {
+    storage::data_file("packs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endgame_specs_need_winnable_pieces() {
+        let constraints = GeneratorConstraints::from_spec("kbn-k").unwrap();
+        assert_eq!(constraints.player_pieces, "KBN");
+        assert_eq!(constraints.endgame(), Some(EndgameKind::BishopKnightVsKing));
+
+        for spec in ["KB-K", "K-KN", "K-K", "KQ", "KX-K", "KQ-KK"] {
+            assert!(GeneratorConstraints::from_spec(spec).is_err(), "{}", spec);
+        }
+    }
+
+    const BROKEN_PACK: &str = r#"{
+        "name": " ",
+        "exercises": [
+            {
+                "title": "Both",
+                "fen": "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+                "generator": { "player_pieces": "KR", "defender_pieces": "K" }
+            },
+            { "title": "Bad FEN", "fen": "not a fen" },
+            {
+                "title": "Bad move",
+                "fen": "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+                "best_moves": ["Ra7", "Qh5"]
+            },
+            {
+                "title": "Too strong",
+                "generator": { "player_pieces": "KQ", "defender_pieces": "K" },
+                "computer_strength": { "skill_level": 25 }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn all_pack_errors_are_reported_at_once() {
+        let pack: ExercisePack = serde_json::from_str(BROKEN_PACK).unwrap();
+        let errors = pack.validate().unwrap_err();
+
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert_eq!(errors[0], "The pack name is empty.");
+        assert_eq!(
+            errors[1],
+            "Exercise 1 : both a FEN and a generator are given."
+        );
+        assert!(errors[2].starts_with("Exercise 2 : bad FEN ("));
+        assert_eq!(errors[3], "Exercise 3 : bad move Qh5.");
+        assert_eq!(errors[4], "Exercise 4 : the skill level is above 20.");
+    }
+
+    fn import_errors(path: &Path) -> Vec<String> {
+        match import_pack(path) {
+            Ok(pack) => panic!("{} has been imported", pack.name),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn invalid_pack_is_not_imported() {
+        let folder = std::env::temp_dir().join(format!("packs-test-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let broken_path = folder.join("broken.json");
+        fs::write(&broken_path, BROKEN_PACK).unwrap();
+        let unreadable_path = folder.join("unreadable.json");
+        fs::write(&unreadable_path, "{ not json").unwrap();
+
+        assert_eq!(import_errors(&broken_path).len(), 5);
+        assert!(!packs_folder().join("broken.json").exists());
+        let errors = import_errors(&unreadable_path);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Bad pack format ("));
+        assert!(import_pack(&folder.join("missing.json")).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
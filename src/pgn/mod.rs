@@ -0,0 +1,284 @@
+use owlchess::{Board, Color};
+
+#[derive(Clone, Default)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// Alternatives to this move, each one starting from the position before it.
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Clone, Default)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    /// Comment given before the first move.
+    pub initial_comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: Option<String>,
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Header(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    Result(String),
+    San(String),
+}
+
+/// The NAGs given by a symbol, the others are only written as `$n`.
+pub const NAG_SYMBOLS: [(u8, &str); 8] = [
+    (1, "!"),
+    (2, "?"),
+    (3, "!!"),
+    (4, "??"),
+    (5, "!?"),
+    (6, "?!"),
+    (10, "="),
+    (16, "±"),
+];
+
+pub fn nag_symbol(nag: u8) -> Option<&'static str> {
+    NAG_SYMBOLS
+        .iter()
+        .find(|(symbol_nag, _)| *symbol_nag == nag)
+        .map(|(_, symbol)| *symbol)
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Parses the first game of the PGN text.
+pub fn parse_pgn(text: &str) -> anyhow::Result<PgnGame> {
+    let tokens = tokenize(text)?;
+    let mut game = PgnGame::default();
+    let mut index = 0;
+
+    while let Some(Token::Header(name, value)) = tokens.get(index) {
+        game.headers.push((name.clone(), value.clone()));
+        index += 1;
+    }
+
+    if let Some(Token::Comment(comment)) = tokens.get(index) {
+        game.initial_comment = Some(comment.clone());
+        index += 1;
+    }
+
+    game.moves = parse_moves(&tokens, &mut index)?;
+
+    match tokens.get(index) {
+        Some(Token::Result(result)) => game.result = Some(result.clone()),
+        Some(Token::VariationEnd) => return Err(anyhow::anyhow!("Unexpected ')'.")),
+        _ => {}
+    }
+
+    Ok(game)
+}
+
+/// Writes the game in PGN, the NAGs as `$n` so that any reader understands them.
+pub fn write_pgn(game: &PgnGame) -> String {
+    let mut text = String::new();
+    for (name, value) in &game.headers {
+        text.push_str(&format!("[{} \"{}\"]\n", name, value.replace('"', "\\\"")));
+    }
+    if !game.headers.is_empty() {
+        text.push('\n');
+    }
+
+    let mut words = vec![];
+    if let Some(comment) = &game.initial_comment {
+        words.push(format_comment(comment));
+    }
+    let (move_number, white_to_move) = game
+        .header("FEN")
+        .and_then(|fen| Board::from_fen(fen).ok())
+        .map(|board| (board.raw().move_number, board.side() == Color::White))
+        .unwrap_or((1, true));
+    write_moves(&game.moves, move_number, white_to_move, &mut words);
+    words.push(game.result.clone().unwrap_or_else(|| "*".to_string()));
+
+    text.push_str(&words.join(" "));
+    text.push('\n');
+    text
+}
+
+fn write_moves(
+    moves: &[PgnMove],
+    mut move_number: u16,
+    mut white_to_move: bool,
+    words: &mut Vec<String>,
+) {
+    // A black move only has its number first in its line or after a comment or a variation.
+    let mut needs_number = true;
+    for pgn_move in moves {
+        if white_to_move {
+            words.push(format!("{}.", move_number));
+        } else if needs_number {
+            words.push(format!("{}...", move_number));
+        }
+        words.push(pgn_move.san.clone());
+        words.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+        if let Some(comment) = &pgn_move.comment {
+            words.push(format_comment(comment));
+        }
+        for variation in &pgn_move.variations {
+            words.push("(".to_string());
+            write_moves(variation, move_number, white_to_move, words);
+            words.push(")".to_string());
+        }
+        needs_number = pgn_move.comment.is_some() || !pgn_move.variations.is_empty();
+
+        if !white_to_move {
+            move_number += 1;
+        }
+        white_to_move = !white_to_move;
+    }
+}
+
+/// The braces would end the comment too soon.
+fn format_comment(comment: &str) -> String {
+    format!("{{{}}}", comment.replace(['{', '}'], ""))
+}
+
+fn parse_moves(tokens: &[Token], index: &mut usize) -> anyhow::Result<Vec<PgnMove>> {
+    let mut moves: Vec<PgnMove> = vec![];
+
+    while let Some(token) = tokens.get(*index) {
+        match token {
+            Token::San(san) => moves.push(PgnMove {
+                san: san.clone(),
+                ..Default::default()
+            }),
+            Token::Nag(nag) => {
+                if let Some(last_move) = moves.last_mut() {
+                    last_move.nags.push(*nag);
+                }
+            }
+            Token::Comment(comment) => {
+                if let Some(last_move) = moves.last_mut() {
+                    last_move.comment = Some(match last_move.comment.take() {
+                        Some(previous) => format!("{} {}", previous, comment),
+                        None => comment.clone(),
+                    });
+                }
+            }
+            Token::VariationStart => {
+                *index += 1;
+                let variation = parse_moves(tokens, index)?;
+                if tokens.get(*index) != Some(&Token::VariationEnd) {
+                    return Err(anyhow::anyhow!("Missing ')'."));
+                }
+                match moves.last_mut() {
+                    Some(last_move) => last_move.variations.push(variation),
+                    None => return Err(anyhow::anyhow!("Variation without move.")),
+                }
+            }
+            Token::VariationEnd | Token::Result(_) => return Ok(moves),
+            Token::Header(_, _) => return Err(anyhow::anyhow!("Unexpected header.")),
+        }
+        *index += 1;
+    }
+
+    Ok(moves)
+}
+
+fn tokenize(text: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(current) = chars.next() {
+        match current {
+            '[' => {
+                let content: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let (name, value) = content
+                    .trim()
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow::anyhow!("Bad header [{}].", content))?;
+                tokens.push(Token::Header(
+                    name.to_string(),
+                    value.trim().trim_matches('"').replace("\\\"", "\""),
+                ));
+            }
+            '{' => {
+                let content: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                tokens.push(Token::Comment(
+                    content.split_whitespace().collect::<Vec<_>>().join(" "),
+                ));
+            }
+            ';' => {
+                let content: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                tokens.push(Token::Comment(content.trim().to_string()));
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(*digit);
+                    chars.next();
+                }
+                let nag = digits
+                    .parse::<u8>()
+                    .map_err(|_| anyhow::anyhow!("Bad NAG ${}.", digits))?;
+                tokens.push(Token::Nag(nag));
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut word = current.to_string();
+                while let Some(next) = chars
+                    .peek()
+                    .filter(|c| !c.is_whitespace() && !"{}()[];$".contains(**c))
+                {
+                    word.push(*next);
+                    chars.next();
+                }
+                tokens.extend(tokenize_word(&word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Splits a word of the movetext into move number, SAN and suffix annotation.
+fn tokenize_word(word: &str) -> Vec<Token> {
+    if RESULTS.contains(&word) {
+        return vec![Token::Result(word.to_string())];
+    }
+
+    let without_number = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if without_number.is_empty() {
+        return vec![];
+    }
+
+    let san = without_number.trim_end_matches(['!', '?']);
+    let suffix = &without_number[san.len()..];
+    let mut tokens = vec![Token::San(san.to_string())];
+    if let Some(nag) = suffix_to_nag(suffix) {
+        tokens.push(Token::Nag(nag));
+    }
+    tokens
+}
+
+fn suffix_to_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
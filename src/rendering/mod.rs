@@ -0,0 +1,492 @@
+use std::f64::consts::PI;
+use std::fs::File as FsFile;
+use std::path::Path;
+
+use anyhow::{self, Context as AnyhowContext};
+use gtk::cairo::{Context, Format, ImageSurface, SvgSurface};
+use owlchess::{Board, Color, Coord, File, Rank};
+
+pub(crate) mod pieces_images;
+
+use self::pieces_images::PiecesImages;
+
+pub type Rgb = (f64, f64, f64);
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub background: Rgb,
+    pub light_cells: Rgb,
+    pub dark_cells: Rgb,
+    pub coordinates: Rgb,
+    pub highlight: Rgb,
+    pub arrow: Rgb,
+}
+
+impl Theme {
+    /// The colors of the game board.
+    pub const CLASSIC: Theme = Theme {
+        name: "classic",
+        background: (0.3, 0.3, 0.8),
+        light_cells: (1.0, 0.87, 0.68),
+        dark_cells: (0.8, 0.52, 0.25),
+        coordinates: (0.78, 0.78, 0.47),
+        highlight: (0.56, 0.93, 0.56),
+        arrow: (0.18, 0.55, 0.34),
+    };
+
+    pub const GREEN: Theme = Theme {
+        name: "green",
+        background: (0.2, 0.3, 0.2),
+        light_cells: (0.93, 0.93, 0.82),
+        dark_cells: (0.46, 0.59, 0.34),
+        coordinates: (0.93, 0.93, 0.82),
+        highlight: (0.97, 0.97, 0.41),
+        arrow: (0.8, 0.36, 0.36),
+    };
+
+    /// Light enough to be printed.
+    pub const PRINT: Theme = Theme {
+        name: "print",
+        background: (1.0, 1.0, 1.0),
+        light_cells: (1.0, 1.0, 1.0),
+        dark_cells: (0.7, 0.7, 0.7),
+        coordinates: (0.0, 0.0, 0.0),
+        highlight: (0.85, 0.85, 0.5),
+        arrow: (0.3, 0.3, 0.3),
+    };
+
+    pub const ALL: [Theme; 3] = [Theme::CLASSIC, Theme::GREEN, Theme::PRINT];
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.iter().find(|theme| theme.name == name).copied()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Highlight {
+    pub cell: Coord,
+    pub color: Rgb,
+}
+
+#[derive(Clone, Copy)]
+pub struct Arrow {
+    pub start: Coord,
+    pub target: Coord,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("png") => Ok(ImageFormat::Png),
+            Some("svg") => Ok(ImageFormat::Svg),
+            _ => Err(anyhow::anyhow!(
+                "Unknown image format for {} (expected .png or .svg).",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A board position with its decorations, drawn without any widget.
+#[derive(Clone)]
+pub struct Diagram {
+    pub board: Board,
+    pub reversed: bool,
+    /// When several highlights are given for a cell, the last one is shown.
+    pub highlights: Vec<Highlight>,
+    pub arrows: Vec<Arrow>,
+    pub theme: Theme,
+}
+
+impl Diagram {
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            reversed: false,
+            highlights: vec![],
+            arrows: vec![],
+            theme: Theme::CLASSIC,
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> anyhow::Result<Self> {
+        let board = Board::from_fen(fen).with_context(|| format!("Bad FEN {}.", fen))?;
+        Ok(Diagram::new(board))
+    }
+
+    /// Highlights the cell with the color of the theme.
+    pub fn highlight(&mut self, cell: Coord) {
+        self.highlights.push(Highlight {
+            cell,
+            color: self.theme.highlight,
+        });
+    }
+
+    /// Draws the diagram in a square of the given size. The piece of the hidden cell,
+    /// if any, is not drawn (e.g. because it is being dragged).
+    pub(crate) fn draw(
+        &self,
+        cx: &Context,
+        size: f64,
+        pieces_images: &PiecesImages,
+        hidden_cell: Option<Coord>,
+    ) -> anyhow::Result<()> {
+        let cells_size = size * 0.111;
+
+        cx.set_source_rgb(
+            self.theme.background.0,
+            self.theme.background.1,
+            self.theme.background.2,
+        );
+        cx.rectangle(0.0, 0.0, size, size);
+        cx.fill()?;
+
+        self.paint_cells(cx, cells_size)?;
+        self.draw_coordinates(cx, cells_size)?;
+        self.paint_pieces(cx, cells_size, pieces_images, hidden_cell);
+        for arrow in &self.arrows {
+            self.draw_arrow(cx, cells_size, arrow)?;
+        }
+        self.draw_player_turn(cx, cells_size)?;
+
+        Ok(())
+    }
+
+    pub fn render_image(&self, size: i32) -> anyhow::Result<ImageSurface> {
+        let image = ImageSurface::create(Format::ARgb32, size, size)?;
+        let context = Context::new(&image)?;
+        let pieces_images = PiecesImages::new(get_cells_size(size))?;
+        self.draw(&context, size as f64, &pieces_images, None)?;
+        drop(context);
+        Ok(image)
+    }
+
+    /// Writes the diagram as PNG or SVG, depending on the extension of the path.
+    pub fn write(&self, path: &Path, size: i32) -> anyhow::Result<()> {
+        match ImageFormat::from_path(path)? {
+            ImageFormat::Png => {
+                let image = self.render_image(size)?;
+                let mut file = FsFile::create(path)
+                    .with_context(|| format!("Failed to create {}.", path.display()))?;
+                image
+                    .write_to_png(&mut file)
+                    .with_context(|| format!("Failed to write {}.", path.display()))?;
+            }
+            ImageFormat::Svg => {
+                let surface = SvgSurface::new(size as f64, size as f64, Some(path))
+                    .with_context(|| format!("Failed to create {}.", path.display()))?;
+                let context = Context::new(&surface)?;
+                let pieces_images = PiecesImages::new(get_cells_size(size))?;
+                self.draw(&context, size as f64, &pieces_images, None)?;
+                drop(context);
+                surface.finish();
+            }
+        }
+        Ok(())
+    }
+
+    fn paint_cells(&self, cx: &Context, cells_size: f64) -> anyhow::Result<()> {
+        for row in 0..8 {
+            for col in 0..8 {
+                let cell = self.get_cell_at(col, row);
+                let is_white_cell = (row + col) % 2 == 0;
+                let default_color = if is_white_cell {
+                    self.theme.light_cells
+                } else {
+                    self.theme.dark_cells
+                };
+                let background_color = self
+                    .highlights
+                    .iter()
+                    .rev()
+                    .find(|highlight| highlight.cell == cell)
+                    .map_or(default_color, |highlight| highlight.color);
+
+                let x = cells_size * (col as f64 + 0.5);
+                let y = cells_size * (row as f64 + 0.5);
+
+                cx.set_source_rgb(background_color.0, background_color.1, background_color.2);
+                cx.rectangle(x, y, cells_size, cells_size);
+                cx.fill()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_coordinates(&self, cx: &Context, cells_size: f64) -> anyhow::Result<()> {
+        let color = self.theme.coordinates;
+        cx.set_source_rgb(color.0, color.1, color.2);
+        cx.set_font_size(cells_size * 0.3);
+        for col in 0..8 {
+            let file = if self.reversed { 7 - col } else { col };
+            let file_string = File::from_index(file)
+                .as_char()
+                .to_ascii_uppercase()
+                .to_string();
+
+            let x = cells_size * (0.9 + col as f64);
+            let y1 = cells_size * 0.35;
+            let y2 = cells_size * 8.85;
+
+            cx.move_to(x, y1);
+            cx.show_text(&file_string)?;
+
+            cx.move_to(x, y2);
+            cx.show_text(&file_string)?;
+        }
+
+        for row in 0..8 {
+            let rank = if self.reversed { row } else { 7 - row };
+            let rank_string = (rank + 1).to_string();
+
+            let y = cells_size * (1.15 + row as f64);
+            let x1 = cells_size * 0.15;
+            let x2 = cells_size * 8.65;
+
+            cx.move_to(x1, y);
+            cx.show_text(&rank_string)?;
+
+            cx.move_to(x2, y);
+            cx.show_text(&rank_string)?;
+        }
+        Ok(())
+    }
+
+    fn paint_pieces(
+        &self,
+        cx: &Context,
+        cells_size: f64,
+        pieces_images: &PiecesImages,
+        hidden_cell: Option<Coord>,
+    ) {
+        for row in 0..8 {
+            for col in 0..8 {
+                let cell = self.get_cell_at(col, row);
+                let square = self.board.get(cell);
+                if square.is_free() || hidden_cell == Some(cell) {
+                    continue;
+                }
+
+                let x = cells_size * (col as f64 + 0.5);
+                let y = cells_size * (row as f64 + 0.5);
+                pieces_images.draw(cx, square.as_char(), x, y);
+            }
+        }
+    }
+
+    fn draw_arrow(&self, cx: &Context, cells_size: f64, arrow: &Arrow) -> anyhow::Result<()> {
+        let (start_x, start_y) = self.get_cell_center(cells_size, arrow.start);
+        let (target_x, target_y) = self.get_cell_center(cells_size, arrow.target);
+        let angle = (target_y - start_y).atan2(target_x - start_x);
+        let head_length = cells_size * 0.4;
+        let head_width = cells_size * 0.25;
+
+        // The line stops at the base of the head, so that the head keeps its tip.
+        let base_x = target_x - head_length * angle.cos();
+        let base_y = target_y - head_length * angle.sin();
+
+        let color = self.theme.arrow;
+        cx.set_source_rgba(color.0, color.1, color.2, 0.8);
+        cx.set_line_width(cells_size * 0.15);
+        cx.move_to(start_x, start_y);
+        cx.line_to(base_x, base_y);
+        cx.stroke()?;
+
+        cx.move_to(target_x, target_y);
+        cx.line_to(
+            base_x + head_width * (angle + PI / 2.0).cos(),
+            base_y + head_width * (angle + PI / 2.0).sin(),
+        );
+        cx.line_to(
+            base_x + head_width * (angle - PI / 2.0).cos(),
+            base_y + head_width * (angle - PI / 2.0).sin(),
+        );
+        cx.close_path();
+        cx.fill()?;
+        Ok(())
+    }
+
+    fn draw_player_turn(&self, cx: &Context, cells_size: f64) -> anyhow::Result<()> {
+        let color = if self.board.side() == Color::White {
+            (1.0, 1.0, 1.0)
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+        let location = cells_size * 8.75;
+        let radius = cells_size * 0.25;
+
+        cx.set_source_rgb(color.0, color.1, color.2);
+        cx.arc(location, location, radius, 0.0, 2.0 * PI);
+        cx.fill()?;
+        Ok(())
+    }
+
+    fn get_cell_at(&self, col: usize, row: usize) -> Coord {
+        let file = if self.reversed { 7 - col } else { col };
+        let rank = if self.reversed { 7 - row } else { row };
+        Coord::from_parts(File::from_index(file), Rank::from_index(rank))
+    }
+
+    fn get_cell_center(&self, cells_size: f64, cell: Coord) -> (f64, f64) {
+        let file = cell.file().index();
+        let rank = cell.rank().index();
+        let col = if self.reversed { 7 - file } else { file };
+        let row = if self.reversed { 7 - rank } else { rank };
+        (
+            cells_size * (col as f64 + 1.0),
+            cells_size * (row as f64 + 1.0),
+        )
+    }
+}
+
+fn get_cells_size(size: i32) -> i32 {
+    (size as f64 * 0.111) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const SIZE: i32 = 360;
+
+    /// Color, in 0-255 components, of the top left corner of the cell, where no piece is drawn.
+    fn cell_color(image: &mut ImageSurface, reversed: bool, cell: &str) -> (u8, u8, u8) {
+        let cell = Coord::from_str(cell).unwrap();
+        let cells_size = SIZE as f64 * 0.111;
+        let file = cell.file().index();
+        let rank = cell.rank().index();
+        let col = if reversed { 7 - file } else { file };
+        let row = if reversed { 7 - rank } else { rank };
+        let x = (cells_size * (col as f64 + 0.5)) as usize + 3;
+        let y = (cells_size * (row as f64 + 0.5)) as usize + 3;
+
+        let stride = image.stride() as usize;
+        let data = image.data().unwrap();
+        let offset = y * stride + x * 4;
+        let pixel = u32::from_ne_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]);
+        ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+    }
+
+    fn assert_color(actual: (u8, u8, u8), expected: Rgb) {
+        let expected = (
+            (expected.0 * 255.0).round() as i16,
+            (expected.1 * 255.0).round() as i16,
+            (expected.2 * 255.0).round() as i16,
+        );
+        let close = |a: u8, b: i16| (a as i16 - b).abs() <= 1;
+        assert!(
+            close(actual.0, expected.0)
+                && close(actual.1, expected.1)
+                && close(actual.2, expected.2),
+            "Got {:?}, expected {:?}.",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn cells_follow_the_theme() {
+        for theme in Theme::ALL {
+            let mut diagram = Diagram::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+            diagram.theme = theme;
+            let mut image = diagram.render_image(SIZE).unwrap();
+
+            assert_color(cell_color(&mut image, false, "a1"), theme.dark_cells);
+            assert_color(cell_color(&mut image, false, "h1"), theme.light_cells);
+            assert_color(cell_color(&mut image, false, "a8"), theme.light_cells);
+        }
+    }
+
+    #[test]
+    fn last_highlight_of_a_cell_is_shown() {
+        let mut diagram = Diagram::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        diagram.highlight(Coord::from_str("e2").unwrap());
+        diagram.highlights.push(Highlight {
+            cell: Coord::from_str("e4").unwrap(),
+            color: (1.0, 0.0, 0.0),
+        });
+        diagram.highlights.push(Highlight {
+            cell: Coord::from_str("e4").unwrap(),
+            color: (0.0, 0.0, 1.0),
+        });
+        let mut image = diagram.render_image(SIZE).unwrap();
+
+        assert_color(
+            cell_color(&mut image, false, "e2"),
+            Theme::CLASSIC.highlight,
+        );
+        assert_color(cell_color(&mut image, false, "e4"), (0.0, 0.0, 1.0));
+        assert_color(
+            cell_color(&mut image, false, "e3"),
+            Theme::CLASSIC.dark_cells,
+        );
+    }
+
+    #[test]
+    fn reversed_diagram_puts_black_at_the_bottom() {
+        let mut diagram = Diagram::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        diagram.reversed = true;
+        diagram.highlight(Coord::from_str("b1").unwrap());
+        let mut image = diagram.render_image(SIZE).unwrap();
+
+        assert_color(cell_color(&mut image, true, "b1"), Theme::CLASSIC.highlight);
+        // b1 is shown where g8 is when not reversed.
+        assert_color(
+            cell_color(&mut image, false, "g8"),
+            Theme::CLASSIC.highlight,
+        );
+    }
+
+    #[test]
+    fn bad_fen_is_rejected() {
+        assert!(Diagram::from_fen("not a position").is_err());
+    }
+
+    #[test]
+    fn format_is_chosen_from_extension() {
+        assert!(ImageFormat::from_path(Path::new("board.png")).unwrap() == ImageFormat::Png);
+        assert!(ImageFormat::from_path(Path::new("board.SVG")).unwrap() == ImageFormat::Svg);
+        assert!(ImageFormat::from_path(Path::new("board.jpg")).is_err());
+        assert!(ImageFormat::from_path(Path::new("board")).is_err());
+    }
+
+    #[test]
+    fn diagram_is_written_as_png_or_svg() {
+        let folder = std::env::temp_dir();
+        let mut diagram = Diagram::from_fen("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1").unwrap();
+        diagram.arrows.push(Arrow {
+            start: Coord::from_str("e2").unwrap(),
+            target: Coord::from_str("e4").unwrap(),
+        });
+
+        let png_path = folder.join(format!("diagram-{}.png", std::process::id()));
+        diagram.write(&png_path, SIZE).unwrap();
+        let png = std::fs::read(&png_path).unwrap();
+        std::fs::remove_file(&png_path).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        let svg_path = folder.join(format!("diagram-{}.svg", std::process::id()));
+        diagram.write(&svg_path, SIZE).unwrap();
+        let svg = std::fs::read_to_string(&svg_path).unwrap();
+        std::fs::remove_file(&svg_path).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+}
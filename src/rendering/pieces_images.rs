@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
+use gtk::cairo::Context as CairoContext;
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::MemoryInputStream;
 use gtk::glib::Bytes;
+use gtk::prelude::*;
 
 use anyhow::{self, Context};
 
@@ -19,6 +21,13 @@ impl PiecesImages {
         Ok(Self { pixbufs })
     }
 
+    /// Draws the piece, given by its FEN letter, with its top left corner at (x, y).
+    pub(crate) fn draw(&self, cx: &CairoContext, piece_type: char, x: f64, y: f64) {
+        let pixbuf = &self.pixbufs[&piece_type];
+        cx.set_source_pixbuf(pixbuf, x, y);
+        cx.paint().unwrap();
+    }
+
     pub(crate) fn build_streams() -> HashMap<char, MemoryInputStream> {
         let mut result = HashMap::new();
         let pieces_types = vec!['P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
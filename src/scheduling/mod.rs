@@ -0,0 +1,179 @@
+use crate::exercises::EndgameKind;
+use crate::statistics::ExerciseResult;
+
+pub const SECONDS_PER_DAY: u64 = 24 * 3600;
+
+const INITIAL_EASINESS: f64 = 2.5;
+const MINIMUM_EASINESS: f64 = 1.3;
+
+/// Spaced repetition state of an endgame, following the SM-2 algorithm.
+#[derive(Clone, Copy)]
+pub struct DrillState {
+    pub kind: EndgameKind,
+    pub repetitions: u32,
+    pub easiness: f64,
+    pub interval_days: u32,
+    /// Seconds since the Unix epoch, None if the endgame has never been practiced.
+    pub due_date: Option<u64>,
+}
+
+impl DrillState {
+    fn new(kind: EndgameKind) -> Self {
+        Self {
+            kind,
+            repetitions: 0,
+            easiness: INITIAL_EASINESS,
+            interval_days: 0,
+            due_date: None,
+        }
+    }
+
+    fn review(&mut self, quality: u8, date: u64) {
+        if quality >= 3 {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f64 * self.easiness).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        }
+
+        let quality_gap = (5 - quality) as f64;
+        self.easiness += 0.1 - quality_gap * (0.08 + quality_gap * 0.02);
+        if self.easiness < MINIMUM_EASINESS {
+            self.easiness = MINIMUM_EASINESS;
+        }
+
+        self.due_date = Some(date + self.interval_days as u64 * SECONDS_PER_DAY);
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        match self.due_date {
+            Some(due_date) => due_date <= now,
+            None => true,
+        }
+    }
+}
+
+/// Grades an attempt between 0 and 5, as expected by SM-2.
+fn grade(result: &ExerciseResult) -> u8 {
+    if !result.success {
+        return if result.moves_played > 0 { 1 } else { 0 };
+    }
+    let moves_over_optimum = result.moves_over_optimum().unwrap_or(0);
+    match (result.hints_used, moves_over_optimum) {
+        (0, 0..=5) => 5,
+        (0, _) | (1, _) => 4,
+        _ => 3,
+    }
+}
+
+/// Replays all the results in order to give the drill state of each endgame,
+/// the next one to practice first.
+pub fn schedule(results: &[ExerciseResult]) -> Vec<DrillState> {
+    let mut states: Vec<DrillState> = EndgameKind::ALL
+        .iter()
+        .map(|kind| DrillState::new(*kind))
+        .collect();
+
+    let mut sorted_results: Vec<&ExerciseResult> = results.iter().collect();
+    sorted_results.sort_by_key(|result| result.date);
+
+    for result in sorted_results {
+        if let Some(state) = states.iter_mut().find(|state| state.kind == result.kind) {
+            state.review(grade(result), result.date);
+        }
+    }
+
+    states.sort_by_key(|state| state.due_date.unwrap_or(0));
+    states
+}
+
+pub fn next_due_drill(results: &[ExerciseResult]) -> EndgameKind {
+    schedule(results)[0].kind
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(kind: EndgameKind, date: u64, success: bool) -> ExerciseResult {
+        ExerciseResult {
+            kind,
+            date,
+            success,
+            moves_played: 10,
+            optimum_moves: Some(8),
+            duration_secs: 60,
+            hints_used: 0,
+            difficulty_score: None,
+        }
+    }
+
+    #[test]
+    fn attempts_are_graded_by_success_hints_and_extra_moves() {
+        let mut attempt = result(EndgameKind::RookVsKing, 0, true);
+        assert_eq!(grade(&attempt), 5);
+        attempt.moves_played = 20;
+        assert_eq!(grade(&attempt), 4);
+        attempt.hints_used = 2;
+        assert_eq!(grade(&attempt), 3);
+        attempt.success = false;
+        assert_eq!(grade(&attempt), 1);
+        attempt.moves_played = 0;
+        assert_eq!(grade(&attempt), 0);
+    }
+
+    #[test]
+    fn intervals_grow_with_the_easiness() {
+        let mut state = DrillState::new(EndgameKind::QueenVsKing);
+
+        state.review(5, 0);
+        assert_eq!(state.interval_days, 1);
+        assert_eq!(state.due_date, Some(SECONDS_PER_DAY));
+        state.review(5, 0);
+        assert_eq!(state.interval_days, 6);
+        let easiness = state.easiness;
+        state.review(5, 0);
+        assert_eq!(state.interval_days, (6.0 * easiness).round() as u32);
+        assert_eq!(state.repetitions, 3);
+    }
+
+    #[test]
+    fn failed_attempt_resets_the_repetitions() {
+        let mut state = DrillState::new(EndgameKind::QueenVsKing);
+        state.review(5, 0);
+        state.review(5, 0);
+
+        state.review(1, 10);
+        assert_eq!(state.repetitions, 0);
+        assert_eq!(state.interval_days, 1);
+        assert_eq!(state.due_date, Some(10 + SECONDS_PER_DAY));
+    }
+
+    #[test]
+    fn easiness_does_not_fall_below_its_floor() {
+        let mut state = DrillState::new(EndgameKind::QueenVsKing);
+        for _ in 0..10 {
+            state.review(0, 0);
+        }
+        assert_eq!(state.easiness, MINIMUM_EASINESS);
+    }
+
+    #[test]
+    fn most_overdue_drill_comes_next() {
+        let day = SECONDS_PER_DAY;
+        let mut results: Vec<ExerciseResult> = EndgameKind::ALL
+            .iter()
+            .map(|kind| result(*kind, 10 * day, true))
+            .collect();
+        results[2].date = 3 * day;
+        assert_eq!(next_due_drill(&results), EndgameKind::TwoBishopsVsKing);
+
+        results.retain(|result| result.kind != EndgameKind::QueenVsRook);
+        assert_eq!(next_due_drill(&results), EndgameKind::QueenVsRook);
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::EngineStrength;
+use crate::exercises::filter::GenerationFilter;
+use crate::storage;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Settings {
+    pub engine_path: Option<PathBuf>,
+    /// Applied to the random positions of the built-in endgames.
+    #[serde(default)]
+    pub generation_filter: Option<GenerationFilter>,
+    /// Strength of the computer side for the exercises not giving one.
+    #[serde(default)]
+    pub computer_strength: EngineStrength,
+    /// Thinking time of the player for a whole game, in seconds : unlimited when not given.
+    #[serde(default)]
+    pub player_time_secs: Option<u64>,
+}
+
+impl Settings {
+    pub fn load() -> anyhow::Result<Self> {
+        storage::load_json(&Settings::file_path())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        storage::save_json(&Settings::file_path(), self)
+    }
+
+    fn file_path() -> PathBuf {
+        storage::config_file("settings.json")
+    }
+}
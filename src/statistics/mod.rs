@@ -0,0 +1,232 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::exercises::EndgameKind;
+use crate::storage;
+
+pub const SECONDS_PER_WEEK: u64 = 7 * 24 * 3600;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExerciseResult {
+    pub kind: EndgameKind,
+    /// Seconds since the Unix epoch.
+    pub date: u64,
+    pub success: bool,
+    pub moves_played: u32,
+    pub optimum_moves: Option<u32>,
+    pub duration_secs: u64,
+    pub hints_used: u32,
+    /// Difficulty score of the start position, when it has been rated.
+    #[serde(default)]
+    pub difficulty_score: Option<u32>,
+}
+
+impl ExerciseResult {
+    pub fn moves_over_optimum(&self) -> Option<u32> {
+        if !self.success {
+            return None;
+        }
+        let optimum_moves = self.optimum_moves?;
+        Some(self.moves_played.saturating_sub(optimum_moves))
+    }
+}
+
+pub struct EndgameSummary {
+    pub attempts: usize,
+    pub successes: usize,
+    pub average_moves_over_optimum: Option<f64>,
+    pub time_spent_secs: u64,
+    pub hints_used: u32,
+    /// Success rate of each of the last weeks, the oldest first.
+    pub weekly_success_rates: Vec<Option<f64>>,
+}
+
+impl EndgameSummary {
+    pub fn success_rate(&self) -> Option<f64> {
+        if self.attempts == 0 {
+            None
+        } else {
+            Some(self.successes as f64 / self.attempts as f64)
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Statistics {
+    results: Vec<ExerciseResult>,
+}
+
+impl Statistics {
+    pub const TREND_WEEKS: u64 = 8;
+
+    pub fn load() -> anyhow::Result<Self> {
+        storage::load_json(&Statistics::file_path())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        storage::save_json(&Statistics::file_path(), self)
+    }
+
+    pub fn add_result(&mut self, result: ExerciseResult) {
+        self.results.push(result);
+    }
+
+    pub fn results(&self) -> &[ExerciseResult] {
+        &self.results
+    }
+
+    pub fn summary(&self, kind: EndgameKind) -> EndgameSummary {
+        self.summary_at(kind, current_timestamp())
+    }
+
+    /// The weekly trend ends at the given timestamp.
+    fn summary_at(&self, kind: EndgameKind, now: u64) -> EndgameSummary {
+        let results: Vec<&ExerciseResult> = self
+            .results
+            .iter()
+            .filter(|result| result.kind == kind)
+            .collect();
+
+        let moves_over_optimum: Vec<u32> = results
+            .iter()
+            .filter_map(|result| result.moves_over_optimum())
+            .collect();
+        let average_moves_over_optimum = if moves_over_optimum.is_empty() {
+            None
+        } else {
+            let total: u32 = moves_over_optimum.iter().sum();
+            Some(total as f64 / moves_over_optimum.len() as f64)
+        };
+
+        let weekly_success_rates = (0..Statistics::TREND_WEEKS)
+            .rev()
+            .map(|weeks_ago| {
+                let week_end = now.saturating_sub(weeks_ago * SECONDS_PER_WEEK);
+                let week_start = week_end.saturating_sub(SECONDS_PER_WEEK);
+                let week_results: Vec<&&ExerciseResult> = results
+                    .iter()
+                    .filter(|result| result.date > week_start && result.date <= week_end)
+                    .collect();
+                if week_results.is_empty() {
+                    None
+                } else {
+                    let successes = week_results.iter().filter(|result| result.success).count();
+                    Some(successes as f64 / week_results.len() as f64)
+                }
+            })
+            .collect();
+
+        EndgameSummary {
+            attempts: results.len(),
+            successes: results.iter().filter(|result| result.success).count(),
+            average_moves_over_optimum,
+            time_spent_secs: results.iter().map(|result| result.duration_secs).sum(),
+            hints_used: results.iter().map(|result| result.hints_used).sum(),
+            weekly_success_rates,
+        }
+    }
+
+    fn file_path() -> PathBuf {
+        storage::data_file("statistics.json")
+    }
+}
+
+pub fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 100 * SECONDS_PER_WEEK;
+
+    fn result(
+        kind: EndgameKind,
+        date: u64,
+        success: bool,
+        optimum_moves: Option<u32>,
+    ) -> ExerciseResult {
+        ExerciseResult {
+            kind,
+            date,
+            success,
+            moves_played: 12,
+            optimum_moves,
+            duration_secs: 60,
+            hints_used: 1,
+            difficulty_score: None,
+        }
+    }
+
+    #[test]
+    fn empty_history_gives_no_rates() {
+        let summary = Statistics::default().summary_at(EndgameKind::RookVsKing, NOW);
+
+        assert_eq!(summary.attempts, 0);
+        assert_eq!(summary.success_rate(), None);
+        assert_eq!(summary.average_moves_over_optimum, None);
+        assert_eq!(summary.time_spent_secs, 0);
+        assert_eq!(
+            summary.weekly_success_rates,
+            vec![None; Statistics::TREND_WEEKS as usize]
+        );
+    }
+
+    #[test]
+    fn results_are_bucketed_by_week_the_latest_last() {
+        let mut statistics = Statistics::default();
+        let kind = EndgameKind::QueenVsKing;
+        statistics.add_result(result(kind, NOW, true, None));
+        // Exactly one week ago still belongs to the last week.
+        statistics.add_result(result(kind, NOW - SECONDS_PER_WEEK + 1, false, None));
+        statistics.add_result(result(kind, NOW - SECONDS_PER_WEEK, true, None));
+        statistics.add_result(result(kind, NOW - 7 * SECONDS_PER_WEEK - 1, true, None));
+        statistics.add_result(result(kind, NOW - 8 * SECONDS_PER_WEEK, true, None));
+        statistics.add_result(result(EndgameKind::RookVsKing, NOW, false, None));
+
+        let summary = statistics.summary_at(kind, NOW);
+        assert_eq!(summary.attempts, 5);
+        assert_eq!(summary.successes, 4);
+        assert_eq!(summary.time_spent_secs, 300);
+        assert_eq!(summary.hints_used, 5);
+
+        let rates = summary.weekly_success_rates;
+        assert_eq!(rates.len(), Statistics::TREND_WEEKS as usize);
+        assert_eq!(rates[7], Some(0.5));
+        assert_eq!(rates[6], Some(1.0));
+        assert_eq!(rates[0], Some(1.0));
+        assert!(rates[1..6].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn moves_over_optimum_need_a_known_optimum_and_a_success() {
+        let kind = EndgameKind::RookVsKing;
+        assert_eq!(
+            result(kind, NOW, true, Some(10)).moves_over_optimum(),
+            Some(2)
+        );
+        assert_eq!(
+            result(kind, NOW, true, Some(15)).moves_over_optimum(),
+            Some(0)
+        );
+        assert_eq!(result(kind, NOW, true, None).moves_over_optimum(), None);
+        assert_eq!(
+            result(kind, NOW, false, Some(10)).moves_over_optimum(),
+            None
+        );
+
+        let mut statistics = Statistics::default();
+        statistics.add_result(result(kind, NOW, true, Some(10)));
+        statistics.add_result(result(kind, NOW, true, Some(9)));
+        statistics.add_result(result(kind, NOW, true, None));
+        statistics.add_result(result(kind, NOW, false, Some(1)));
+        let summary = statistics.summary_at(kind, NOW);
+        assert_eq!(summary.average_moves_over_optimum, Some(2.5));
+    }
+}
@@ -0,0 +1,90 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{self, Context};
+use gtk::glib;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APPLICATION_FOLDER: &str = "basic-chess-endgames";
+
+pub fn config_file(name: &str) -> PathBuf {
+    glib::user_config_dir().join(APPLICATION_FOLDER).join(name)
+}
+
+pub fn data_file(name: &str) -> PathBuf {
+    glib::user_data_dir().join(APPLICATION_FOLDER).join(name)
+}
+
+/// Reads the value stored in the file, or the default value if the file is missing.
+/// A file which can not be read is renamed with a `.bak` extension, so that saving
+/// the default value does not overwrite it : the error tells where it has been kept.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> anyhow::Result<T> {
+    let error = match fs::read_to_string(path) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(value) => return Ok(value),
+            Err(err) => err.to_string(),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(err) => err.to_string(),
+    };
+    let backup = backup_path(path);
+    fs::rename(path, &backup).with_context(|| {
+        format!(
+            "Failed to read {} ({}), nor to rename it as {}.",
+            path.display(),
+            error,
+            backup.display()
+        )
+    })?;
+    Err(anyhow::anyhow!(
+        "Failed to read {} ({}) : it has been kept as {}.",
+        path.display(),
+        error,
+        backup.display()
+    ))
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)
+            .with_context(|| format!("Failed to create folder {}.", folder.display()))?;
+    }
+    let content =
+        serde_json::to_string_pretty(value).with_context(|| "Failed to serialize data.")?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}.", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_file_is_kept_apart() {
+        let folder = std::env::temp_dir().join(format!("storage-test-{}", std::process::id()));
+        let path = folder.join("statistics.json");
+        let backup = folder.join("statistics.json.bak");
+        let _ = fs::remove_dir_all(&folder);
+
+        let missing: anyhow::Result<Vec<u32>> = load_json(&path);
+        assert_eq!(missing.unwrap(), Vec::<u32>::new());
+
+        save_json(&path, &vec![1, 2]).unwrap();
+        let saved: Vec<u32> = load_json(&path).unwrap();
+        assert_eq!(saved, vec![1, 2]);
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(load_json::<Vec<u32>>(&path).is_err());
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "{ not json");
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use anyhow::{self, Context};
+use owlchess::{Board, Color, File, Move, Rank};
+use tr::tr;
+
+use crate::engine::{accepts_draw, UciEngine};
+use crate::exercises::Exercise;
+use crate::game::{describe_outcome, GameEvent, GameSession, GameStatus};
+use crate::widgets::history::utils::{piece_glyph, san_to_fan};
+
+const QUIT_COMMAND: &str = "quit";
+const CLAIM_DRAW_COMMAND: &str = "claim";
+const RESIGN_COMMAND: &str = "resign";
+
+/// ANSI 256 colors of the cells, close to the ones of the graphical board.
+const LIGHT_CELL_COLOR: u8 = 223;
+const DARK_CELL_COLOR: u8 = 173;
+const PIECES_COLOR: u8 = 16;
+
+/// Lines of history shown beside the board.
+const HISTORY_LINES: usize = 9;
+
+/// The moves played, as figurines, the first one being played by the side
+/// to move of the start position.
+struct MovesHistory {
+    first_move_number: u16,
+    black_starts: bool,
+    moves: Vec<String>,
+}
+
+impl MovesHistory {
+    fn new(start_position: &Board) -> Self {
+        Self {
+            first_move_number: start_position.raw().move_number,
+            black_starts: start_position.side() == Color::Black,
+            moves: vec![],
+        }
+    }
+
+    /// One line per move number (e.g. "1. e4 e5").
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        let mut moves = self.moves.iter();
+        let mut move_number = self.first_move_number;
+
+        if self.black_starts {
+            if let Some(black_move) = moves.next() {
+                lines.push(format!("{}. ... {}", move_number, black_move));
+                move_number += 1;
+            }
+        }
+
+        while let Some(white_move) = moves.next() {
+            let line = match moves.next() {
+                Some(black_move) => format!("{}. {} {}", move_number, white_move, black_move),
+                None => format!("{}. {}", move_number, white_move),
+            };
+            lines.push(line);
+            move_number += 1;
+        }
+
+        lines
+    }
+}
+
+/// Plays the exercise against the engine, reading the player moves on the standard input.
+/// The colors and the screen clearing are only used when the output is a terminal.
+pub fn play(exercise: Exercise, engine_path: &Path) -> anyhow::Result<()> {
+    let mut engine = UciEngine::new(engine_path)?;
+    let mut session = GameSession::default();
+    let mut history = MovesHistory::new(&exercise.start_position);
+    let computer_strength = exercise.computer_strength.unwrap_or_default();
+    session.start(exercise.start_position, exercise.player_side);
+
+    let ansi = io::stdout().is_terminal();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut message = tr!(
+        "Type your moves in SAN (e.g. Qe7) or UCI (e.g. d6e7) notation, {} to claim a draw, {} to resign, or {} to stop.",
+        CLAIM_DRAW_COMMAND,
+        RESIGN_COMMAND,
+        QUIT_COMMAND
+    );
+
+    while session.is_in_progress() {
+        if !session.is_player_turn() {
+            let analysis = engine.computer_move(
+                session.moves_chain(),
+                UciEngine::COMPUTER_MOVE_TIME_MS,
+                &computer_strength,
+            )?;
+            if session.claimable_draw().is_some() && accepts_draw(analysis.score, true) {
+                session.claim_draw()?;
+                continue;
+            }
+            let san = session.play_move(analysis.best_move)?;
+            message = tr!("Computer played {}.", san);
+            record_moves(&mut session, &mut history);
+            continue;
+        }
+
+        print!("{}", format_screen(&session, &history, &message, ansi));
+        print!("> ");
+        io::stdout()
+            .flush()
+            .with_context(|| "Failed to write the prompt.")?;
+
+        let line = match lines.next() {
+            Some(line) => line.with_context(|| "Failed to read the move.")?,
+            None => break,
+        };
+        let line = line.trim();
+        if line == QUIT_COMMAND {
+            session.stop();
+            break;
+        }
+        if line == RESIGN_COMMAND {
+            session.resign()?;
+            break;
+        }
+        if line == CLAIM_DRAW_COMMAND {
+            if session.claim_draw().is_err() {
+                message = tr!("No draw can be claimed.");
+            }
+            continue;
+        }
+
+        match parse_player_move(line, session.board()) {
+            Some(player_move) => {
+                session.play_move(player_move)?;
+                message.clear();
+                record_moves(&mut session, &mut history);
+            }
+            None => message = tr!("Illegal move {}.", line),
+        }
+    }
+
+    if let GameStatus::Over(outcome) = session.status() {
+        let success = exercise.goal.is_reached(&outcome, exercise.player_side);
+        let message = format!(
+            "{}\n{}",
+            describe_outcome(outcome),
+            if success {
+                tr!("Exercise succeeded.")
+            } else {
+                tr!("Exercise failed.")
+            }
+        );
+        print!("{}", format_screen(&session, &history, &message, ansi));
+    }
+
+    Ok(())
+}
+
+fn record_moves(session: &mut GameSession, history: &mut MovesHistory) {
+    for event in session.take_events() {
+        if let GameEvent::MovePlayed { san, white_player } = event {
+            history.moves.push(san_to_fan(san, white_player));
+        }
+    }
+}
+
+/// Reads the move in SAN, or else in UCI notation.
+fn parse_player_move(input: &str, board: &Board) -> Option<Move> {
+    Move::from_san(input, board)
+        .ok()
+        .or_else(|| Move::from_uci_legal(input, board).ok())
+}
+
+/// The board, with the last moves beside it, then the message.
+fn format_screen(
+    session: &GameSession,
+    history: &MovesHistory,
+    message: &str,
+    ansi: bool,
+) -> String {
+    let board_lines = format_board(session.board(), session.player_side(), ansi);
+    let history_lines = history.lines();
+    let shown_history = &history_lines[history_lines.len().saturating_sub(HISTORY_LINES)..];
+
+    let mut result = String::new();
+    if ansi {
+        // Clears the screen and moves the cursor to its top left corner.
+        result.push_str("\x1b[2J\x1b[H");
+    } else {
+        result.push('\n');
+    }
+
+    for (index, board_line) in board_lines.iter().enumerate() {
+        result.push_str(board_line);
+        if let Some(history_line) = shown_history.get(index) {
+            result.push_str("    ");
+            result.push_str(history_line);
+        }
+        result.push('\n');
+    }
+
+    if !message.is_empty() {
+        result.push('\n');
+        result.push_str(message);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// The board as seen from the given side, one line per rank, then the files line.
+fn format_board(board: &Board, point_of_view: Color, ansi: bool) -> Vec<String> {
+    let reversed = point_of_view == Color::Black;
+    let mut lines = vec![];
+
+    for row in 0..8 {
+        let rank = if reversed { 7 - row } else { row };
+        let mut line = format!("{} ", Rank::from_index(rank).as_char());
+        for col in 0..8 {
+            let file = if reversed { 7 - col } else { col };
+            let cell = board.get2(File::from_index(file), Rank::from_index(rank));
+            let is_white_cell = (row + col) % 2 == 0;
+
+            if ansi {
+                let glyph = if cell.is_free() {
+                    ' '
+                } else {
+                    piece_glyph(cell.as_char())
+                };
+                let background = if is_white_cell {
+                    LIGHT_CELL_COLOR
+                } else {
+                    DARK_CELL_COLOR
+                };
+                line.push_str(&format!(
+                    "\x1b[48;5;{}m\x1b[38;5;{}m {} \x1b[0m",
+                    background, PIECES_COLOR, glyph
+                ));
+            } else {
+                let glyph = if cell.is_free() {
+                    '.'
+                } else {
+                    piece_glyph(cell.as_char())
+                };
+                line.push(' ');
+                line.push(glyph);
+                line.push(' ');
+            }
+        }
+        lines.push(line);
+    }
+
+    let mut files_line = String::from("  ");
+    for col in 0..8 {
+        let file = if reversed { 7 - col } else { col };
+        files_line.push(' ');
+        files_line.push(File::from_index(file).as_char());
+        files_line.push(' ');
+    }
+    lines.push(files_line);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_is_read_in_san_or_uci() {
+        let board = Board::from_fen("4k3/8/3K4/8/8/8/8/7Q w - - 0 1").unwrap();
+
+        let san_move = parse_player_move("Qe4+", &board).unwrap();
+        assert_eq!(san_move.to_string(), "h1e4");
+        let uci_move = parse_player_move("h1e4", &board).unwrap();
+        assert_eq!(uci_move, san_move);
+
+        assert!(parse_player_move("Qa1", &board).is_some());
+        assert!(parse_player_move("Qh9", &board).is_none());
+        assert!(parse_player_move("d6d7", &board).is_none());
+        assert!(parse_player_move("", &board).is_none());
+    }
+
+    #[test]
+    fn board_is_drawn_from_the_player_side() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+
+        let white_lines = format_board(&board, Color::White, false);
+        assert_eq!(white_lines[0], "8  .  .  .  .  \u{265a}  .  .  . ");
+        assert_eq!(white_lines[7], "1  \u{2656}  .  .  .  \u{2654}  .  .  . ");
+        assert_eq!(white_lines[8], "   a  b  c  d  e  f  g  h ");
+
+        let black_lines = format_board(&board, Color::Black, false);
+        assert_eq!(black_lines[0], "1  .  .  .  \u{2654}  .  .  .  \u{2656} ");
+        assert_eq!(black_lines[8], "   h  g  f  e  d  c  b  a ");
+    }
+
+    #[test]
+    fn history_is_numbered_from_the_start_position() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 12").unwrap();
+        let mut history = MovesHistory::new(&board);
+        assert!(history.lines().is_empty());
+
+        for san in ["Kd8", "\u{2656}a7", "Kc8", "\u{2654}d2"] {
+            history.moves.push(san.to_string());
+        }
+        assert_eq!(
+            history.lines(),
+            vec!["12. ... Kd8", "13. \u{2656}a7 Kc8", "14. \u{2654}d2"]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+
+use gtk::prelude::*;
+use owlchess::MoveChain;
+use relm::{Channel, Relm, Widget};
+use relm_derive::{widget, Msg};
+use tr::tr;
+
+use crate::engine::analysis::{AnalysisUpdate, AnalysisWorker};
+use crate::engine::PvLine;
+use crate::widgets::evaluation_bar::Evaluation;
+
+pub(crate) mod utils;
+
+/// Principal variations shown.
+const LINES_COUNT: usize = 3;
+
+#[widget]
+impl Widget for AnalysisPanel {
+    view! {
+        #[name="root"]
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            spacing: 5,
+            #[name="toggle"]
+            gtk::CheckButton {
+                toggled(button) => Msg::Toggled(button.is_active()),
+            },
+            #[name="status_label"]
+            gtk::Label {
+                halign: gtk::Align::Start,
+            },
+            #[name="lines_box"]
+            gtk::Box {
+                orientation: gtk::Orientation::Vertical,
+                spacing: 3,
+            },
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::SetEngine(path) => self.set_engine(path),
+            Msg::SetPosition(moves_chain) => {
+                self.model.moves_chain = Some(*moves_chain);
+                self.restart();
+            }
+            Msg::Toggled(enabled) => {
+                self.model.enabled = enabled;
+                self.restart();
+            }
+            Msg::Updated(update) => self.handle_update(update),
+            Msg::Evaluated(_) => {}
+        }
+    }
+
+    fn model(relm: &Relm<Self>, _: ()) -> Model {
+        Model {
+            relm: relm.clone(),
+            engine_path: None,
+            worker: None,
+            updates_channel: None,
+            enabled: false,
+            moves_chain: None,
+            lines: vec![None; LINES_COUNT],
+            lines_labels: vec![],
+        }
+    }
+
+    fn init_view(&mut self) {
+        self.widgets.toggle.set_label(&tr!("Analysis"));
+        for _ in 0..LINES_COUNT {
+            let label = gtk::Label::new(None);
+            label.set_halign(gtk::Align::Start);
+            label.set_line_wrap(true);
+            label.set_selectable(true);
+            self.widgets.lines_box.add(&label);
+            self.model.lines_labels.push(label);
+        }
+    }
+}
+
+impl AnalysisPanel {
+    /// The engine is only launched once the analysis is enabled.
+    fn set_engine(&mut self, path: PathBuf) {
+        self.model.worker = None;
+        self.model.updates_channel = None;
+        self.model.engine_path = Some(path);
+        self.restart();
+    }
+
+    /// Analyses the displayed position again, or stops if the analysis is disabled.
+    fn restart(&mut self) {
+        self.model.lines = vec![None; LINES_COUNT];
+        let evaluation = match self.model.moves_chain.as_ref() {
+            Some(moves_chain) if self.model.enabled => {
+                Evaluation::from_position(moves_chain.last())
+            }
+            _ => None,
+        };
+        self.model.relm.stream().emit(Msg::Evaluated(evaluation));
+        if !self.model.enabled {
+            if let Some(worker) = self.model.worker.as_mut() {
+                worker.stop();
+            }
+            self.refresh();
+            return;
+        }
+
+        if self.model.worker.is_none() {
+            if let Err(err) = self.launch_engine() {
+                self.widgets.status_label.set_text(&format!("{:#}", err));
+                return;
+            }
+        }
+        if let (Some(worker), Some(moves_chain)) =
+            (self.model.worker.as_mut(), self.model.moves_chain.as_ref())
+        {
+            worker.start(moves_chain);
+        }
+        self.refresh();
+    }
+
+    fn launch_engine(&mut self) -> anyhow::Result<()> {
+        let engine_path = self
+            .model
+            .engine_path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!(tr!("No engine has been selected.")))?;
+        let stream = self.model.relm.stream().clone();
+        let (channel, sender) = Channel::new(move |update| {
+            stream.emit(Msg::Updated(update));
+        });
+        let worker = AnalysisWorker::new(&engine_path, LINES_COUNT, move |update| {
+            let _ = sender.send(update);
+        })?;
+        self.model.worker = Some(worker);
+        self.model.updates_channel = Some(channel);
+        Ok(())
+    }
+
+    fn handle_update(&mut self, update: AnalysisUpdate) {
+        let is_current = match self.model.worker.as_ref() {
+            Some(worker) => worker.analysis_id() == update.analysis_id,
+            None => false,
+        };
+        if !self.model.enabled || !is_current || update.line.index >= LINES_COUNT {
+            return;
+        }
+        let index = update.line.index;
+        if let (0, Some(score), Some(moves_chain)) =
+            (index, update.line.score, self.model.moves_chain.as_ref())
+        {
+            let position = moves_chain.last();
+            let evaluation = Evaluation::from_position(position)
+                .unwrap_or_else(|| Evaluation::from_score(score, position.side()));
+            self.model
+                .relm
+                .stream()
+                .emit(Msg::Evaluated(Some(evaluation)));
+        }
+        self.model.lines[index] = Some(update.line);
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        let position = match self.model.moves_chain.as_ref() {
+            Some(moves_chain) if self.model.enabled => moves_chain.last().clone(),
+            _ => {
+                self.widgets.status_label.set_text("");
+                for label in &self.model.lines_labels {
+                    label.set_text("");
+                }
+                return;
+            }
+        };
+
+        let status = match &self.model.lines[0] {
+            Some(best_line) => match best_line.nodes_per_second {
+                Some(nodes_per_second) => tr!(
+                    "Depth {}, {} kN/s",
+                    best_line.depth,
+                    nodes_per_second / 1000
+                ),
+                None => tr!("Depth {}", best_line.depth),
+            },
+            None => tr!("Thinking..."),
+        };
+        self.widgets.status_label.set_text(&status);
+
+        for (label, line) in self.model.lines_labels.iter().zip(self.model.lines.iter()) {
+            let text = match line {
+                Some(line) => format!(
+                    "{}  {}",
+                    line.score
+                        .map(|score| utils::format_score(score, position.side()))
+                        .unwrap_or_default(),
+                    utils::format_variation(&position, &line.moves)
+                ),
+                None => String::new(),
+            };
+            label.set_text(&text);
+        }
+    }
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    SetEngine(PathBuf),
+    SetPosition(Box<MoveChain>),
+    Toggled(bool),
+    Updated(AnalysisUpdate),
+    /// The evaluation of the best line, None when the analysis is disabled.
+    Evaluated(Option<Evaluation>),
+}
+
+pub struct Model {
+    relm: Relm<AnalysisPanel>,
+    engine_path: Option<PathBuf>,
+    worker: Option<AnalysisWorker>,
+    /// Gives the updates of the analysis thread to the GTK loop.
+    updates_channel: Option<Channel<AnalysisUpdate>>,
+    enabled: bool,
+    moves_chain: Option<MoveChain>,
+    lines: Vec<Option<PvLine>>,
+    lines_labels: Vec<gtk::Label>,
+}
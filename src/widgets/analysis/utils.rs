@@ -0,0 +1,77 @@
+use owlchess::{Board, Color, Move};
+
+use crate::engine::Score;
+use crate::widgets::history::utils::san_to_fan;
+
+/// The score from the white side point of view : "+1.25", "-0.40", "#3" or "#-2".
+pub(crate) fn format_score(score: Score, side_to_move: Color) -> String {
+    let sign = if side_to_move == Color::White { 1 } else { -1 };
+    match score {
+        Score::Centipawns(centipawns) => format!("{:+.2}", (sign * centipawns) as f64 / 100.0),
+        Score::Mate(moves) => format!("#{}", sign * moves),
+    }
+}
+
+/// The moves as figurines, numbered from the position (e.g. "12. ... ♚d7 13. ♖a7+").
+pub(crate) fn format_variation(position: &Board, moves: &[Move]) -> String {
+    let mut board = position.clone();
+    let mut move_number = position.raw().move_number;
+    let mut parts = vec![];
+
+    for (index, variation_move) in moves.iter().enumerate() {
+        let white_player = board.side() == Color::White;
+        if white_player {
+            parts.push(format!("{}.", move_number));
+        } else if index == 0 {
+            parts.push(format!("{}. ...", move_number));
+        }
+
+        let san = match variation_move.san(&board) {
+            Ok(san) => san.to_string(),
+            Err(_) => break,
+        };
+        parts.push(san_to_fan(san, white_player));
+
+        board = match board.make_move(*variation_move) {
+            Ok(next_board) => next_board,
+            Err(_) => break,
+        };
+        if !white_player {
+            move_number += 1;
+        }
+    }
+
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_given_for_white() {
+        assert_eq!(format_score(Score::Centipawns(125), Color::White), "+1.25");
+        assert_eq!(format_score(Score::Centipawns(125), Color::Black), "-1.25");
+        assert_eq!(format_score(Score::Centipawns(0), Color::White), "+0.00");
+        assert_eq!(format_score(Score::Mate(3), Color::White), "#3");
+        assert_eq!(format_score(Score::Mate(3), Color::Black), "#-3");
+    }
+
+    #[test]
+    fn variations_are_numbered_from_the_position() {
+        let board = Board::from_fen("4k3/8/4K3/8/8/8/8/R7 b - - 0 12").unwrap();
+        let moves: Vec<Move> = ["e8f8", "a1a8"]
+            .iter()
+            .scan(board.clone(), |position, uci| {
+                let uci_move = Move::from_uci_legal(uci, position).unwrap();
+                *position = position.make_move(uci_move).unwrap();
+                Some(uci_move)
+            })
+            .collect();
+        assert_eq!(
+            format_variation(&board, &moves),
+            "12. ... \u{265a}f8 13. \u{2656}a8+"
+        );
+        assert_eq!(format_variation(&board, &[]), "");
+    }
+}
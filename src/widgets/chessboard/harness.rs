@@ -0,0 +1,362 @@
+//! Drives the mouse handler with synthetic positions on a virtual board,
+//! so that the drag and drop and promotion flows are tested without a display.
+
+use owlchess::{Board, Color, DrawReason, Move, Outcome, WinReason};
+
+use super::mouse_handler::{BoardInput, MouseHandler};
+use super::painter::Painter;
+use super::{get_session_messages, submit_move, DragAndDropData, Msg};
+use crate::game::GameSession;
+
+use super::Msg::*;
+
+pub(crate) struct BoardHarness {
+    session: GameSession,
+    dnd_data: Option<DragAndDropData>,
+    reversed: bool,
+    lesson_mode: bool,
+    board_size: f64,
+    messages: Vec<Msg>,
+}
+
+impl BoardHarness {
+    pub(crate) fn new(fen: &str, player_side: Color, board_size: f64) -> Self {
+        let mut session = GameSession::default();
+        session.start(Board::from_fen(fen).unwrap(), player_side);
+        let messages = get_session_messages(&mut session, false);
+        Self {
+            session,
+            dnd_data: None,
+            reversed: false,
+            lesson_mode: false,
+            board_size,
+            messages,
+        }
+    }
+
+    pub(crate) fn reversed(mut self) -> Self {
+        self.reversed = true;
+        self
+    }
+
+    pub(crate) fn in_lesson(mut self) -> Self {
+        self.lesson_mode = true;
+        self
+    }
+
+    /// Center of a cell (such as "e2"), following the cells layout of the painter.
+    pub(crate) fn cell_center(&self, cell: &str) -> (f64, f64) {
+        let cell = cell.as_bytes();
+        let file = (cell[0] - b'a') as f64;
+        let rank = (cell[1] - b'1') as f64;
+        let col = if self.reversed { 7.0 - file } else { file };
+        let row = if self.reversed { rank } else { 7.0 - rank };
+        let cells_size = self.board_size * 0.111;
+        (cells_size * (col + 1.0), cells_size * (row + 1.0))
+    }
+
+    /// Center of a promotion button, following the layout of the painter.
+    pub(crate) fn promotion_button_center(&self, piece_type: char) -> (f64, f64) {
+        let white_turn = self.session.board().side() == Color::White;
+        let buttons_y_ratio = if white_turn == self.reversed {
+            Painter::BUTTON_Y1_RATIO
+        } else {
+            Painter::BUTTON_Y2_RATIO
+        };
+        let buttons_x_ratio = match piece_type {
+            'q' => Painter::QUEEN_BUTTON_X_RATIO,
+            'r' => Painter::ROOK_BUTTON_X_RATIO,
+            'b' => Painter::BISHOP_BUTTON_X_RATIO,
+            'n' => Painter::KNIGHT_BUTTON_X_RATIO,
+            other => panic!("Forbidden promote value : {}.", other),
+        };
+        let half_button_size = self.board_size * Painter::BUTTON_SIZE_RATIO * 0.5;
+        (
+            self.board_size * buttons_x_ratio + half_button_size,
+            self.board_size * buttons_y_ratio + half_button_size,
+        )
+    }
+
+    pub(crate) fn press(&mut self, (x, y): (f64, f64)) {
+        let promotion_move = MouseHandler::handle_button_down(&mut self.input(), x, y);
+        self.submit(promotion_move);
+    }
+
+    pub(crate) fn move_to(&mut self, (x, y): (f64, f64)) {
+        MouseHandler::handle_mouse_drag(&mut self.input(), x, y);
+    }
+
+    pub(crate) fn release(&mut self, (x, y): (f64, f64)) {
+        let dropped_move = MouseHandler::handle_button_up(&mut self.input(), x, y);
+        self.submit(dropped_move);
+    }
+
+    /// Presses on the start cell, moves to the target cell, then releases there.
+    pub(crate) fn drag(&mut self, start_cell: &str, target_cell: &str) {
+        self.press(self.cell_center(start_cell));
+        self.move_to(self.cell_center(target_cell));
+        self.release(self.cell_center(target_cell));
+    }
+
+    pub(crate) fn click_promotion(&mut self, piece_type: char) {
+        self.press(self.promotion_button_center(piece_type));
+    }
+
+    pub(crate) fn take_messages(&mut self) -> Vec<Msg> {
+        std::mem::take(&mut self.messages)
+    }
+
+    pub(crate) fn fen(&self) -> String {
+        self.session.board().as_fen()
+    }
+
+    pub(crate) fn dnd_data(&self) -> Option<&DragAndDropData> {
+        self.dnd_data.as_ref()
+    }
+
+    fn input(&mut self) -> BoardInput<'_> {
+        BoardInput {
+            session: &self.session,
+            dnd_data: &mut self.dnd_data,
+            reversed: self.reversed,
+            lesson_mode: self.lesson_mode,
+            board_size: self.board_size,
+        }
+    }
+
+    fn submit(&mut self, player_move: Option<Move>) {
+        if let Some(player_move) = player_move {
+            let messages = submit_move(&mut self.session, self.lesson_mode, player_move);
+            self.messages.extend(messages);
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    const ROOK_FEN: &str = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+    const BOARD_SIZES: [f64; 3] = [400.0, 257.0, 1000.0];
+
+    fn assert_single_move(messages: &[Msg], expected_san: &str, expected_white: bool) {
+        match messages {
+            [MovePlayed(san, white_player)] => {
+                assert_eq!(san, expected_san);
+                assert_eq!(*white_player, expected_white);
+            }
+            _ => panic!("Expected only the move {}.", expected_san),
+        }
+    }
+
+    #[test]
+    fn start_emits_game_started() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0);
+        assert!(matches!(harness.take_messages()[..], [GameStarted]));
+    }
+
+    #[test]
+    fn drag_and_drop_plays_move() {
+        for board_size in BOARD_SIZES {
+            let mut harness = BoardHarness::new(ROOK_FEN, Color::White, board_size);
+            harness.take_messages();
+
+            harness.drag("a1", "a7");
+
+            assert_single_move(&harness.take_messages(), "Ra7", true);
+            assert_eq!(harness.fen(), "4k3/R7/8/8/8/8/8/4K3 b - - 1 1");
+            assert!(harness.dnd_data().is_none());
+        }
+    }
+
+    #[test]
+    fn drag_and_drop_plays_move_on_reversed_board() {
+        for board_size in BOARD_SIZES {
+            let fen = "4k3/8/8/8/8/8/8/R3K3 b - - 0 1";
+            let mut harness = BoardHarness::new(fen, Color::Black, board_size).reversed();
+            harness.take_messages();
+
+            harness.drag("e8", "d7");
+
+            assert_single_move(&harness.take_messages(), "Kd7", false);
+        }
+    }
+
+    #[test]
+    fn reversed_board_mirrors_the_cells() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0).reversed();
+        harness.take_messages();
+
+        // Where a1 stands on a board which is not reversed, there is h8.
+        let cells_size = 400.0 * 0.111;
+        harness.press((cells_size, cells_size * 8.0));
+        assert!(harness.dnd_data().is_none());
+
+        harness.press((cells_size * 8.0, cells_size));
+        let dnd_data = harness.dnd_data().unwrap();
+        assert_eq!((dnd_data.start_file, dnd_data.start_rank), (0, 0));
+        assert_eq!(dnd_data.piece, 'R');
+    }
+
+    #[test]
+    fn dragging_follows_the_pointer() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0);
+
+        harness.press(harness.cell_center("a1"));
+        harness.move_to(harness.cell_center("c5"));
+
+        let (x, y) = harness.cell_center("c5");
+        let dnd_data = harness.dnd_data().unwrap();
+        assert_eq!((dnd_data.x, dnd_data.y), (x, y));
+        assert_eq!((dnd_data.target_file, dnd_data.target_rank), (2, 4));
+        assert_eq!((dnd_data.start_file, dnd_data.start_rank), (0, 0));
+
+        // Out of the board, the last target cell is kept.
+        harness.move_to((399.0, 399.0));
+        let dnd_data = harness.dnd_data().unwrap();
+        assert_eq!((dnd_data.target_file, dnd_data.target_rank), (2, 4));
+    }
+
+    #[test]
+    fn drop_out_of_board_cancels_move() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0);
+        harness.take_messages();
+
+        harness.press(harness.cell_center("a1"));
+        harness.release((399.0, 399.0));
+
+        assert!(harness.take_messages().is_empty());
+        assert!(harness.dnd_data().is_none());
+        assert_eq!(harness.fen(), ROOK_FEN);
+    }
+
+    #[test]
+    fn illegal_drop_is_ignored() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0);
+        harness.take_messages();
+
+        harness.drag("a1", "b2");
+
+        assert!(harness.take_messages().is_empty());
+        assert!(harness.dnd_data().is_none());
+        assert_eq!(harness.fen(), ROOK_FEN);
+    }
+
+    #[test]
+    fn only_pieces_of_side_to_move_are_dragged() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::White, 400.0);
+
+        harness.press(harness.cell_center("e8"));
+        assert!(harness.dnd_data().is_none());
+
+        harness.press(harness.cell_center("d4"));
+        assert!(harness.dnd_data().is_none());
+    }
+
+    #[test]
+    fn computer_side_is_not_dragged() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::Black, 400.0);
+        harness.take_messages();
+
+        harness.drag("a1", "a7");
+
+        assert!(harness.dnd_data().is_none());
+        assert!(harness.take_messages().is_empty());
+    }
+
+    #[test]
+    fn promotion_waits_for_piece_choice() {
+        for board_size in BOARD_SIZES {
+            let fen = "8/P3k3/8/8/8/8/7P/4K3 w - - 0 1";
+            let mut harness = BoardHarness::new(fen, Color::White, board_size);
+            harness.take_messages();
+
+            harness.drag("a7", "a8");
+            assert!(harness.take_messages().is_empty());
+            assert_eq!(harness.dnd_data().unwrap().pending_promotion, Some(true));
+
+            // Neither dragging nor clicking out of the buttons cancels the promotion.
+            harness.move_to(harness.cell_center("d4"));
+            harness.press(harness.cell_center("d4"));
+            assert_eq!(harness.dnd_data().unwrap().pending_promotion, Some(true));
+
+            harness.click_promotion('n');
+
+            assert_single_move(&harness.take_messages(), "a8=N", true);
+            assert!(harness.dnd_data().is_none());
+        }
+    }
+
+    #[test]
+    fn promotion_on_reversed_board() {
+        for (piece_type, expected_san) in [('q', "a1=Q+"), ('r', "a1=R+"), ('b', "a1=B")] {
+            let fen = "4k3/7p/8/8/8/8/p7/4K3 b - - 0 1";
+            let mut harness = BoardHarness::new(fen, Color::Black, 400.0).reversed();
+            harness.take_messages();
+
+            harness.drag("a2", "a1");
+            assert_eq!(harness.dnd_data().unwrap().pending_promotion, Some(false));
+            harness.click_promotion(piece_type);
+
+            assert_single_move(&harness.take_messages(), expected_san, false);
+        }
+    }
+
+    #[test]
+    fn checkmate_emits_game_over() {
+        let fen = "k7/8/1K6/8/8/8/8/7R w - - 0 1";
+        let mut harness = BoardHarness::new(fen, Color::White, 400.0);
+        harness.take_messages();
+
+        harness.drag("h1", "h8");
+
+        match &harness.take_messages()[..] {
+            [MovePlayed(san, true), GameOver(outcome)] => {
+                assert_eq!(san, "Rh8#");
+                assert_eq!(
+                    *outcome,
+                    Outcome::Win {
+                        side: Color::White,
+                        reason: WinReason::Checkmate,
+                    }
+                );
+            }
+            _ => panic!("Expected the move then the game over."),
+        }
+
+        harness.drag("a8", "b8");
+        assert!(harness.take_messages().is_empty());
+    }
+
+    #[test]
+    fn capture_of_last_piece_emits_draw() {
+        let fen = "4k3/8/8/8/8/8/3r4/4K3 w - - 0 1";
+        let mut harness = BoardHarness::new(fen, Color::White, 400.0);
+        harness.take_messages();
+
+        harness.drag("e1", "d2");
+
+        assert!(matches!(
+            harness.take_messages()[..],
+            [
+                MovePlayed(_, true),
+                GameOver(Outcome::Draw(DrawReason::InsufficientMaterial))
+            ]
+        ));
+    }
+
+    #[test]
+    fn lesson_only_submits_moves() {
+        let mut harness = BoardHarness::new(ROOK_FEN, Color::Black, 400.0).in_lesson();
+        harness.take_messages();
+
+        harness.drag("a1", "a7");
+
+        match &harness.take_messages()[..] {
+            [MoveAttempted(attempted_move)] => {
+                assert_eq!(attempted_move.to_string(), "a1a7")
+            }
+            _ => panic!("Expected only the attempted move."),
+        }
+        assert_eq!(harness.fen(), ROOK_FEN);
+    }
+}
@@ -1,13 +1,25 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
 use gtk::gdk::{EventButton, EventMotion};
 use gtk::prelude::*;
-use owlchess::chain::BaseMoveChain;
-use owlchess::{Board, Make, Move, MoveChain, Outcome, Color};
+use owlchess::{Board, Color, Move, MoveChain, Outcome};
 use relm::{Relm, Widget};
 use relm_derive::{widget, Msg};
-
+use tr::tr;
+
+use crate::engine::worker::{EngineAnswer, EngineTask, EngineWorker};
+use crate::engine::{accepts_draw, offers_draw, EngineStrength, Score};
+use crate::exercises::Exercise;
+use crate::game::review::{GameReview, ReviewSummary};
+use crate::game::tree::{GameTree, NodeId};
+use crate::game::{GameEvent, GameSession};
+use crate::rendering::pieces_images::PiecesImages;
+
+#[cfg(test)]
+mod harness;
 mod mouse_handler;
 mod painter;
-mod pieces_images;
 mod utils;
 
 use anyhow::Context;
@@ -18,7 +30,9 @@ pub enum Msg {
     Repaint,
     UpdatePiecesImagesSize,
     ToggleOrientation,
-    StartGame,
+    StartGame(Box<Exercise>),
+    /// Goes on with an imported game, from the end of its main line.
+    LoadGame(Box<GameTree>),
     StopGame,
     SetReversed(bool),
     ButtonDown(EventButton),
@@ -27,13 +41,66 @@ pub enum Msg {
     GameOver(Outcome),
     GameStopped,
     GameStarted,
+    /// A finished game goes on, from a position before its end.
+    GameResumed,
     MovePlayed(String, bool),
+    /// The game has started or a move has been played : gives the moves from the start position.
+    PositionChanged(Box<MoveChain>),
+    /// The moves tree has changed, or its current node.
+    HistoryChanged(Box<GameTree>, NodeId),
+    /// The board is now seen from the black side, or not.
+    OrientationChanged(bool),
+    SetEngine(PathBuf),
+    ShowHint,
+    HintUsed,
+    OptimumMovesComputed(u32),
+    EngineError(String),
+    EngineAnswered(EngineAnswer),
+    StartLesson(Box<Board>),
+    EndLesson,
+    PlayMove(Move),
+    MoveAttempted(Move),
+    GoToNode(NodeId),
+    PromoteVariation(NodeId),
+    DeleteVariation(NodeId),
+    SetComment(NodeId, Option<String>),
+    ToggleNag(NodeId, u8),
+    StartReview,
+    /// The critical moves have been annotated : gives them, with the side of the player.
+    ReviewFinished(Box<ReviewSummary>, Color),
+    ReviewCancelled,
+    /// Removes the move with the moves after it, the game going on from the position before it.
+    RetryFrom(NodeId),
+    ClaimDraw,
+    /// The player offers a draw, which the computer side accepts unless it expects to win.
+    OfferDraw,
+    /// The player accepts the draw offered by the computer side.
+    AcceptDraw,
+    Resign,
+    DrawOffered,
+    DrawDeclined,
+    /// There is no threefold repetition nor 50 moves rule to claim, or it is not the player turn.
+    DrawNotClaimable,
+    /// Limits the thinking time of the player to these seconds, from the next game on.
+    SetPlayerTime(Option<u64>),
+    Tick,
+    /// Gives the seconds left to the player, when their time is limited.
+    PlayerTimeChanged(Option<u64>),
+    /// The player ran out of time, the game being over just after.
+    TimeUp,
+    /// Gives the names of the white and black sides of the game just started.
+    PlayersChanged(String, String),
 }
 
-use self::mouse_handler::MouseHandler;
-use self::utils::get_uci_move_for;
+use self::mouse_handler::{BoardInput, MouseHandler};
 use self::Msg::*;
 
+/// Plies between two draw offers of the computer side.
+const DRAW_OFFER_INTERVAL: usize = 20;
+
+/// How often the player clock is checked.
+const CLOCK_TICK_MS: u32 = 200;
+
 pub struct DragAndDropData {
     piece: char,
     x: f64,
@@ -45,14 +112,42 @@ pub struct DragAndDropData {
     pending_promotion: Option<bool>,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum HintStage {
+    Piece,
+    Destination,
+}
+
+pub struct HintData {
+    hint_move: Move,
+    stage: HintStage,
+}
+
 pub struct Model {
     #[allow(dead_code)]
-    pieces_images: pieces_images::PiecesImages,
-    board: Board,
-    board_moves_chain: MoveChain,
+    pieces_images: PiecesImages,
+    session: GameSession,
     reversed: bool,
     dnd_data: Option<DragAndDropData>,
-    game_in_progress: bool,
+    hint: Option<HintData>,
+    engine: Option<EngineWorker>,
+    /// Gives the answers of the engine thread to the GTK loop, as `EngineAnswered` messages.
+    engine_channel: Option<relm::Channel<EngineAnswer>>,
+    /// A hint has been asked, the engine is searching it.
+    hint_pending: bool,
+    computer_strength: EngineStrength,
+    /// A draw has been offered to the computer side, the engine is scoring the position.
+    draw_offer_pending: bool,
+    /// The computer side does not offer a draw again before this ply.
+    next_draw_offer_ply: usize,
+    /// The thinking time given to the player at the start of each game, if limited.
+    player_time: Option<Duration>,
+    /// The seconds left to the player, as last emitted.
+    shown_player_time: Option<u64>,
+    /// The scores of the reviewed game, while the engine gives them.
+    review: Option<GameReview>,
+    /// In a lesson, the moves of the user are only submitted, the lesson plays them.
+    lesson_mode: bool,
     relm: Relm<ChessBoard>,
 }
 
@@ -81,6 +176,7 @@ impl Widget for ChessBoard {
             }
             ToggleOrientation => {
                 self.model.reversed = !self.model.reversed;
+                self.emit_orientation();
                 self.reverse_dragged_piece_position();
                 painter::Painter::draw(self).unwrap();
             }
@@ -90,37 +186,130 @@ impl Widget for ChessBoard {
                     self.reverse_dragged_piece_position();
                 }
                 self.model.reversed = reversed;
+                self.emit_orientation();
                 painter::Painter::draw(self).unwrap();
             }
             ButtonDown(event) => {
-                MouseHandler::handle_button_down(self, event);
+                let (x, y) = event.position();
+                let promotion_move =
+                    MouseHandler::handle_button_down(&mut self.board_input(), x, y);
+                self.handle_player_input(promotion_move);
             }
             ButtonUp(event) => {
-                MouseHandler::handle_button_up(self, event);
+                let (x, y) = event.position();
+                let dropped_move = MouseHandler::handle_button_up(&mut self.board_input(), x, y);
+                self.handle_player_input(dropped_move);
             }
             MouseMoved(event) => {
-                MouseHandler::handle_mouse_drag(self, event);
+                let (x, y) = event.position();
+                MouseHandler::handle_mouse_drag(&mut self.board_input(), x, y);
             }
             GameOver(_) => {}
             GameStarted => {}
             GameStopped => {}
+            GameResumed => {}
             MovePlayed(_, _) => {}
+            PositionChanged(_) => {}
+            HistoryChanged(_, _) => {}
+            OrientationChanged(_) => {}
+            HintUsed => {}
+            OptimumMovesComputed(_) => {}
+            EngineError(_) => {}
+            MoveAttempted(_) => {}
+            ReviewFinished(_, _) => {}
+            ReviewCancelled => {}
+            DrawOffered => {}
+            DrawDeclined => {}
+            DrawNotClaimable => {}
+            PlayerTimeChanged(_) => {}
+            TimeUp => {}
+            PlayersChanged(_, _) => {}
+            SetPlayerTime(seconds) => {
+                self.model.player_time = seconds.map(Duration::from_secs);
+            }
+            Tick => self.check_player_time(),
+            ClaimDraw => self.claim_draw(),
+            OfferDraw => self.offer_draw(),
+            AcceptDraw => {
+                self.cancel_engine_tasks();
+                // Only fails once the game is over : the offer came too late.
+                let _ = self.model.session.agree_draw();
+                self.dispatch_session_events();
+            }
+            Resign => {
+                self.cancel_engine_tasks();
+                let _ = self.model.session.resign();
+                self.dispatch_session_events();
+            }
+            StartReview => self.start_review(),
+            SetEngine(path) => self.set_engine(&path),
+            EngineAnswered(answer) => self.handle_engine_answer(answer),
+            ShowHint => self.show_hint(),
             StopGame => self.stop_game(),
-            StartGame => self.start_new_game(),
+            StartGame(exercise) => self.start_new_game(*exercise),
+            LoadGame(tree) => self.load_game(*tree),
+            StartLesson(start_position) => self.start_lesson(*start_position),
+            EndLesson => {
+                self.model.session.stop();
+                self.dispatch_session_events();
+                self.model.lesson_mode = false;
+            }
+            PlayMove(move_to_play) => {
+                self.play_move(move_to_play);
+                painter::Painter::draw(self).unwrap();
+            }
+            GoToNode(node) => self.go_to_node(node),
+            RetryFrom(node) => self.retry_from(node),
+            SetComment(node, comment) => {
+                self.model.session.set_comment(node, comment);
+                self.emit_position_changed();
+            }
+            ToggleNag(node, nag) => {
+                self.model.session.toggle_nag(node, nag);
+                self.emit_position_changed();
+            }
+            PromoteVariation(node) => {
+                self.model.session.promote_variation(node);
+                self.emit_position_changed();
+            }
+            DeleteVariation(node) => {
+                let current_node = self.model.session.current_node();
+                let was_browsing = self.model.session.is_browsing();
+                // Only fails once the game is stopped, the variation being kept.
+                let _ = self.model.session.delete_variation(node);
+                // The game is resumed if its end was in the variation.
+                self.dispatch_session_events();
+                let moved = self.model.session.current_node() != current_node;
+                if moved {
+                    self.reset_for_navigation();
+                    painter::Painter::draw(self).unwrap();
+                }
+                if moved || was_browsing {
+                    self.play_computer_move_if_needed();
+                }
+                self.emit_position_changed();
+            }
         }
     }
 
     fn model(relm: &Relm<Self>, _: ()) -> Model {
-        let images = pieces_images::PiecesImages::new(30).expect("Failed to build pieces images.");
-        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
-        let board_clone = board.clone();
+        let images = PiecesImages::new(30).expect("Failed to build pieces images.");
         Model {
             pieces_images: images,
-            board,
+            session: GameSession::default(),
             reversed: false,
             dnd_data: None,
-            board_moves_chain: BaseMoveChain::new(board_clone),
-            game_in_progress: false,
+            hint: None,
+            engine: None,
+            engine_channel: None,
+            hint_pending: false,
+            computer_strength: EngineStrength::default(),
+            draw_offer_pending: false,
+            next_draw_offer_ply: 0,
+            player_time: None,
+            shown_player_time: None,
+            review: None,
+            lesson_mode: false,
             relm: relm.clone(),
         }
     }
@@ -130,60 +319,123 @@ impl Widget for ChessBoard {
         let cells_size = ((size as f64) * 0.111) as i32;
         self.widgets.drawing_area.set_size_request(400, 400);
         self.model.pieces_images =
-            pieces_images::PiecesImages::new(cells_size).expect("Failed to build pieces images.");
+            PiecesImages::new(cells_size).expect("Failed to build pieces images.");
+        relm::interval(self.model.relm.stream(), CLOCK_TICK_MS, || Tick);
     }
 }
 
 impl ChessBoard {
-    pub fn start_new_game(&mut self) {
-        let board = Board::initial();
-        let board_clone = board.clone();
-        self.model.board = board;
-        self.model.board_moves_chain = MoveChain::new(board_clone);
-        self.model.game_in_progress = true;
-        self.model.relm.stream().emit(GameStarted);
+    pub fn start_new_game(&mut self, exercise: Exercise) {
+        self.cancel_engine_tasks();
+        self.model.reversed = exercise.player_side == Color::Black;
+        self.emit_orientation();
+        self.model.dnd_data = None;
+        self.model.hint = None;
+        self.model.lesson_mode = false;
+        self.model.computer_strength = exercise.computer_strength.unwrap_or_default();
+        self.model.next_draw_offer_ply = DRAW_OFFER_INTERVAL;
+        self.model
+            .session
+            .start(exercise.start_position, exercise.player_side);
+        self.limit_player_time();
+        self.dispatch_session_events();
+        self.emit_players();
+
+        self.compute_optimum_moves();
+        painter::Painter::draw(self).unwrap();
+        self.play_computer_move_if_needed();
+    }
+
+    /// The player has the side to move at the end of the main line.
+    pub fn load_game(&mut self, tree: GameTree) {
+        self.cancel_engine_tasks();
+        let player_side = tree.position(tree.main_line_end()).side();
+        self.model.reversed = player_side == Color::Black;
+        self.emit_orientation();
+        self.model.dnd_data = None;
+        self.model.hint = None;
+        self.model.lesson_mode = false;
+        // Only fails if the game can not be browsed, which it can once started.
+        let _ = self.model.session.load(tree, player_side);
+        self.limit_player_time();
+        self.model.next_draw_offer_ply =
+            self.model.session.moves_chain().len() + DRAW_OFFER_INTERVAL;
+        self.dispatch_session_events();
+        self.emit_players();
+
+        painter::Painter::draw(self).unwrap();
     }
 
-    pub fn commit_promotion(&mut self, piece_type: char) {
-        if piece_type != 'q' && piece_type != 'r' && piece_type != 'b' && piece_type != 'n' {
+    pub fn start_lesson(&mut self, start_position: Board) {
+        self.cancel_engine_tasks();
+        self.model.reversed = start_position.side() == Color::Black;
+        self.emit_orientation();
+        self.model.dnd_data = None;
+        self.model.hint = None;
+        self.model.lesson_mode = true;
+        let player_side = start_position.side();
+        self.model.session.start(start_position, player_side);
+        self.emit_player_time();
+        self.dispatch_session_events();
+        self.emit_position_changed();
+
+        painter::Painter::draw(self).unwrap();
+    }
+
+    /// Shows the position of the node, a finished game being only browsed. Lessons can not be browsed.
+    fn go_to_node(&mut self, node: NodeId) {
+        if self.model.lesson_mode || self.model.session.current_node() == node {
             return;
         }
-
-        if self.model.dnd_data.is_none() {
+        // Only fails once the game is stopped : the history is then only shown.
+        if self.model.session.go_to(node).is_err() {
             return;
         }
+        self.reset_for_navigation();
+        self.emit_position_changed();
+        painter::Painter::draw(self).unwrap();
 
-        let dnd_data = self.model.dnd_data.as_ref().unwrap();
-
-        let start_file = dnd_data.start_file;
-        let start_rank = dnd_data.start_rank;
-        let target_file = dnd_data.target_file;
-        let target_rank = dnd_data.target_rank;
-
-        let uci_move = get_uci_move_for(
-            start_file,
-            start_rank,
-            target_file,
-            target_rank,
-            Some(piece_type),
-        );
-        let matching_move = uci_move.into_move(&self.model.board);
-        let move_san = match matching_move {
-            Ok(matching_move) => match matching_move.san(&self.model.board) {
-                Ok(san) => Some(san.to_string()),
-                _ => None,
-            },
-            Err(_) => None,
-        };
+        // Back to the position the game stands at, the computer goes on with it.
+        self.play_computer_move_if_needed();
+    }
 
-        if let Ok(matching_move) = matching_move {
-            match matching_move.make_raw(&mut self.model.board) {
-                Ok(_) => self.process_move_done(matching_move, move_san),
-                Err(_) => {}
-            }
+    /// Lessons can not be retried, as they can not be browsed.
+    fn retry_from(&mut self, node: NodeId) {
+        if self.model.lesson_mode {
+            return;
         }
+        self.reset_for_navigation();
+        // Only fails once the game is stopped, or for the start position.
+        if self.model.session.retry_from(node).is_err() {
+            return;
+        }
+        self.dispatch_session_events();
+        self.emit_position_changed();
+        painter::Painter::draw(self).unwrap();
+        self.play_computer_move_if_needed();
+    }
 
+    fn reset_for_navigation(&mut self) {
+        self.cancel_engine_tasks();
         self.model.dnd_data = None;
+        self.model.hint = None;
+    }
+
+    fn board_input(&mut self) -> BoardInput<'_> {
+        BoardInput {
+            board_size: self.common_size() as f64,
+            session: &self.model.session,
+            dnd_data: &mut self.model.dnd_data,
+            reversed: self.model.reversed,
+            lesson_mode: self.model.lesson_mode,
+        }
+    }
+
+    fn handle_player_input(&mut self, player_move: Option<Move>) {
+        if let Some(player_move) = player_move {
+            self.submit_player_move(player_move);
+            self.play_computer_move_if_needed();
+        }
     }
 
     fn set_image(&self, image: &gtk::cairo::ImageSurface) -> anyhow::Result<()> {
@@ -207,7 +459,7 @@ impl ChessBoard {
     }
 
     fn resize_pieces_images(&mut self, new_size: i32) -> anyhow::Result<()> {
-        self.model.pieces_images = pieces_images::PiecesImages::new(new_size)?;
+        self.model.pieces_images = PiecesImages::new(new_size)?;
 
         Ok(())
     }
@@ -229,26 +481,434 @@ impl ChessBoard {
             _ => {}
         };
     }
-    fn handle_game_termination(&mut self, outcome: &Outcome) {
-        self.model.game_in_progress = false;
-        self.model.relm.stream().emit(GameOver(*outcome));
+    fn stop_game(&mut self) {
+        self.cancel_engine_tasks();
+        self.model.session.stop();
+        self.dispatch_session_events();
     }
 
-    fn stop_game(&mut self) {
-        if !self.model.game_in_progress {
+    fn set_engine(&mut self, path: &Path) {
+        self.model.engine = None;
+        let stream = self.model.relm.stream().clone();
+        let (channel, sender) = relm::Channel::new(move |answer| {
+            stream.emit(EngineAnswered(answer));
+        });
+        let on_answer = move |answer| {
+            let _ = sender.send(answer);
+        };
+        match EngineWorker::new(path, on_answer) {
+            Ok(engine) => {
+                self.model.engine = Some(engine);
+                self.model.engine_channel = Some(channel);
+            }
+            Err(err) => self
+                .model
+                .relm
+                .stream()
+                .emit(EngineError(format!("{:#}", err))),
+        }
+    }
+
+    /// Queues the search, the answer coming back as an `EngineAnswered` message.
+    fn submit_engine_task(&mut self, task: EngineTask) {
+        let engine = match self.model.engine.as_mut() {
+            Some(engine) => engine,
+            None => {
+                // The optimum moves are only a bonus of the statistics.
+                if !matches!(task, EngineTask::OptimumMoves { .. }) {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(EngineError(tr!("No engine has been selected.")));
+                }
+                return;
+            }
+        };
+        if let Err(err) = engine.submit(self.model.session.moves_chain(), task) {
+            self.model
+                .relm
+                .stream()
+                .emit(EngineError(format!("{:#}", err)));
+        }
+    }
+
+    /// Forgets the searches in progress : their answers would not match the board anymore.
+    pub(crate) fn cancel_engine_tasks(&mut self) {
+        if let Some(engine) = self.model.engine.as_mut() {
+            engine.cancel_all();
+        }
+        self.model.hint_pending = false;
+        self.model.draw_offer_pending = false;
+        if self.model.review.take().is_some() {
+            self.model.relm.stream().emit(ReviewCancelled);
+        }
+    }
+
+    fn handle_engine_answer(&mut self, answer: EngineAnswer) {
+        let is_valid = match self.model.engine.as_ref() {
+            Some(engine) => engine.is_valid(&answer),
+            None => false,
+        };
+        if !is_valid {
             return;
         }
-        self.model.game_in_progress = false;
-        self.model.relm.stream().emit(GameStopped);
+        let analysis = match answer.result {
+            Ok(analysis) => analysis,
+            Err(err) => {
+                if let EngineTask::Review { .. } = answer.task {
+                    self.cancel_engine_tasks();
+                }
+                self.model
+                    .relm
+                    .stream()
+                    .emit(EngineError(format!("{:#}", err)));
+                return;
+            }
+        };
+
+        match answer.task {
+            EngineTask::ComputerMove(_) => {
+                if self.model.session.is_in_progress() && !self.model.session.is_player_turn() {
+                    if self.model.session.claimable_draw().is_some()
+                        && accepts_draw(analysis.score, true)
+                    {
+                        let _ = self.model.session.claim_draw();
+                        self.dispatch_session_events();
+                        return;
+                    }
+                    let draw_offered = self.should_offer_draw(analysis.score);
+                    self.play_move(analysis.best_move);
+                    painter::Painter::draw(self).unwrap();
+                    if draw_offered && self.model.session.is_in_progress() {
+                        self.model.relm.stream().emit(DrawOffered);
+                    }
+                }
+            }
+            EngineTask::DrawOffer => {
+                if self.model.draw_offer_pending && self.model.session.is_in_progress() {
+                    self.model.draw_offer_pending = false;
+                    let computer_to_move = !self.model.session.is_player_turn();
+                    if accepts_draw(analysis.score, computer_to_move) {
+                        let _ = self.model.session.agree_draw();
+                        self.dispatch_session_events();
+                    } else {
+                        self.model.relm.stream().emit(DrawDeclined);
+                    }
+                }
+            }
+            EngineTask::Hint => {
+                if self.model.hint_pending && self.model.session.is_in_progress() {
+                    self.model.hint_pending = false;
+                    self.model.hint = Some(HintData {
+                        hint_move: analysis.best_move,
+                        stage: HintStage::Piece,
+                    });
+                    self.model.relm.stream().emit(HintUsed);
+                    painter::Painter::draw(self).unwrap();
+                }
+            }
+            EngineTask::OptimumMoves { player_turn } => {
+                if let Some(Score::Mate(moves)) = analysis.score {
+                    if moves != 0 && (moves > 0) == player_turn {
+                        self.model
+                            .relm
+                            .stream()
+                            .emit(OptimumMovesComputed(moves.unsigned_abs()));
+                    }
+                }
+            }
+            EngineTask::Review { ply } => {
+                if let Some(review) = self.model.review.as_mut() {
+                    review.set_score(ply, analysis.score);
+                }
+                self.finish_review_if_complete();
+            }
+        }
     }
 
-    fn process_move_done(&mut self, move_to_process: Move, move_san: Option<String>) {
-        self.model.board_moves_chain.push(move_to_process).unwrap();
-        if let Some(san) = move_san {
-            let white_player = self.model.board.side() == Color::Black;
-            self.model.relm.stream().emit(MovePlayed(san, white_player));
+    /// Asks the engine the score of each position of the main line : the critical
+    /// moves are annotated once all of them are known.
+    fn start_review(&mut self) {
+        if self.model.lesson_mode || self.model.review.is_some() {
+            return;
         }
+        let engine = match self.model.engine.as_mut() {
+            Some(engine) => engine,
+            None => {
+                self.model
+                    .relm
+                    .stream()
+                    .emit(EngineError(tr!("No engine has been selected.")));
+                return;
+            }
+        };
+
+        let tree = self.model.session.tree();
+        let review = GameReview::new(tree);
+        for (ply, node) in review.pending_positions() {
+            let submitted = tree
+                .moves_chain(node)
+                .and_then(|moves_chain| engine.submit(&moves_chain, EngineTask::Review { ply }));
+            if let Err(err) = submitted {
+                self.model
+                    .relm
+                    .stream()
+                    .emit(EngineError(format!("{:#}", err)));
+                self.model.relm.stream().emit(ReviewCancelled);
+                return;
+            }
+        }
+        self.model.review = Some(review);
+        self.finish_review_if_complete();
+    }
+
+    fn finish_review_if_complete(&mut self) {
+        if !self
+            .model
+            .review
+            .as_ref()
+            .is_some_and(|review| review.is_complete())
+        {
+            return;
+        }
+        let summary = match self.model.review.take() {
+            Some(review) => review.summary(self.model.session.tree()),
+            None => return,
+        };
+        for critical_move in &summary.critical_moves {
+            self.model
+                .session
+                .add_nag(critical_move.node, critical_move.quality.nag());
+        }
+        self.emit_position_changed();
+        self.model.relm.stream().emit(ReviewFinished(
+            Box::new(summary),
+            self.model.session.player_side(),
+        ));
+    }
+
+    fn show_hint(&mut self) {
+        if !self.model.session.is_in_progress()
+            || self.model.session.is_input_locked()
+            || self.model.dnd_data.is_some()
+            || self.model.hint_pending
+        {
+            return;
+        }
+
+        match self.model.hint.take() {
+            None => {
+                self.model.hint_pending = self.model.engine.is_some();
+                self.submit_engine_task(EngineTask::Hint);
+            }
+            Some(HintData {
+                hint_move,
+                stage: HintStage::Piece,
+            }) => {
+                self.model.hint = Some(HintData {
+                    hint_move,
+                    stage: HintStage::Destination,
+                });
+            }
+            Some(HintData {
+                hint_move,
+                stage: HintStage::Destination,
+            }) => {
+                self.play_move(hint_move);
+                painter::Painter::draw(self).unwrap();
+                self.play_computer_move_if_needed();
+                return;
+            }
+        }
+
+        painter::Painter::draw(self).unwrap();
+    }
+
+    /// The player can only claim on their turn, the computer side claiming on its own.
+    fn claim_draw(&mut self) {
+        if self.model.lesson_mode || !self.model.session.is_player_turn() {
+            self.model.relm.stream().emit(DrawNotClaimable);
+            return;
+        }
+        if self.model.session.claim_draw().is_err() {
+            self.model.relm.stream().emit(DrawNotClaimable);
+            return;
+        }
+        self.cancel_engine_tasks();
+        self.dispatch_session_events();
+    }
+
+    /// Offered on the player turn, so that the computer side answers it before moving.
+    fn offer_draw(&mut self) {
+        if !self.model.session.is_in_progress()
+            || !self.model.session.is_player_turn()
+            || self.model.lesson_mode
+            || self.model.draw_offer_pending
+        {
+            return;
+        }
+        self.model.draw_offer_pending = self.model.engine.is_some();
+        self.submit_engine_task(EngineTask::DrawOffer);
+    }
+
+    /// The computer side offers a draw when it expects one, waiting a few moves
+    /// before offering it again.
+    fn should_offer_draw(&mut self, score: Option<Score>) -> bool {
+        let ply = self.model.session.moves_chain().len();
+        if ply < self.model.next_draw_offer_ply || !offers_draw(score) {
+            return false;
+        }
+        self.model.next_draw_offer_ply = ply + DRAW_OFFER_INTERVAL;
+        true
+    }
+
+    /// Gives the player the time of the settings for the game just started, lessons not being timed.
+    fn limit_player_time(&mut self) {
+        if let Some(time) = self.model.player_time {
+            self.model.session.limit_player_time(time);
+        }
+        self.emit_player_time();
     }
+
+    /// Ends the game once the player has run out of time.
+    fn check_player_time(&mut self) {
+        let now = Instant::now();
+        if self.model.session.check_time(now).is_some() {
+            self.cancel_engine_tasks();
+            self.model.dnd_data = None;
+            self.model.hint = None;
+            self.dispatch_session_events();
+            painter::Painter::draw(self).unwrap();
+        }
+        self.emit_player_time();
+    }
+
+    /// Only emits when the shown seconds change.
+    fn emit_player_time(&mut self) {
+        let seconds = self
+            .model
+            .session
+            .player_time_left(Instant::now())
+            .map(|time| time.as_secs());
+        if seconds != self.model.shown_player_time {
+            self.model.shown_player_time = seconds;
+            self.model.relm.stream().emit(PlayerTimeChanged(seconds));
+        }
+    }
+
+    /// Asks the engine in how many moves the player can win, if it can find out.
+    fn compute_optimum_moves(&mut self) {
+        let player_turn = self.model.session.is_player_turn();
+        self.submit_engine_task(EngineTask::OptimumMoves { player_turn });
+    }
+
+    /// Not while browsing, the computer only moving from the position the game stands at.
+    pub(crate) fn play_computer_move_if_needed(&mut self) {
+        if !self.model.session.is_in_progress()
+            || self.model.lesson_mode
+            || self.model.session.is_player_turn()
+            || self.model.session.is_browsing()
+        {
+            return;
+        }
+
+        self.submit_engine_task(EngineTask::ComputerMove(self.model.computer_strength));
+    }
+
+    fn submit_player_move(&mut self, player_move: Move) {
+        let messages = submit_move(&mut self.model.session, self.model.lesson_mode, player_move);
+        self.emit_messages(messages);
+    }
+
+    fn play_move(&mut self, move_to_play: Move) {
+        // Only fails if the game is over or the move is illegal : nothing to show then.
+        let _ = self.model.session.play_move(move_to_play);
+        self.dispatch_session_events();
+    }
+
+    fn dispatch_session_events(&mut self) {
+        let messages = get_session_messages(&mut self.model.session, self.model.lesson_mode);
+        self.emit_messages(messages);
+    }
+
+    fn emit_messages(&mut self, messages: Vec<Msg>) {
+        let mut position_changed = false;
+        for message in messages {
+            if let MovePlayed(_, _) = message {
+                self.model.hint = None;
+                self.model.hint_pending = false;
+                self.model.draw_offer_pending = false;
+            }
+            position_changed |= matches!(message, MovePlayed(_, _) | GameStarted);
+            self.model.relm.stream().emit(message);
+        }
+        if position_changed {
+            self.emit_position_changed();
+        }
+    }
+
+    fn emit_orientation(&self) {
+        self.model
+            .relm
+            .stream()
+            .emit(OrientationChanged(self.model.reversed));
+    }
+
+    /// The computer side is named after its engine, when it gives a name.
+    fn emit_players(&self) {
+        let player = tr!("You");
+        let computer = self
+            .model
+            .engine
+            .as_ref()
+            .and_then(|engine| engine.name())
+            .map(str::to_string)
+            .unwrap_or_else(|| tr!("Computer"));
+        let (white, black) = if self.model.session.player_side() == Color::White {
+            (player, computer)
+        } else {
+            (computer, player)
+        };
+        self.model.relm.stream().emit(PlayersChanged(white, black));
+    }
+
+    fn emit_position_changed(&self) {
+        let moves_chain = self.model.session.moves_chain().clone();
+        self.model
+            .relm
+            .stream()
+            .emit(PositionChanged(Box::new(moves_chain)));
+        self.model.relm.stream().emit(HistoryChanged(
+            Box::new(self.model.session.tree().clone()),
+            self.model.session.current_node(),
+        ));
+    }
+}
+
+/// Plays the move of the user, or only submits it in a lesson : the lesson decides
+/// whether to play it. Gives the messages to emit.
+fn submit_move(session: &mut GameSession, lesson_mode: bool, player_move: Move) -> Vec<Msg> {
+    if lesson_mode {
+        return vec![MoveAttempted(player_move)];
+    }
+    let _ = session.play_move(player_move);
+    get_session_messages(session, lesson_mode)
+}
+
+fn get_session_messages(session: &mut GameSession, lesson_mode: bool) -> Vec<Msg> {
+    session
+        .take_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            GameEvent::Started | GameEvent::Stopped if lesson_mode => None,
+            GameEvent::Started => Some(GameStarted),
+            GameEvent::MovePlayed { san, white_player } => Some(MovePlayed(san, white_player)),
+            GameEvent::TimeUp(_) => Some(TimeUp),
+            GameEvent::Over(outcome) => Some(GameOver(outcome)),
+            GameEvent::Stopped => Some(GameStopped),
+            GameEvent::Resumed => Some(GameResumed),
+        })
+        .collect()
 }
 
 fn create_context(widget: &gtk::DrawingArea) -> anyhow::Result<gtk::cairo::Context> {
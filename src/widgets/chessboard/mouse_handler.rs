@@ -1,42 +1,47 @@
-use gtk::gdk::{EventButton, EventMotion};
-use owlchess::{Color, File, Make, Rank};
+use owlchess::{Color, File, Move, Rank};
 
-use super::{
-    painter::Painter,
-    utils::{get_piece_type_from, get_uci_move_for},
-    ChessBoard, DragAndDropData,
-};
+use super::{painter::Painter, utils::get_piece_type_from, DragAndDropData};
+use crate::game::GameSession;
 
 pub(crate) struct MouseHandler;
 
+/// The part of the board state the mouse handler works on, so that it does
+/// not need the widget : the positions are given in the board area.
+pub(crate) struct BoardInput<'a> {
+    pub(crate) session: &'a GameSession,
+    pub(crate) dnd_data: &'a mut Option<DragAndDropData>,
+    pub(crate) reversed: bool,
+    pub(crate) lesson_mode: bool,
+    pub(crate) board_size: f64,
+}
+
 impl MouseHandler {
-    pub(crate) fn handle_button_down(board: &mut ChessBoard, event: EventButton) {
-        if !board.model.game_in_progress {
-            return;
+    /// Returns the move chosen if the click was on a promotion button.
+    pub(crate) fn handle_button_down(input: &mut BoardInput, x: f64, y: f64) -> Option<Move> {
+        if !input.session.is_in_progress() {
+            return None;
+        }
+
+        if !input.lesson_mode && !input.session.is_player_turn() {
+            return None;
         }
 
         // Cancelling if there is a pending promotion move.
-        let dnd_data = board.model.dnd_data.as_ref();
+        let dnd_data = input.dnd_data.as_ref();
         match dnd_data {
             Some(dnd_data) => {
                 if dnd_data.pending_promotion.is_some() {
-                    MouseHandler::handle_promotion_button_click(board, event);
-                    return;
+                    return MouseHandler::handle_promotion_button_click(input, x, y);
                 }
             }
             _ => {}
         }
 
-        let (x, y) = event.position();
-        let cells_size = board.common_size() as f64 * 0.111;
-        let col = ((x - cells_size * 0.5) / cells_size).floor() as i16;
-        let row = ((y - cells_size * 0.5) / cells_size).floor() as i16;
-        let file = if board.model.reversed { 7 - col } else { col };
-        let rank = if board.model.reversed { row } else { 7 - row };
+        let (file, rank) = MouseHandler::get_cell_at(input, x, y);
 
         let in_bounds = file >= 0 && file <= 7 && rank >= 0 && rank <= 7;
         if in_bounds {
-            let square = board.model.board.get2(
+            let square = input.session.board().get2(
                 File::from_index(file as usize),
                 Rank::from_index((7 - rank) as usize),
             );
@@ -44,7 +49,7 @@ impl MouseHandler {
             let piece_color = square.color();
 
             let not_empty_piece = piece_type != None && piece_color != None;
-            let white_turn = board.model.board.side() == Color::White;
+            let white_turn = input.session.board().side() == Color::White;
             let our_piece = match piece_color {
                 Some(piece_color) => {
                     (piece_color == Color::White && white_turn)
@@ -65,36 +70,35 @@ impl MouseHandler {
                     target_rank: rank as u8,
                     pending_promotion: None,
                 };
-                board.model.dnd_data = Some(drag_drop_data);
+                *input.dnd_data = Some(drag_drop_data);
             }
         }
+
+        None
     }
 
-    pub(crate) fn handle_button_up(board: &mut ChessBoard, event: EventButton) {
-        if !board.model.game_in_progress {
-            return;
+    /// Returns the move dropped, if it is legal.
+    pub(crate) fn handle_button_up(input: &mut BoardInput, x: f64, y: f64) -> Option<Move> {
+        if !input.session.is_in_progress() {
+            return None;
         }
 
         // Cancelling if there is a pending promotion move.
-        let dnd_data = board.model.dnd_data.as_ref();
+        let dnd_data = input.dnd_data.as_ref();
         match dnd_data {
             Some(dnd_data) => {
                 if dnd_data.pending_promotion.is_some() {
-                    return;
+                    return None;
                 }
             }
             _ => {}
         }
 
-        let (x, y) = event.position();
-        let cells_size = board.common_size() as f64 * 0.111;
-        let col = ((x - cells_size * 0.5) / cells_size).floor() as i16;
-        let row = ((y - cells_size * 0.5) / cells_size).floor() as i16;
-        let file = if board.model.reversed { 7 - col } else { col };
-        let rank = if board.model.reversed { row } else { 7 - row };
+        let (file, rank) = MouseHandler::get_cell_at(input, x, y);
+        let mut dropped_move = None;
 
-        if board.model.dnd_data.is_some() {
-            let dnd_data = board.model.dnd_data.as_mut().unwrap();
+        if input.dnd_data.is_some() {
+            let dnd_data = input.dnd_data.as_mut().unwrap();
             let start_file = dnd_data.start_file;
             let start_rank = dnd_data.start_rank;
 
@@ -104,48 +108,34 @@ impl MouseHandler {
                 && file <= 7;
 
             if is_promotion_move {
-                let white_turn = board.model.board.side() == Color::White;
+                let white_turn = input.session.board().side() == Color::White;
                 dnd_data.pending_promotion = Some(white_turn);
                 dnd_data.target_file = file as u8;
                 dnd_data.target_rank = rank as u8;
-                return;
+                return None;
             }
 
             if file < 0 || file > 7 || rank < 0 || rank > 7 {
-                board.model.dnd_data = None;
-                return;
+                *input.dnd_data = None;
+                return None;
             }
 
-            let uci_move = get_uci_move_for(start_file, start_rank, file as u8, rank as u8, None);
-            let matching_move = uci_move.into_move(&board.model.board);
-            
-            let move_san = match matching_move {
-                Ok(matching_move) => match matching_move.san(&board.model.board) {
-                    Ok(san) => Some(san.to_string()),
-                    _ => None,
-                },
-                Err(_) => None,
-            };
-    
-            if let Ok(matching_move) = matching_move {
-                match matching_move.make_raw(&mut board.model.board) {
-                    Ok(_) => board.process_move_done(matching_move, move_san),
-                    Err(_) => {}
-                }
-            }
+            dropped_move = input
+                .session
+                .move_from_squares(start_file, start_rank, file as u8, rank as u8, None);
         }
 
-        board.model.dnd_data = None;
-        MouseHandler::handle_game_termination(board);
+        *input.dnd_data = None;
+        dropped_move
     }
 
-    pub(crate) fn handle_mouse_drag(board: &mut ChessBoard, event: EventMotion) {
-        if !board.model.game_in_progress {
+    pub(crate) fn handle_mouse_drag(input: &mut BoardInput, x: f64, y: f64) {
+        if !input.session.is_in_progress() {
             return;
         }
 
         // Cancelling if there is a pending promotion move.
-        let dnd_data = board.model.dnd_data.as_ref();
+        let dnd_data = input.dnd_data.as_ref();
         match dnd_data {
             Some(dnd_data) => {
                 if dnd_data.pending_promotion.is_some() {
@@ -155,15 +145,10 @@ impl MouseHandler {
             _ => {}
         }
 
-        let (x, y) = event.position();
-        let cells_size = board.common_size() as f64 * 0.111;
-        let col = ((x - cells_size * 0.5) / cells_size).floor() as i16;
-        let row = ((y - cells_size * 0.5) / cells_size).floor() as i16;
-        let file = if board.model.reversed { 7 - col } else { col };
-        let rank = if board.model.reversed { row } else { 7 - row };
+        let (file, rank) = MouseHandler::get_cell_at(input, x, y);
 
-        match board.model.dnd_data {
-            Some(ref mut dnd_data) => {
+        match input.dnd_data.as_mut() {
+            Some(dnd_data) => {
                 dnd_data.x = x;
                 dnd_data.y = y;
 
@@ -178,17 +163,10 @@ impl MouseHandler {
         };
     }
 
-    pub(crate) fn handle_promotion_button_click(board: &mut ChessBoard, event: EventButton) {
-        let (x, y) = event.position();
-        let board_size = board.common_size() as f64;
-        let reversed = board.model.reversed;
-        let white_turn = board
-            .model
-            .dnd_data
-            .as_ref()
-            .unwrap()
-            .pending_promotion
-            .unwrap();
+    fn handle_promotion_button_click(input: &mut BoardInput, x: f64, y: f64) -> Option<Move> {
+        let board_size = input.board_size;
+        let reversed = input.reversed;
+        let white_turn = input.dnd_data.as_ref().unwrap().pending_promotion.unwrap();
 
         let buttons_y = if white_turn {
             board_size
@@ -225,24 +203,37 @@ impl MouseHandler {
         let knight_button_clicked = y_in_range && x_in_knight_button;
 
         if queen_button_clicked {
-            board.commit_promotion('q');
+            MouseHandler::commit_promotion(input, 'q')
         } else if rook_button_clicked {
-            board.commit_promotion('r');
+            MouseHandler::commit_promotion(input, 'r')
         } else if bishop_button_clicked {
-            board.commit_promotion('b');
+            MouseHandler::commit_promotion(input, 'b')
         } else if knight_button_clicked {
-            board.commit_promotion('n');
+            MouseHandler::commit_promotion(input, 'n')
+        } else {
+            None
         }
     }
 
-    fn handle_game_termination(board: &mut ChessBoard) {
-        let outcome_1 = board.model.board_moves_chain.calc_outcome();
-        let outcome_2 = board.model.board.calc_outcome();
+    fn commit_promotion(input: &mut BoardInput, piece_type: char) -> Option<Move> {
+        let dnd_data = input.dnd_data.take()?;
 
-        if let Some(outcome) = outcome_1 {
-            board.handle_game_termination(&outcome);
-        } else if let Some(outcome) = outcome_2 {
-            board.handle_game_termination(&outcome);
-        }
+        input.session.move_from_squares(
+            dnd_data.start_file,
+            dnd_data.start_rank,
+            dnd_data.target_file,
+            dnd_data.target_rank,
+            Some(piece_type),
+        )
+    }
+
+    /// File and rank (from 0 to 7 when inside the board) under the given position.
+    fn get_cell_at(input: &BoardInput, x: f64, y: f64) -> (i16, i16) {
+        let cells_size = input.board_size * 0.111;
+        let col = ((x - cells_size * 0.5) / cells_size).floor() as i16;
+        let row = ((y - cells_size * 0.5) / cells_size).floor() as i16;
+        let file = if input.reversed { 7 - col } else { col };
+        let rank = if input.reversed { row } else { 7 - row };
+        (file, rank)
     }
 }
@@ -1,10 +1,8 @@
-use super::ChessBoard;
-use super::{pieces_images::PiecesImages, utils::get_piece_type_from};
+use super::utils::get_coord_of;
+use super::{ChessBoard, HintStage};
+use crate::rendering::{pieces_images::PiecesImages, Diagram, Highlight, Theme};
 
-use core::ascii;
 use gtk::{cairo::Context, prelude::*};
-use owlchess::{Color, File, Rank};
-use std::f64::consts::PI;
 
 pub(crate) struct Painter;
 
@@ -20,21 +18,24 @@ impl Painter {
 
     pub(crate) fn draw(board: &mut ChessBoard) -> anyhow::Result<()> {
         let size = board.common_size();
-        let cells_size = (size as f64) * 0.111;
-        let white_turn = board.model.board.side() == Color::White;
-        let reversed = board.model.reversed;
 
         let image = gtk::cairo::ImageSurface::create(gtk::cairo::Format::ARgb32, size, size)?;
         let context = gtk::cairo::Context::new(&image)?;
 
-        let drag_drop_data = board.model.dnd_data.as_ref();
-
-        Painter::clear_background(&context, size as f64);
-        Painter::paint_cells(&context, cells_size, board);
-        Painter::draw_coordinates(&context, cells_size, reversed);
-        Painter::paint_pieces(&context, cells_size, board, reversed);
-        Painter::draw_player_turn(&context, cells_size, white_turn);
+        let diagram = Painter::build_diagram(board);
+        let moved_piece_cell = board
+            .model
+            .dnd_data
+            .as_ref()
+            .map(|dnd_data| get_coord_of(dnd_data.start_file, dnd_data.start_rank));
+        diagram.draw(
+            &context,
+            size as f64,
+            &board.model.pieces_images,
+            moved_piece_cell,
+        )?;
 
+        let drag_drop_data = board.model.dnd_data.as_ref();
         if let Some(drag_drop_data) = drag_drop_data {
             Painter::draw_moved_piece(&context, board);
             if let Some(white_turn) = drag_drop_data.pending_promotion {
@@ -42,170 +43,60 @@ impl Painter {
             }
         }
 
+        drop(context);
         board.set_image(&image)?;
         Ok(())
     }
 
-    fn clear_background(cx: &Context, size: f64) {
-        cx.set_source_rgb(0.3, 0.3, 0.8);
-        cx.rectangle(0.0, 0.0, size, size);
-        cx.fill().unwrap();
-    }
-
-    fn paint_cells(cx: &Context, cells_size: f64, widget_board: &ChessBoard) {
-        for row in 0..8 {
-            for col in 0..8 {
-                let file = if widget_board.model.reversed {
-                    7 - col
-                } else {
-                    col
-                };
-                let rank = if widget_board.model.reversed {
-                    row
-                } else {
-                    7 - row
-                };
-
-                let navajowhite = (1.0, 0.87, 0.68);
-                let peru = (0.8, 0.52, 0.25);
-
-                let olive = (0.5, 0.5, 0.0);
-                let indian_red = (0.8, 0.36, 0.36);
-
-                let is_white_cell = (row + col) % 2 == 0;
-                let mut background_color = if is_white_cell { navajowhite } else { peru };
-
-                let is_target_cell = match widget_board.model.dnd_data.as_ref() {
-                    Some(drag_drop_data) => {
-                        drag_drop_data.target_file == file && drag_drop_data.target_rank == rank
-                    }
-                    None => false,
-                };
-
-                let is_start_cell = match widget_board.model.dnd_data.as_ref() {
-                    Some(drag_drop_data) => {
-                        drag_drop_data.start_file == file && drag_drop_data.start_rank == rank
-                    }
-                    None => false,
-                };
-
-                if is_target_cell {
-                    background_color = indian_red;
-                }
-
-                if is_start_cell {
-                    background_color = olive;
-                }
-
-                let x = cells_size * (col as f64 + 0.5);
-                let y = cells_size * (row as f64 + 0.5);
-
-                cx.set_source_rgb(background_color.0, background_color.1, background_color.2);
-                cx.rectangle(x, y, cells_size, cells_size);
-                cx.fill().unwrap();
-            }
-        }
-    }
-
-    fn paint_pieces(cx: &Context, cells_size: f64, board: &ChessBoard, reversed: bool) {
-        for row in 0..8 {
-            for col in 0..8 {
-                let file = if reversed { 7 - col } else { col } as u8;
-                let rank = if reversed { row } else { 7 - row } as u8;
-                let square = board.model.board.get2(
-                    File::from_index(file as usize),
-                    Rank::from_index((7 - rank) as usize),
-                );
-                let piece_type = square.piece();
-                let piece_color = square.color();
-
-                let is_moved_piece = match board.model.dnd_data {
-                    Some(ref dnd_data) => {
-                        file == dnd_data.start_file && rank == dnd_data.start_rank
-                    }
-                    None => false,
-                };
-
-                if square.is_free() || is_moved_piece {
-                    continue;
-                }
-
-                let x = cells_size as f64 * (col as f64 + 0.5);
-                let y = cells_size as f64 * (row as f64 + 0.5);
-                let piece_type = get_piece_type_from(piece_type.unwrap(), piece_color.unwrap());
-                Painter::draw_piece(cx, board, piece_type, x, y);
+    /// The highlights are given from the least to the most important.
+    fn build_diagram(board: &ChessBoard) -> Diagram {
+        let light_green = (0.56, 0.93, 0.56);
+        let sea_green = (0.18, 0.55, 0.34);
+        let indian_red = (0.8, 0.36, 0.36);
+        let olive = (0.5, 0.5, 0.0);
+
+        let mut diagram = Diagram::new(board.model.session.board().clone());
+        diagram.reversed = board.model.reversed;
+        diagram.theme = Theme::CLASSIC;
+
+        if let Some(hint) = board.model.hint.as_ref() {
+            diagram.highlights.push(Highlight {
+                cell: hint.hint_move.src(),
+                color: light_green,
+            });
+            if hint.stage == HintStage::Destination {
+                diagram.highlights.push(Highlight {
+                    cell: hint.hint_move.dst(),
+                    color: sea_green,
+                });
             }
         }
-    }
-
-    fn draw_coordinates(cx: &Context, cells_size: f64, reversed: bool) {
-        cx.set_source_rgb(0.78, 0.78, 0.47);
-        cx.set_font_size(cells_size * 0.3);
-        for col in 0..8 {
-            let file = if reversed { 7 - col } else { col };
-            let file_letter = (ascii::escape_default(b'A').next().unwrap() + file) as char;
-            let file_string = format!("{}", file_letter);
 
-            let x = cells_size * (0.9 + col as f64);
-            let y1 = cells_size * 0.35;
-            let y2 = cells_size * 8.85;
-
-            cx.move_to(x, y1);
-            cx.show_text(&file_string).unwrap();
-
-            cx.move_to(x, y2);
-            cx.show_text(&file_string).unwrap();
+        if let Some(dnd_data) = board.model.dnd_data.as_ref() {
+            diagram.highlights.push(Highlight {
+                cell: get_coord_of(dnd_data.target_file, dnd_data.target_rank),
+                color: indian_red,
+            });
+            diagram.highlights.push(Highlight {
+                cell: get_coord_of(dnd_data.start_file, dnd_data.start_rank),
+                color: olive,
+            });
         }
 
-        for row in 0..8 {
-            let rank = if reversed { row } else { 7 - row };
-            let rank_letter = (ascii::escape_default(b'1').next().unwrap() + rank) as char;
-            let rank_string = format!("{}", rank_letter);
-
-            let y = cells_size * (1.15 + row as f64);
-            let x1 = cells_size * 0.15;
-            let x2 = cells_size * 8.65;
-
-            cx.move_to(x1, y);
-            cx.show_text(&rank_string).unwrap();
-
-            cx.move_to(x2, y);
-            cx.show_text(&rank_string).unwrap();
-        }
-    }
-
-    fn draw_player_turn(cx: &Context, cells_size: f64, white_turn: bool) {
-        let color = if white_turn {
-            (1.0, 1.0, 1.0)
-        } else {
-            (0.0, 0.0, 0.0)
-        };
-        let location = cells_size as f64 * 8.75;
-        let radius = cells_size as f64 * 0.25;
-
-        cx.set_source_rgb(color.0, color.1, color.2);
-        cx.arc(location, location, radius, 0.0, 2.0 * PI);
-        cx.fill().unwrap();
+        diagram
     }
 
     fn draw_moved_piece(cx: &Context, board: &ChessBoard) {
         let drag_drop_data = board.model.dnd_data.as_ref().unwrap();
         let half_cells_size = board.common_size() as f64 * 0.055;
-        Painter::draw_piece(
+        board.model.pieces_images.draw(
             cx,
-            board,
             drag_drop_data.piece,
             drag_drop_data.x - half_cells_size,
             drag_drop_data.y - half_cells_size,
         );
     }
 
-    fn draw_piece(cx: &Context, board: &ChessBoard, piece_type: char, x: f64, y: f64) {
-        let pixbuf = &board.model.pieces_images.pixbufs[&piece_type];
-        cx.set_source_pixbuf(pixbuf, x, y);
-        cx.paint().unwrap();
-    }
-
     fn draw_promotion_buttons(cx: &Context, board: &ChessBoard, white_turn: bool) {
         let board_size = board.common_size() as f64;
         let reversed = board.model.reversed;
@@ -0,0 +1,114 @@
+use gtk::prelude::*;
+use gtk::{ComboBoxText, Dialog, DialogFlags, Entry, Label, ResponseType};
+use tr::tr;
+
+/// A question of the dialog, with its own list of choices.
+pub struct ChoiceList<'a> {
+    pub question: &'a str,
+    pub choices: &'a [String],
+    pub default_index: usize,
+}
+
+/// Asks the user to pick one of the choices, returns its index or None if cancelled.
+pub fn ask_choice(
+    parent: &gtk::Window,
+    title: &str,
+    question: &str,
+    validate_label: &str,
+    choices: &[String],
+    default_index: usize,
+) -> Option<usize> {
+    let list = ChoiceList {
+        question,
+        choices,
+        default_index,
+    };
+    ask_choices(parent, title, validate_label, &[list]).map(|indexes| indexes[0])
+}
+
+/// Asks the user to pick one choice of each list, returns their indexes or None if cancelled.
+pub fn ask_choices(
+    parent: &gtk::Window,
+    title: &str,
+    validate_label: &str,
+    lists: &[ChoiceList],
+) -> Option<Vec<usize>> {
+    let dialog = Dialog::with_buttons(
+        Some(title),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[
+            (&tr!("Cancel"), ResponseType::Cancel),
+            (validate_label, ResponseType::Ok),
+        ],
+    );
+
+    let content = dialog.content_area();
+    content.set_spacing(5);
+    let mut combos = vec![];
+    for list in lists {
+        let label = Label::new(Some(list.question));
+        let choices_combo = ComboBoxText::new();
+        for choice in list.choices {
+            choices_combo.append_text(choice);
+        }
+        choices_combo.set_active(Some(list.default_index as u32));
+
+        content.add(&label);
+        content.add(&choices_combo);
+        combos.push(choices_combo);
+    }
+    dialog.show_all();
+
+    let response = dialog.run();
+    let selected_indexes: Option<Vec<usize>> = combos
+        .iter()
+        .map(|combo| combo.active().map(|index| index as usize))
+        .collect();
+    dialog.emit_close();
+
+    if response != ResponseType::Ok {
+        return None;
+    }
+
+    selected_indexes
+}
+
+/// Asks the user for a text, starting from the given one. Returns None if cancelled.
+pub fn ask_text(
+    parent: &gtk::Window,
+    title: &str,
+    question: &str,
+    validate_label: &str,
+    initial_text: &str,
+) -> Option<String> {
+    let dialog = Dialog::with_buttons(
+        Some(title),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[
+            (&tr!("Cancel"), ResponseType::Cancel),
+            (validate_label, ResponseType::Ok),
+        ],
+    );
+    dialog.set_default_response(ResponseType::Ok);
+
+    let content = dialog.content_area();
+    content.set_spacing(5);
+    let entry = Entry::new();
+    entry.set_text(initial_text);
+    entry.set_activates_default(true);
+    content.add(&Label::new(Some(question)));
+    content.add(&entry);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let text = entry.text().to_string();
+    dialog.emit_close();
+
+    if response != ResponseType::Ok {
+        return None;
+    }
+
+    Some(text)
+}
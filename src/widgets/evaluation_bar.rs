@@ -0,0 +1,234 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::cairo::Context;
+use gtk::prelude::*;
+use owlchess::{Board, Color, Outcome};
+use relm::{Relm, Widget};
+use relm_derive::{widget, Msg};
+use tr::tr;
+
+use crate::engine::Score;
+
+/// What the bar shows, from the white side point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evaluation {
+    /// Positive when White is better.
+    Centipawns(i32),
+    /// Moves to mate, 0 once the position is checkmate.
+    Mate {
+        moves: u32,
+        winner: Color,
+    },
+    Draw,
+}
+
+impl Evaluation {
+    /// The engine score is given from the point of view of the side to move.
+    pub fn from_score(score: Score, side_to_move: Color) -> Self {
+        let sign = if side_to_move == Color::White { 1 } else { -1 };
+        match score {
+            Score::Centipawns(centipawns) => Evaluation::Centipawns(sign * centipawns),
+            Score::Mate(moves) => Evaluation::Mate {
+                moves: moves.unsigned_abs(),
+                winner: if moves > 0 {
+                    side_to_move
+                } else {
+                    side_to_move.inv()
+                },
+            },
+        }
+    }
+
+    /// The evaluation of a position already over, if so.
+    pub fn from_position(position: &Board) -> Option<Self> {
+        match position.calc_outcome()? {
+            Outcome::Win { side, .. } => Some(Evaluation::Mate {
+                moves: 0,
+                winner: side,
+            }),
+            Outcome::Draw(_) => Some(Evaluation::Draw),
+        }
+    }
+
+    /// Part of the bar given to White, from 0 to 1 : the expected score of the Elo formula.
+    fn white_share(&self) -> f64 {
+        match self {
+            Evaluation::Centipawns(centipawns) => {
+                1.0 / (1.0 + 10f64.powf(-*centipawns as f64 / 400.0))
+            }
+            Evaluation::Mate { winner, .. } => {
+                if *winner == Color::White {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Evaluation::Draw => 0.5,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Evaluation::Centipawns(centipawns) => format!("{:+.1}", *centipawns as f64 / 100.0),
+            Evaluation::Mate { moves: 0, .. } => tr!("Checkmate"),
+            Evaluation::Mate { moves, .. } => tr!("Mate in {}", moves),
+            Evaluation::Draw => tr!("Draw"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BarState {
+    evaluation: Option<Evaluation>,
+    reversed: bool,
+}
+
+#[widget]
+impl Widget for EvaluationBar {
+    view! {
+        #[name="root"]
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            spacing: 5,
+            #[name="drawing_area"]
+            gtk::DrawingArea {
+                vexpand: true,
+            },
+            #[name="label"]
+            gtk::Label {
+            },
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::SetEvaluation(evaluation) => {
+                self.model.state.borrow_mut().evaluation = evaluation;
+                let text = evaluation
+                    .map(|evaluation| evaluation.text())
+                    .unwrap_or_default();
+                self.widgets.label.set_text(&text);
+                self.widgets.drawing_area.queue_draw();
+            }
+            Msg::SetReversed(reversed) => {
+                self.model.state.borrow_mut().reversed = reversed;
+                self.widgets.drawing_area.queue_draw();
+            }
+        }
+    }
+
+    fn model(_: &Relm<Self>, _: ()) -> Model {
+        Model {
+            state: Rc::new(RefCell::new(BarState::default())),
+        }
+    }
+
+    fn init_view(&mut self) {
+        self.widgets.drawing_area.set_size_request(24, 400);
+        let state = self.model.state.clone();
+        self.widgets.drawing_area.connect_draw(move |area, cx| {
+            let width = area.allocated_width() as f64;
+            let height = area.allocated_height() as f64;
+            // Only fails if the drawing context is broken : nothing to draw then.
+            let _ = draw_bar(cx, width, height, &state.borrow());
+            gtk::Inhibit(false)
+        });
+    }
+}
+
+/// The white part starts from the side of the white pieces.
+fn draw_bar(cx: &Context, width: f64, height: f64, state: &BarState) -> anyhow::Result<()> {
+    cx.set_source_rgb(0.25, 0.25, 0.25);
+    cx.rectangle(0.0, 0.0, width, height);
+    cx.fill()?;
+
+    let white_share = match state.evaluation {
+        Some(evaluation) => evaluation.white_share(),
+        None => return Ok(()),
+    };
+    let white_height = height * white_share;
+    let white_top = if state.reversed {
+        0.0
+    } else {
+        height - white_height
+    };
+    cx.set_source_rgb(0.95, 0.95, 0.95);
+    cx.rectangle(0.0, white_top, width, white_height);
+    cx.fill()?;
+
+    // Middle line, where the position is equal.
+    cx.set_source_rgb(0.8, 0.2, 0.2);
+    cx.rectangle(0.0, height / 2.0 - 1.0, width, 2.0);
+    cx.fill()?;
+    Ok(())
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    SetEvaluation(Option<Evaluation>),
+    SetReversed(bool),
+}
+
+pub struct Model {
+    state: Rc<RefCell<BarState>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_turned_to_the_white_side() {
+        assert_eq!(
+            Evaluation::from_score(Score::Centipawns(150), Color::Black),
+            Evaluation::Centipawns(-150)
+        );
+        assert_eq!(
+            Evaluation::from_score(Score::Mate(-4), Color::White),
+            Evaluation::Mate {
+                moves: 4,
+                winner: Color::Black
+            }
+        );
+        assert_eq!(
+            Evaluation::from_score(Score::Mate(2), Color::Black),
+            Evaluation::Mate {
+                moves: 2,
+                winner: Color::Black
+            }
+        );
+    }
+
+    #[test]
+    fn white_share_follows_the_evaluation() {
+        assert_eq!(Evaluation::Centipawns(0).white_share(), 0.5);
+        assert!(Evaluation::Centipawns(300).white_share() > 0.8);
+        assert!(Evaluation::Centipawns(-300).white_share() < 0.2);
+        assert_eq!(Evaluation::Draw.white_share(), 0.5);
+        let black_mates = Evaluation::Mate {
+            moves: 3,
+            winner: Color::Black,
+        };
+        assert_eq!(black_mates.white_share(), 0.0);
+        assert_eq!(black_mates.text(), "Mate in 3");
+    }
+
+    #[test]
+    fn finished_positions_are_evaluated() {
+        let checkmate = Board::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            Evaluation::from_position(&checkmate),
+            Some(Evaluation::Mate {
+                moves: 0,
+                winner: Color::White
+            })
+        );
+        let stalemate = Board::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            Evaluation::from_position(&stalemate),
+            Some(Evaluation::Draw)
+        );
+        assert_eq!(Evaluation::from_position(&Board::initial()), None);
+    }
+}
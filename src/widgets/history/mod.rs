@@ -1,35 +1,85 @@
-use gtk::{
-    traits::{CssProviderExt, StyleContextExt, WidgetExt, FlowBoxExt, ContainerExt},
-    CssProvider, STYLE_PROVIDER_PRIORITY_APPLICATION,
-};
+use std::collections::HashSet;
+
+use gtk::prelude::*;
+use gtk::{CssProvider, STYLE_PROVIDER_PRIORITY_APPLICATION};
+use owlchess::Outcome;
 use relm::{Relm, Widget};
 use relm_derive::{widget, Msg};
+use tr::tr;
+
+use super::choice_dialog::ask_text;
+use super::statistics_dialog::format_duration;
+use crate::game::describe_outcome;
+use crate::game::tree::{GameTree, NodeId, TreeItem};
+use crate::pgn::{nag_symbol, NAG_SYMBOLS};
 
-mod utils;
+pub(crate) mod utils;
 
 #[widget]
 impl Widget for History {
     view! {
         #[name="root"]
-        gtk::FlowBox {
+        gtk::Box {
             widget_name: "history_root",
-            halign: gtk::Align::Start,
-            valign: gtk::Align::Start,
+            orientation: gtk::Orientation::Vertical,
+            #[name="players_label"]
+            gtk::Label {
+                halign: gtk::Align::Start,
+            },
+            #[name="moves_box"]
+            gtk::FlowBox {
+                halign: gtk::Align::Start,
+                valign: gtk::Align::Start,
+                selection_mode: gtk::SelectionMode::None,
+            },
+            #[name="summary_label"]
+            gtk::Label {
+                halign: gtk::Align::Start,
+                line_wrap: true,
+            },
         }
     }
 
     fn update(&mut self, event: Msg) {
         match event {
             Msg::NewGame() => self.start_new_game(),
-            Msg::AddMoveSan(san, white_player) => self.add_move_san(utils::san_to_fan(san, white_player), white_player),
+            Msg::SetTree(tree, current_node) => {
+                self.model.tree = Some(*tree);
+                self.model.current_node = current_node;
+                self.refresh();
+            }
+            Msg::SetPlayers(white, black) => {
+                self.model.players = Some((white, black));
+                self.refresh();
+            }
+            Msg::SetGameDuration(seconds) => {
+                self.model.duration_secs = Some(seconds);
+                self.refresh();
+            }
+            Msg::ToggleCollapsed(node) => {
+                if !self.model.collapsed.remove(&node) {
+                    self.model.collapsed.insert(node);
+                }
+                self.refresh();
+            }
+            Msg::ShowMoveMenu(node) => self.show_move_menu(node),
+            Msg::EditComment(node) => self.edit_comment(node),
+            Msg::NodeSelected(_) => {}
+            Msg::CommentEdited(_, _) => {}
+            Msg::NagToggled(_, _) => {}
+            Msg::PromoteVariation(_) => {}
+            Msg::DeleteVariation(_) => {}
         }
     }
 
     fn model(relm: &Relm<Self>, _: ()) -> Model {
         Model {
             relm: relm.clone(),
-            game_in_progress: false,
-            move_number: 1,
+            tree: None,
+            current_node: GameTree::ROOT,
+            collapsed: HashSet::new(),
+            players: None,
+            duration_secs: None,
         }
     }
 
@@ -39,64 +89,267 @@ impl Widget for History {
         let provider = CssProvider::new();
         provider.load_from_data(style).unwrap();
         style_context.add_provider(&provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
+        add_style(self.widgets.players_label.clone(), "players_label");
+        add_style(self.widgets.summary_label.clone(), "summary_label");
     }
 }
 
 impl History {
     fn start_new_game(&mut self) {
+        self.model.tree = None;
+        self.model.current_node = GameTree::ROOT;
+        self.model.collapsed.clear();
+        self.model.players = None;
+        self.model.duration_secs = None;
+        self.refresh();
+    }
+
+    /// Builds the buttons again from the tree : the moves with their annotations,
+    /// the variations between parentheses, the collapsed variations, then the result.
+    /// The players are given above them and, once the game is over, its summary below.
+    fn refresh(&mut self) {
         self.clear_content();
+        let players = self
+            .model
+            .players
+            .as_ref()
+            .map(|(white, black)| tr!("White : {}, Black : {}", white, black));
+        self.widgets
+            .players_label
+            .set_text(players.as_deref().unwrap_or_default());
+        self.widgets.summary_label.set_text("");
+        let tree = match self.model.tree.as_ref() {
+            Some(tree) => tree,
+            None => return,
+        };
 
-        self.model.move_number = 1;
+        let mut variation_depth = 0;
+        for item in tree.items(&self.model.collapsed) {
+            let button = match item {
+                TreeItem::MoveNumber(text) => {
+                    add_style(gtk::Button::with_label(&text), "number_button")
+                }
+                TreeItem::Move(node) => self.build_move_button(tree, node, variation_depth),
+                TreeItem::Comment(node) => {
+                    let label = gtk::Label::new(tree.comment(node));
+                    label.set_line_wrap(true);
+                    label.style_context().add_class("comment_label");
+                    self.widgets.moves_box.insert(&label, -1);
+                    continue;
+                }
+                TreeItem::VariationStart => {
+                    variation_depth += 1;
+                    add_style(gtk::Button::with_label("("), "parenthesis_button")
+                }
+                TreeItem::VariationEnd => {
+                    variation_depth -= 1;
+                    add_style(gtk::Button::with_label(")"), "parenthesis_button")
+                }
+                TreeItem::Collapsed(node) => {
+                    let button = add_style(gtk::Button::with_label("(…)"), "parenthesis_button");
+                    button.set_tooltip_text(Some(&tr!("Expand the variation")));
+                    let stream = self.model.relm.stream().clone();
+                    button.connect_clicked(move |_| stream.emit(Msg::ToggleCollapsed(node)));
+                    button
+                }
+            };
+            self.widgets.moves_box.insert(&button, -1);
+        }
+
+        if let Some(outcome) = tree.outcome() {
+            let label = gtk::Label::new(Some(utils::format_result(outcome)));
+            label.style_context().add_class("result_label");
+            self.widgets.moves_box.insert(&label, -1);
+            self.widgets.summary_label.set_text(&format_summary(
+                outcome,
+                self.model.duration_secs,
+                tree.main_line_moves(),
+            ));
+        }
 
-        self.add_move_number();
         self.widgets.root.show_all();
     }
 
-    fn add_move_san(&mut self, san: String, white_player: bool) {
-        let button = gtk::Button::with_label(&san);
-        let style_context = button.style_context();
-        style_context.add_class("move_button");
-        let style = include_bytes!("./style.css");
-        let provider = CssProvider::new();
-        provider.load_from_data(style).unwrap();
-        style_context.add_provider(&provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
-        self.widgets.root.insert(&button, -1);
+    /// A click shows the position after the move, a right click its annotations and
+    /// the actions on its variation.
+    fn build_move_button(
+        &self,
+        tree: &GameTree,
+        node: NodeId,
+        variation_depth: u32,
+    ) -> gtk::Button {
+        let san = utils::san_to_fan(tree.san(node).to_string(), tree.is_white_move(node));
+        let san = format_annotated_move(san, tree.nags(node));
+        let style_class = if variation_depth > 0 {
+            "variation_button"
+        } else {
+            "move_button"
+        };
+        let button = add_style(gtk::Button::with_label(&san), style_class);
+        if node == self.model.current_node {
+            button.style_context().add_class("current_move");
+        }
+
+        let stream = self.model.relm.stream().clone();
+        button.connect_clicked(move |_| stream.emit(Msg::NodeSelected(node)));
+        let stream = self.model.relm.stream().clone();
+        button.connect_button_press_event(move |_, event| {
+            if event.button() == 3 {
+                stream.emit(Msg::ShowMoveMenu(node));
+                return gtk::Inhibit(true);
+            }
+            gtk::Inhibit(false)
+        });
+        button
+    }
+
+    /// The variation actions are only given for the moves of a variation.
+    fn show_move_menu(&self, node: NodeId) {
+        let tree = match self.model.tree.as_ref() {
+            Some(tree) => tree,
+            None => return,
+        };
 
-        if !white_player {
-            self.model.move_number += 1;
-            self.add_move_number();
+        let menu = gtk::Menu::new();
+        let comment_item = gtk::MenuItem::with_label(&tr!("Comment..."));
+        let stream = self.model.relm.stream().clone();
+        comment_item.connect_activate(move |_| stream.emit(Msg::EditComment(node)));
+        menu.append(&comment_item);
+
+        for (nag, symbol) in NAG_SYMBOLS {
+            let item = gtk::CheckMenuItem::with_label(symbol);
+            item.set_active(tree.nags(node).contains(&nag));
+            let stream = self.model.relm.stream().clone();
+            item.connect_activate(move |_| stream.emit(Msg::NagToggled(node, nag)));
+            menu.append(&item);
         }
 
-        self.widgets.root.show_all();
+        if let Some(variation_start) = tree.variation_start(node) {
+            menu.append(&gtk::SeparatorMenuItem::new());
+            let actions = [
+                (
+                    tr!("Promote to main line"),
+                    Msg::PromoteVariation as fn(NodeId) -> Msg,
+                    node,
+                ),
+                (tr!("Delete variation"), Msg::DeleteVariation, node),
+                (
+                    tr!("Collapse variation"),
+                    Msg::ToggleCollapsed,
+                    variation_start,
+                ),
+            ];
+            for (label, message, target) in actions {
+                let item = gtk::MenuItem::with_label(&label);
+                let stream = self.model.relm.stream().clone();
+                item.connect_activate(move |_| stream.emit(message(target)));
+                menu.append(&item);
+            }
+        }
+
+        menu.set_attach_widget(Some(&self.widgets.root));
+        menu.show_all();
+        menu.popup_easy(3, gtk::current_event_time());
     }
 
-    fn add_move_number(&mut self) {
-        let button_label = format!("{}.", self.model.move_number);
-        let button = gtk::Button::with_label(&button_label);
-        let style_context = button.style_context();
-        style_context.add_class("number_button");
-        let style = include_bytes!("./style.css");
-        let provider = CssProvider::new();
-        provider.load_from_data(style).unwrap();
-        style_context.add_provider(&provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
-        self.widgets.root.insert(&button, -1);
+    fn edit_comment(&self, node: NodeId) {
+        let parent = match self.widgets.root.toplevel() {
+            Some(toplevel) => match toplevel.downcast::<gtk::Window>() {
+                Ok(window) => window,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        let current_comment = self
+            .model
+            .tree
+            .as_ref()
+            .and_then(|tree| tree.comment(node))
+            .unwrap_or_default()
+            .to_string();
+        if let Some(comment) = ask_text(
+            &parent,
+            &tr!("Comment"),
+            &tr!("Comment of the move :"),
+            &tr!("Save"),
+            &current_comment,
+        ) {
+            self.model
+                .relm
+                .stream()
+                .emit(Msg::CommentEdited(node, Some(comment)));
+        }
     }
 
     fn clear_content(&mut self) {
-        for widget in self.widgets.root.children() {
-            self.widgets.root.remove(&widget);
+        for widget in self.widgets.moves_box.children() {
+            self.widgets.moves_box.remove(&widget);
+        }
+    }
+}
+
+/// The move assessments follow the move, the position assessments are apart.
+fn format_annotated_move(san: String, nags: &[u8]) -> String {
+    let mut text = san;
+    for nag in nags {
+        let symbol = nag_symbol(*nag)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("${}", nag));
+        if *nag > 9 {
+            text.push(' ');
         }
+        text.push_str(&symbol);
+    }
+    text
+}
+
+/// The reason of the end, the duration when known, then the number of moves.
+fn format_summary(outcome: Outcome, duration_secs: Option<u64>, moves: usize) -> String {
+    let mut parts = vec![describe_outcome(outcome)];
+    if let Some(seconds) = duration_secs {
+        parts.push(tr!("Duration : {}", format_duration(seconds)));
     }
+    parts.push(tr!("Moves : {}", moves));
+    parts.join(" \u{b7} ")
+}
+
+fn add_style<W: IsA<gtk::Widget>>(widget: W, style_class: &str) -> W {
+    let style_context = widget.style_context();
+    style_context.add_class(style_class);
+    let style = include_bytes!("./style.css");
+    let provider = CssProvider::new();
+    provider.load_from_data(style).unwrap();
+    style_context.add_provider(&provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
+    widget
 }
 
 #[derive(Msg)]
 pub enum Msg {
     NewGame(),
-    AddMoveSan(String, bool),
+    SetTree(Box<GameTree>, NodeId),
+    /// The white and black sides of the game, shown above the moves.
+    SetPlayers(String, String),
+    /// The game has lasted these seconds, shown in its summary once it is over.
+    SetGameDuration(u64),
+    ToggleCollapsed(NodeId),
+    ShowMoveMenu(NodeId),
+    EditComment(NodeId),
+    /// The position after the move has been asked for.
+    NodeSelected(NodeId),
+    /// An empty comment removes it.
+    CommentEdited(NodeId, Option<String>),
+    NagToggled(NodeId, u8),
+    PromoteVariation(NodeId),
+    DeleteVariation(NodeId),
 }
 
 pub struct Model {
     relm: Relm<History>,
-    game_in_progress: bool,
-    move_number: u16,
+    tree: Option<GameTree>,
+    current_node: NodeId,
+    /// First nodes of the variations shown without their moves.
+    collapsed: HashSet<NodeId>,
+    /// The white and black sides.
+    players: Option<(String, String)>,
+    duration_secs: Option<u64>,
 }
@@ -1,3 +1,5 @@
+use owlchess::{Color, Outcome};
+
 pub(crate) fn san_to_fan(san: String, white_player: bool) -> String {
     let san_as_vector: Vec<char> = san.chars().collect();
     let mut first_occurence_index = None;
@@ -35,6 +37,37 @@ pub(crate) fn san_to_fan(san: String, white_player: bool) -> String {
     }
 }
 
+/// The figurine of the piece given by its FEN letter.
+pub(crate) fn piece_glyph(piece: char) -> char {
+    let white = piece.is_ascii_uppercase();
+    match piece.to_ascii_uppercase() {
+        'P' => pawn_text(white),
+        'N' => knight_text(white),
+        'B' => bishop_text(white),
+        'R' => rook_text(white),
+        'Q' => queen_text(white),
+        'K' => king_text(white),
+        _ => panic!("Forbidden piece value : {}.", piece),
+    }
+}
+
+/// The result token ending the moves, such as "1-0" or "½-½".
+pub(crate) fn format_result(outcome: Outcome) -> &'static str {
+    match outcome.winner() {
+        Some(Color::White) => "1-0",
+        Some(Color::Black) => "0-1",
+        None => "\u{bd}-\u{bd}",
+    }
+}
+
+fn pawn_text(white: bool) -> char {
+    if white {
+        '\u{2659}'
+    } else {
+        '\u{265f}'
+    }
+}
+
 fn knight_text(white: bool) -> char {
     if white {
         '\u{2658}'
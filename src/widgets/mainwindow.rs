@@ -1,22 +1,69 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::MemoryInputStream;
 use gtk::glib::Bytes;
 use gtk::ResponseType;
 use gtk::{
-    prelude::*, traits::ToolbarExt, ButtonsType, DialogFlags, MessageDialog, MessageType,
-    ToolButton,
+    prelude::*, traits::ToolbarExt, ButtonsType, DialogFlags, FileChooserAction, FileChooserDialog,
+    FileFilter, IconSize, MessageDialog, MessageType, ToolButton,
 };
-use owlchess::{Color, DrawReason, Outcome, WinReason};
+use owlchess::{Color, Move, MoveChain, Outcome};
 use relm::{connect, Relm, Widget};
 use relm_derive::{widget, Msg};
 
 use super::chessboard::{ChessBoard, Msg as BoardMsg};
 use BoardMsg::{
-    GameOver as BoardGameOver, GameStarted as BoardGameStarted, GameStopped as BoardGameStopped,
-    StartGame as BoardStartGame, StopGame as BoardStopGame, MovePlayed as BoardMovePlayed,
+    AcceptDraw as BoardAcceptDraw, ClaimDraw as BoardClaimDraw,
+    DeleteVariation as BoardDeleteVariation, DrawDeclined as BoardDrawDeclined,
+    DrawNotClaimable as BoardDrawNotClaimable, DrawOffered as BoardDrawOffered,
+    EndLesson as BoardEndLesson, EngineError as BoardEngineError, GameOver as BoardGameOver,
+    GameResumed as BoardGameResumed, GameStarted as BoardGameStarted,
+    GameStopped as BoardGameStopped, GoToNode as BoardGoToNode, HintUsed as BoardHintUsed,
+    HistoryChanged as BoardHistoryChanged, LoadGame as BoardLoadGame,
+    MoveAttempted as BoardMoveAttempted, MovePlayed as BoardMovePlayed,
+    OfferDraw as BoardOfferDraw, OptimumMovesComputed as BoardOptimumMovesComputed,
+    OrientationChanged as BoardOrientationChanged, PlayMove as BoardPlayMove,
+    PlayerTimeChanged as BoardPlayerTimeChanged, PlayersChanged as BoardPlayersChanged,
+    PositionChanged as BoardPositionChanged, PromoteVariation as BoardPromoteVariation,
+    Repaint as BoardRepaint, Resign as BoardResign, RetryFrom as BoardRetryFrom,
+    ReviewCancelled as BoardReviewCancelled, ReviewFinished as BoardReviewFinished,
+    SetComment as BoardSetComment, SetEngine as BoardSetEngine,
+    SetPlayerTime as BoardSetPlayerTime, ShowHint as BoardShowHint, StartGame as BoardStartGame,
+    StartLesson as BoardStartLesson, StartReview as BoardStartReview, StopGame as BoardStopGame,
+    TimeUp as BoardTimeUp, ToggleNag as BoardToggleNag,
 };
 
-use super::history::{self, History};
+use super::analysis::{self, AnalysisPanel, Msg::Evaluated as AnalysisEvaluated};
+use super::choice_dialog::{ask_choice, ask_choices, ChoiceList};
+use super::evaluation_bar::{self, Evaluation, EvaluationBar};
+use super::history::{
+    self, History,
+    Msg::{
+        CommentEdited as HistoryCommentEdited, DeleteVariation as HistoryDeleteVariation,
+        NagToggled as HistoryNagToggled, NodeSelected as HistoryNodeSelected,
+        PromoteVariation as HistoryPromoteVariation,
+    },
+};
+use super::queue_dialog::show_queue_dialog;
+use super::statistics_dialog::show_statistics_dialog;
+use crate::engine::UciEngine;
+use crate::exercises::difficulty::{generate_rated_exercise, Difficulty};
+use crate::exercises::filter::{generate_filtered_exercise, GenerationFilter};
+use crate::exercises::{generate_exercise, EndgameKind, Exercise, Goal};
+use crate::game::describe_outcome;
+use crate::game::review::{format_accuracies, CriticalMove, ReviewSummary};
+use crate::game::tree::{self, GameTree, NodeId};
+use crate::lessons::{self, Answer, LessonPlayer};
+use crate::packs::{self, ExercisePack};
+use crate::scheduling::next_due_drill;
+use crate::settings::Settings;
+use crate::statistics::{current_timestamp, ExerciseResult, Statistics};
 
 use tr::tr;
 
@@ -34,6 +81,9 @@ impl Widget for MainWindow {
                 gtk::Box {
                     orientation: gtk::Orientation::Horizontal,
                     spacing: 5,
+                    #[name="evaluation_bar"]
+                    EvaluationBar {
+                    },
                     #[name="board"]
                     ChessBoard {
                         halign: gtk::Align::Center,
@@ -41,12 +91,50 @@ impl Widget for MainWindow {
                         BoardGameOver(outcome) => GameOver(outcome),
                         BoardGameStarted => GameStarted,
                         BoardGameStopped => GameStoppedByUser,
+                        BoardGameResumed => GameResumed,
                         BoardMovePlayed(ref san, white_player) => MovePlayed(san.clone(), white_player),
+                        BoardHintUsed => HintUsed,
+                        BoardOptimumMovesComputed(moves) => OptimumMovesComputed(moves),
+                        BoardEngineError(ref message) => EngineError(message.clone()),
+                        BoardMoveAttempted(attempted_move) => LessonMoveAttempted(attempted_move),
+                        BoardPositionChanged(ref moves_chain) => PositionChanged(moves_chain.clone()),
+                        BoardOrientationChanged(reversed) => OrientationChanged(reversed),
+                        BoardHistoryChanged(ref tree, node) => HistoryChanged(tree.clone(), node),
+                        BoardReviewFinished(ref summary, player_side) => ReviewFinished(summary.clone(), player_side),
+                        BoardReviewCancelled => ReviewCancelled,
+                        BoardDrawOffered => DrawOffered,
+                        BoardDrawDeclined => DrawDeclined,
+                        BoardDrawNotClaimable => DrawNotClaimable,
+                        BoardPlayerTimeChanged(seconds) => PlayerTimeChanged(seconds),
+                        BoardTimeUp => TimeUp,
+                        BoardPlayersChanged(ref white, ref black) => PlayersChanged(white.clone(), black.clone()),
+                    },
+                    gtk::Box {
+                        orientation: gtk::Orientation::Vertical,
+                        spacing: 5,
+                        #[name="clock_label"]
+                        gtk::Label {
+                            halign: gtk::Align::Start,
+                        },
+                        #[name="history"]
+                        History {
+                            HistoryNodeSelected(node) => GoToNode(node),
+                            HistoryPromoteVariation(node) => PromoteVariation(node),
+                            HistoryDeleteVariation(node) => DeleteVariation(node),
+                            HistoryCommentEdited(node, ref comment) => SetComment(node, comment.clone()),
+                            HistoryNagToggled(node, nag) => ToggleNag(node, nag),
+                        },
+                        #[name="analysis"]
+                        AnalysisPanel {
+                            AnalysisEvaluated(evaluation) => Evaluated(evaluation),
+                        },
                     },
-                    #[name="history"]
-                    History {
-                        
-                    }
+                },
+                #[name="lesson_label"]
+                gtk::Label {
+                    line_wrap: true,
+                    halign: gtk::Align::Start,
+                    selectable: true,
                 },
                 orientation: gtk::Orientation::Vertical,
                 spacing: 5,
@@ -61,16 +149,107 @@ impl Widget for MainWindow {
             GameOver(outcome) => self.handle_game_termination(outcome),
             StartGame => self.purpose_start_new_game(),
             StopGame => self.show_stop_confirmation_dialog(),
-            GameStarted => self.model.game_in_progress = true,
+            GameStarted => {
+                self.model.game_in_progress = true;
+                self.model.hints_used = 0;
+                self.model.game_start_time = Instant::now();
+            }
             GameStoppedByUser => self.handle_game_stopped_by_user(),
+            GameResumed => self.model.game_in_progress = true,
             MovePlayed(san, white_player) => self.add_move_played(san, white_player),
+            SelectEngine => self.select_engine(),
+            ShowHint => self.show_hint(),
+            HintUsed => self.model.hints_used += 1,
+            OptimumMovesComputed(moves) => {
+                if let Some(exercise) = self.model.exercise.as_mut() {
+                    exercise.optimum_moves = Some(moves);
+                }
+            }
+            ShowStatistics => show_statistics_dialog(&self.widgets.root, &self.model.statistics),
+            ShowQueue => show_queue_dialog(&self.widgets.root, &self.model.statistics),
+            ImportPack => self.import_pack(),
+            ExportPack => self.export_pack(),
+            EngineError(message) => self.show_error(&message),
+            OpenLesson => self.open_lesson(),
+            LessonNextStep => self.play_next_lesson_step(),
+            LessonMoveAttempted(attempted_move) => self.check_lesson_answer(attempted_move),
+            PositionChanged(moves_chain) => self
+                .components
+                .analysis
+                .emit(analysis::Msg::SetPosition(moves_chain)),
+            OrientationChanged(reversed) => self
+                .components
+                .evaluation_bar
+                .emit(evaluation_bar::Msg::SetReversed(reversed)),
+            HistoryChanged(tree, node) => {
+                self.model.game_tree = Some((*tree).clone());
+                self.components
+                    .history
+                    .emit(history::Msg::SetTree(tree, node));
+            }
+            GoToNode(node) => self.components.board.emit(BoardGoToNode(node)),
+            PromoteVariation(node) => self.components.board.emit(BoardPromoteVariation(node)),
+            DeleteVariation(node) => self.components.board.emit(BoardDeleteVariation(node)),
+            SetComment(node, comment) => self.components.board.emit(BoardSetComment(node, comment)),
+            ToggleNag(node, nag) => self.components.board.emit(BoardToggleNag(node, nag)),
+            ImportGame => self.import_game(),
+            ReviewFinished(summary, player_side) => self.show_review_summary(*summary, player_side),
+            ReviewCancelled => self.widgets.lesson_label.set_text(""),
+            ExportGame => self.export_game(),
+            ClaimDraw => {
+                if self.model.game_in_progress {
+                    self.components.board.emit(BoardClaimDraw);
+                }
+            }
+            OfferDraw => {
+                if self.model.game_in_progress {
+                    self.components.board.emit(BoardOfferDraw);
+                }
+            }
+            Resign => self.show_resign_confirmation_dialog(),
+            DrawOffered => self.answer_draw_offer(),
+            DrawDeclined => self.show_message(&tr!("The computer declines the draw offer.")),
+            DrawNotClaimable => self.show_message(&tr!(
+                "A draw can only be claimed on your turn, after a threefold repetition or 50 moves without capture nor pawn move."
+            )),
+            Evaluated(evaluation) => self
+                .components
+                .evaluation_bar
+                .emit(evaluation_bar::Msg::SetEvaluation(evaluation)),
+            PlayerTimeChanged(seconds) => self
+                .widgets
+                .clock_label
+                .set_text(&seconds.map(format_player_time).unwrap_or_default()),
+            TimeUp => self.model.time_up = true,
+            PlayersChanged(white, black) => {
+                self.model.players = Some((white.clone(), black.clone()));
+                self.components
+                    .history
+                    .emit(history::Msg::SetPlayers(white, black));
+            }
+            ExerciseGenerated(generated) => self.handle_generated_exercise(*generated),
+            CancelGeneration => self.cancel_generation(),
         }
     }
 
-    fn model(relm: &Relm<Self>, _: ()) -> Model {
+    fn model(relm: &Relm<Self>, startup_exercise: Option<Exercise>) -> Model {
+        let mut load_errors = vec![];
         Model {
             relm: relm.clone(),
+            startup_exercise,
             game_in_progress: false,
+            hints_used: 0,
+            time_up: false,
+            game_start_time: Instant::now(),
+            exercise: None,
+            settings: load_or_default(Settings::load(), &mut load_errors),
+            statistics: load_or_default(Statistics::load(), &mut load_errors),
+            packs: packs::load_imported_packs(),
+            lesson: None,
+            game_tree: None,
+            players: None,
+            load_errors,
+            generation: None,
         }
     }
 
@@ -94,6 +273,21 @@ impl Widget for MainWindow {
         let stop_image = gtk::Image::from_pixbuf(Some(&stop_pixbuf));
         let stop_button = ToolButton::new(Some(&stop_image), None);
 
+        let hint_button = get_themed_tool_button("dialog-question", &tr!("Hint"));
+        let engine_button = get_themed_tool_button("preferences-system", &tr!("Select engine"));
+        let statistics_button = get_themed_tool_button("x-office-spreadsheet", &tr!("Statistics"));
+        let queue_button = get_themed_tool_button("x-office-calendar", &tr!("Drills queue"));
+        let import_pack_button = get_themed_tool_button("document-open", &tr!("Import pack"));
+        let export_pack_button = get_themed_tool_button("document-save-as", &tr!("Export pack"));
+        let import_game_button =
+            get_themed_tool_button("document-open-recent", &tr!("Import game"));
+        let export_game_button = get_themed_tool_button("document-save", &tr!("Export game"));
+        let claim_draw_button = get_themed_tool_button("face-plain", &tr!("Claim draw"));
+        let offer_draw_button = get_themed_tool_button("mail-send", &tr!("Offer draw"));
+        let resign_button = get_themed_tool_button("face-sad", &tr!("Resign"));
+        let lesson_button = get_themed_tool_button("accessories-dictionary", &tr!("Open lesson"));
+        let lesson_next_button = get_themed_tool_button("go-next", &tr!("Next lesson step"));
+
         connect!(
             reverse_board_button,
             connect_clicked(_),
@@ -105,39 +299,150 @@ impl Widget for MainWindow {
 
         connect!(stop_button, connect_clicked(_), self.model.relm, StopGame);
 
+        connect!(hint_button, connect_clicked(_), self.model.relm, ShowHint);
+
+        connect!(
+            engine_button,
+            connect_clicked(_),
+            self.model.relm,
+            SelectEngine
+        );
+
+        connect!(
+            statistics_button,
+            connect_clicked(_),
+            self.model.relm,
+            ShowStatistics
+        );
+
+        connect!(queue_button, connect_clicked(_), self.model.relm, ShowQueue);
+
+        connect!(
+            import_pack_button,
+            connect_clicked(_),
+            self.model.relm,
+            ImportPack
+        );
+
+        connect!(
+            export_pack_button,
+            connect_clicked(_),
+            self.model.relm,
+            ExportPack
+        );
+
+        connect!(
+            import_game_button,
+            connect_clicked(_),
+            self.model.relm,
+            ImportGame
+        );
+
+        connect!(
+            export_game_button,
+            connect_clicked(_),
+            self.model.relm,
+            ExportGame
+        );
+
+        connect!(
+            claim_draw_button,
+            connect_clicked(_),
+            self.model.relm,
+            ClaimDraw
+        );
+
+        connect!(
+            offer_draw_button,
+            connect_clicked(_),
+            self.model.relm,
+            OfferDraw
+        );
+
+        connect!(resign_button, connect_clicked(_), self.model.relm, Resign);
+
+        connect!(
+            lesson_button,
+            connect_clicked(_),
+            self.model.relm,
+            OpenLesson
+        );
+
+        connect!(
+            lesson_next_button,
+            connect_clicked(_),
+            self.model.relm,
+            LessonNextStep
+        );
+
         self.widgets.toolbar.insert(&reverse_board_button, -1);
         self.widgets.toolbar.insert(&start_button, -1);
         self.widgets.toolbar.insert(&stop_button, -1);
+        self.widgets.toolbar.insert(&hint_button, -1);
+        self.widgets.toolbar.insert(&engine_button, -1);
+        self.widgets.toolbar.insert(&statistics_button, -1);
+        self.widgets.toolbar.insert(&queue_button, -1);
+        self.widgets.toolbar.insert(&import_pack_button, -1);
+        self.widgets.toolbar.insert(&export_pack_button, -1);
+        self.widgets.toolbar.insert(&import_game_button, -1);
+        self.widgets.toolbar.insert(&export_game_button, -1);
+        self.widgets.toolbar.insert(&claim_draw_button, -1);
+        self.widgets.toolbar.insert(&offer_draw_button, -1);
+        self.widgets.toolbar.insert(&resign_button, -1);
+        self.widgets.toolbar.insert(&lesson_button, -1);
+        self.widgets.toolbar.insert(&lesson_next_button, -1);
+
+        self.components
+            .board
+            .emit(BoardSetPlayerTime(self.model.settings.player_time_secs));
+        if let Some(engine_path) = self.model.settings.engine_path.clone() {
+            self.components
+                .analysis
+                .emit(analysis::Msg::SetEngine(engine_path.clone()));
+            self.components.board.emit(BoardSetEngine(engine_path));
+        }
 
         self.widgets.root.show_all();
+
+        for error in std::mem::take(&mut self.model.load_errors) {
+            self.show_error(&error);
+        }
+
+        if let Some(exercise) = self.model.startup_exercise.take() {
+            if self.model.settings.engine_path.is_none() {
+                self.show_error(&tr!("No engine has been selected."));
+            } else {
+                self.start_exercise(exercise);
+            }
+        }
     }
 }
 
 impl MainWindow {
     fn handle_game_termination(&mut self, outcome: Outcome) {
-        let message = match outcome {
-            Outcome::Draw(draw_type) => match draw_type {
-                DrawReason::InsufficientMaterial => tr!("Draw by missing material."),
-                DrawReason::Stalemate => tr!("Draw by stalemate."),
-                DrawReason::Moves50 => tr!("Draw by the 50 moves rule."),
-                DrawReason::Moves75 => tr!("Draw by the 75 moves rule."),
-                DrawReason::Repeat3 => tr!("Draw by three fold repetition."),
-                DrawReason::Repeat5 => tr!("Draw by five fold repetition."),
-                _ => tr!("Draw by unknown reason."),
-            },
-            Outcome::Win { side, reason } => {
-                let side_text = if side == Color::White {
-                    tr!("White")
-                } else {
-                    tr!("Black")
-                };
-                match reason {
-                    WinReason::Checkmate => {
-                        tr!("{} won by checkmate.", side_text)
-                    }
-                    _ => tr!("{} won by unknown reason.", side_text),
-                }
-            }
+        self.components.history.emit(history::Msg::SetGameDuration(
+            self.model.game_start_time.elapsed().as_secs(),
+        ));
+        // A draw by missing material after a time forfeit would otherwise read as a usual one.
+        let message = if std::mem::take(&mut self.model.time_up) {
+            format!("{}\n{}", tr!("Your time is up."), describe_outcome(outcome))
+        } else {
+            describe_outcome(outcome)
+        };
+        let success = match self.model.exercise.as_ref() {
+            Some(exercise) => exercise.goal.is_reached(&outcome, exercise.player_side),
+            None => false,
+        };
+        self.record_exercise_result(success);
+
+        let message = if self.model.hints_used > 0 {
+            format!(
+                "{}\n{}",
+                message,
+                tr!("Hints used : {}", self.model.hints_used)
+            )
+        } else {
+            message
         };
         let dialog = MessageDialog::new(
             Some(&self.widgets.root),
@@ -146,9 +451,84 @@ impl MainWindow {
             ButtonsType::Ok,
             &message,
         );
-        dialog.run();
+        if self.model.settings.engine_path.is_some() {
+            dialog.add_button(&tr!("Review"), ResponseType::Apply);
+        }
+        let response = dialog.run();
         dialog.emit_close();
         self.model.game_in_progress = false;
+
+        if response == ResponseType::Apply {
+            self.widgets
+                .lesson_label
+                .set_text(&tr!("Reviewing the game..."));
+            self.components.board.emit(BoardStartReview);
+        }
+    }
+
+    /// Gives the accuracies, then lets the player retry from one of their critical
+    /// moves : the moves changing the result are proposed first.
+    fn show_review_summary(&mut self, summary: ReviewSummary, player_side: Color) {
+        self.widgets.lesson_label.set_text("");
+        let accuracies = format_accuracies(&summary, player_side);
+        let game_tree = match self.model.game_tree.as_ref() {
+            Some(game_tree) => game_tree,
+            None => return,
+        };
+        let player_moves: Vec<&CriticalMove> = summary
+            .critical_moves
+            .iter()
+            .filter(|critical_move| {
+                game_tree.is_white_move(critical_move.node) == (player_side == Color::White)
+            })
+            .collect();
+        if player_moves.is_empty() {
+            self.show_message(&format!(
+                "{}\n{}",
+                accuracies,
+                tr!("You made no critical move.")
+            ));
+            return;
+        }
+
+        let choices: Vec<String> = player_moves
+            .iter()
+            .map(|critical_move| {
+                let text = format!(
+                    "{} : {}",
+                    game_tree.numbered_san(critical_move.node),
+                    critical_move.quality.title()
+                );
+                if critical_move.changes_result {
+                    tr!("{} (changes the result)", text)
+                } else {
+                    text
+                }
+            })
+            .collect();
+        let default_index = player_moves
+            .iter()
+            .position(|critical_move| critical_move.changes_result)
+            .unwrap_or(0);
+        let question = format!(
+            "{}\n{}",
+            accuracies,
+            tr!("Retry from the position before the move :")
+        );
+        let choice_index = match ask_choice(
+            &self.widgets.root,
+            &tr!("Review"),
+            &question,
+            &tr!("Retry from here"),
+            &choices,
+            default_index,
+        ) {
+            Some(index) => index,
+            None => return,
+        };
+        self.components
+            .board
+            .emit(BoardRetryFrom(player_moves[choice_index].node));
     }
 
     fn show_stop_confirmation_dialog(&self) {
@@ -171,8 +551,46 @@ impl MainWindow {
         }
     }
 
+    fn show_resign_confirmation_dialog(&self) {
+        if !self.model.game_in_progress {
+            return;
+        }
+        let message = tr!("Do you want to resign ?");
+        let dialog = MessageDialog::new(
+            Some(&self.widgets.root),
+            DialogFlags::MODAL,
+            MessageType::Question,
+            ButtonsType::YesNo,
+            &message,
+        );
+        let response = dialog.run();
+        dialog.emit_close();
+
+        if response == ResponseType::Yes {
+            self.components.board.emit(BoardResign);
+        }
+    }
+
+    fn answer_draw_offer(&self) {
+        let message = tr!("The computer offers a draw. Do you accept it ?");
+        let dialog = MessageDialog::new(
+            Some(&self.widgets.root),
+            DialogFlags::MODAL,
+            MessageType::Question,
+            ButtonsType::YesNo,
+            &message,
+        );
+        let response = dialog.run();
+        dialog.emit_close();
+
+        if response == ResponseType::Yes {
+            self.components.board.emit(BoardAcceptDraw);
+        }
+    }
+
     fn handle_game_stopped_by_user(&mut self) {
         self.model.game_in_progress = false;
+        self.record_exercise_result(false);
 
         let message = tr!("Game interrupted.");
         let dialog = MessageDialog::new(
@@ -186,7 +604,7 @@ impl MainWindow {
         dialog.emit_close();
     }
 
-    fn purpose_start_new_game(&self) {
+    fn purpose_start_new_game(&mut self) {
         if self.model.game_in_progress {
             let message = tr!("Do you want to quit current game and start a new one ?");
             let dialog = MessageDialog::new(
@@ -207,13 +625,697 @@ impl MainWindow {
         }
     }
 
-    fn start_new_game(&self) {
+    fn start_new_game(&mut self) {
+        if self.model.settings.engine_path.is_none() {
+            self.show_error(&tr!("No engine has been selected."));
+            return;
+        }
+
+        let mut choices: Vec<String> = EndgameKind::ALL.iter().map(|kind| kind.title()).collect();
+        let mut packs_exercises = vec![];
+        for (pack_index, pack) in self.model.packs.iter().enumerate() {
+            for (exercise_index, exercise) in pack.exercises.iter().enumerate() {
+                choices.push(format!("{} : {}", pack.name, exercise.title));
+                packs_exercises.push((pack_index, exercise_index));
+            }
+        }
+
+        let next_drill = next_due_drill(self.model.statistics.results());
+        let default_index = EndgameKind::ALL
+            .iter()
+            .position(|kind| *kind == next_drill)
+            .unwrap_or(0);
+        let mut difficulties = vec![tr!("Any")];
+        difficulties.extend(Difficulty::ALL.iter().map(|difficulty| difficulty.title()));
+        let filters = get_proposed_filters(self.model.settings.generation_filter);
+        let filters_titles: Vec<String> = filters
+            .iter()
+            .map(|filter| match filter {
+                Some(filter) => filter.title(),
+                None => tr!("None"),
+            })
+            .collect();
+        let skill_levels =
+            get_proposed_skill_levels(self.model.settings.computer_strength.skill_level);
+        let skill_levels_titles: Vec<String> = skill_levels
+            .iter()
+            .map(|skill_level| match skill_level {
+                Some(skill_level) => tr!("Skill level {}", skill_level),
+                None => tr!("Full strength"),
+            })
+            .collect();
+        let player_times = get_proposed_player_times(self.model.settings.player_time_secs);
+        let player_times_titles: Vec<String> = player_times
+            .iter()
+            .map(|player_time| match player_time {
+                Some(secs) if secs % 60 == 0 => {
+                    tr!("One minute" | "{n} minutes" % secs / 60)
+                }
+                Some(secs) => tr!("One second" | "{n} seconds" % *secs),
+                None => tr!("Unlimited"),
+            })
+            .collect();
+        let lists = [
+            ChoiceList {
+                question: &tr!("Which endgame do you want to practice ?"),
+                choices: &choices,
+                default_index,
+            },
+            ChoiceList {
+                question: &tr!("Which difficulty (for the built-in endgames) ?"),
+                choices: &difficulties,
+                default_index: 0,
+            },
+            ChoiceList {
+                question: &tr!("Which generation filter (for the built-in endgames) ?"),
+                choices: &filters_titles,
+                default_index: filters
+                    .iter()
+                    .position(|filter| *filter == self.model.settings.generation_filter)
+                    .unwrap_or(0),
+            },
+            ChoiceList {
+                question: &tr!("Which strength for the computer (unless the exercise gives one) ?"),
+                choices: &skill_levels_titles,
+                default_index: skill_levels
+                    .iter()
+                    .position(|skill_level| {
+                        *skill_level == self.model.settings.computer_strength.skill_level
+                    })
+                    .unwrap_or(0),
+            },
+            ChoiceList {
+                question: &tr!("Which thinking time for the player ?"),
+                choices: &player_times_titles,
+                default_index: player_times
+                    .iter()
+                    .position(|player_time| *player_time == self.model.settings.player_time_secs)
+                    .unwrap_or(0),
+            },
+        ];
+        let (choice_index, difficulty_index, filter_index, skill_level_index, player_time_index) =
+            match ask_choices(&self.widgets.root, &tr!("New game"), &tr!("Start"), &lists) {
+                Some(indexes) => (indexes[0], indexes[1], indexes[2], indexes[3], indexes[4]),
+                None => return,
+            };
+        let player_time_changed =
+            player_times[player_time_index] != self.model.settings.player_time_secs;
+        let settings = &mut self.model.settings;
+        let settings_changed = filters[filter_index] != settings.generation_filter
+            || skill_levels[skill_level_index] != settings.computer_strength.skill_level
+            || player_time_changed;
+        settings.generation_filter = filters[filter_index];
+        settings.computer_strength.skill_level = skill_levels[skill_level_index];
+        settings.player_time_secs = player_times[player_time_index];
+        if settings_changed {
+            self.save_settings();
+        }
+        if player_time_changed {
+            self.components
+                .board
+                .emit(BoardSetPlayerTime(self.model.settings.player_time_secs));
+        }
+        let difficulty = difficulty_index
+            .checked_sub(1)
+            .map(|index| Difficulty::ALL[index]);
+
+        let exercise = if choice_index < EndgameKind::ALL.len() {
+            let kind = EndgameKind::ALL[choice_index];
+            let filter = self.model.settings.generation_filter;
+            if difficulty.is_some() || filter.is_some() {
+                self.start_generation(kind, difficulty, filter);
+                return;
+            }
+            generate_exercise(kind)
+        } else {
+            let (pack_index, exercise_index) =
+                packs_exercises[choice_index - EndgameKind::ALL.len()];
+            self.model.packs[pack_index].exercises[exercise_index].to_exercise()
+        };
+        let exercise = match exercise {
+            Ok(exercise) => exercise,
+            Err(err) => {
+                self.show_error(&format!("{:#}", err));
+                return;
+            }
+        };
+
+        self.start_exercise(exercise);
+    }
+
+    /// Generates the exercise on its own thread, as the engine rates many positions,
+    /// while a dialog lets the player cancel it.
+    fn start_generation(
+        &mut self,
+        kind: EndgameKind,
+        difficulty: Option<Difficulty>,
+        filter: Option<GenerationFilter>,
+    ) {
+        let engine_path = match self.model.settings.engine_path.clone() {
+            Some(engine_path) => engine_path,
+            None => return,
+        };
+        let stream = self.model.relm.stream().clone();
+        let (channel, sender) = relm::Channel::new(move |generated| {
+            stream.emit(ExerciseGenerated(Box::new(generated)));
+        });
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let generated = generate_endgame_exercise(
+                kind,
+                difficulty,
+                filter,
+                &engine_path,
+                &thread_cancelled,
+            )
+            .map_err(|err| format!("{:#}", err));
+            let _ = sender.send(generated);
+        });
+
+        let dialog = MessageDialog::new(
+            Some(&self.widgets.root),
+            DialogFlags::MODAL,
+            MessageType::Info,
+            ButtonsType::Cancel,
+            &tr!("Generating the position..."),
+        );
+        let spinner = gtk::Spinner::new();
+        spinner.start();
+        dialog.content_area().add(&spinner);
+        connect!(
+            dialog,
+            connect_response(_, _),
+            self.model.relm,
+            CancelGeneration
+        );
+        dialog.show_all();
+
+        self.model.generation = Some(Generation {
+            _channel: channel,
+            cancelled,
+            dialog,
+        });
+    }
+
+    fn handle_generated_exercise(&mut self, generated: Result<Exercise, String>) {
+        let generation = match self.model.generation.take() {
+            Some(generation) => generation,
+            None => return,
+        };
+        generation.dialog.emit_close();
+        match generated {
+            Ok(exercise) => self.start_exercise(exercise),
+            Err(message) => self.show_error(&message),
+        }
+    }
+
+    /// The generation thread stops before its next attempt, its answer being dropped.
+    fn cancel_generation(&mut self) {
+        if let Some(generation) = self.model.generation.take() {
+            generation.cancelled.store(true, Ordering::SeqCst);
+            generation.dialog.emit_close();
+        }
+    }
+
+    fn start_exercise(&mut self, mut exercise: Exercise) {
+        if self.model.game_in_progress {
+            self.record_exercise_result(false);
+        }
+
+        self.model.exercise = Some(ExerciseProgress {
+            kind: exercise.kind,
+            player_side: exercise.player_side,
+            goal: exercise.goal,
+            text_hints: exercise.hints.iter().cloned().collect(),
+            start_time: Instant::now(),
+            player_moves: 0,
+            optimum_moves: None,
+            best_moves: exercise.best_moves.clone(),
+            avoid_moves: exercise.avoid_moves.clone(),
+            difficulty_score: exercise.difficulty_score,
+        });
+        self.model.lesson = None;
+        self.widgets.lesson_label.set_text("");
+
         self.components.history.emit(history::Msg::NewGame());
-        self.components.board.emit(BoardStartGame);
+        exercise
+            .computer_strength
+            .get_or_insert(self.model.settings.computer_strength);
+        self.components
+            .board
+            .emit(BoardStartGame(Box::new(exercise)));
+    }
+
+    fn record_exercise_result(&mut self, success: bool) {
+        let exercise = match self.model.exercise.take() {
+            Some(exercise) => exercise,
+            None => return,
+        };
+        let kind = match exercise.kind {
+            Some(kind) => kind,
+            None => return,
+        };
+
+        self.model.statistics.add_result(ExerciseResult {
+            kind,
+            date: current_timestamp(),
+            success,
+            moves_played: exercise.player_moves,
+            optimum_moves: exercise.optimum_moves,
+            duration_secs: exercise.start_time.elapsed().as_secs(),
+            hints_used: self.model.hints_used,
+            difficulty_score: exercise.difficulty_score,
+        });
+
+        if let Err(err) = self.model.statistics.save() {
+            self.show_error(&format!("{:#}", err));
+        }
     }
 
     fn add_move_played(&mut self, move_played: String, white_player: bool) {
-        self.components.history.emit(history::Msg::AddMoveSan(move_played, white_player));
+        let mut first_move_feedback = None;
+        if let Some(exercise) = self.model.exercise.as_mut() {
+            if white_player == (exercise.player_side == Color::White) {
+                exercise.player_moves += 1;
+                if exercise.player_moves == 1 {
+                    first_move_feedback = get_first_move_feedback(exercise, &move_played);
+                }
+            }
+        }
+        if let Some(feedback) = first_move_feedback {
+            self.widgets.lesson_label.set_text(&feedback);
+        }
+    }
+
+    fn show_hint(&mut self) {
+        if !self.model.game_in_progress {
+            return;
+        }
+
+        let text_hint = self
+            .model
+            .exercise
+            .as_mut()
+            .and_then(|exercise| exercise.text_hints.pop_front());
+        if let Some(text_hint) = text_hint {
+            self.model.hints_used += 1;
+            self.show_message(&text_hint);
+            return;
+        }
+
+        if self.model.settings.engine_path.is_none() {
+            self.show_error(&tr!("No engine has been selected."));
+            return;
+        }
+        self.components.board.emit(BoardShowHint);
+    }
+
+    fn import_pack(&mut self) {
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Import pack")),
+            Some(&self.widgets.root),
+            FileChooserAction::Open,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Open"), ResponseType::Accept),
+            ],
+        );
+        dialog.add_filter(&get_pack_file_filter());
+        dialog.add_filter(&get_epd_file_filter());
+        let response = dialog.run();
+        let pack_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        if let Some(pack_path) = pack_path {
+            match packs::import_pack(&pack_path) {
+                Ok(pack) => {
+                    self.show_message(&tr!("Pack {} imported.", pack.name));
+                    self.model
+                        .packs
+                        .retain(|imported_pack| imported_pack.name != pack.name);
+                    self.model.packs.push(pack);
+                }
+                Err(errors) => self.show_error(&format!(
+                    "{}\n{}",
+                    tr!("Failed to import the pack :"),
+                    errors.join("\n")
+                )),
+            }
+        }
+    }
+
+    fn export_pack(&self) {
+        let mut choices = vec![tr!("Basic endgames")];
+        choices.extend(self.model.packs.iter().map(|pack| pack.name.clone()));
+        let choice_index = match ask_choice(
+            &self.widgets.root,
+            &tr!("Export pack"),
+            &tr!("Which pack do you want to export ?"),
+            &tr!("Export"),
+            &choices,
+            0,
+        ) {
+            Some(index) => index,
+            None => return,
+        };
+        let pack = if choice_index == 0 {
+            ExercisePack::builtin()
+        } else {
+            self.model.packs[choice_index - 1].clone()
+        };
+
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Export pack")),
+            Some(&self.widgets.root),
+            FileChooserAction::Save,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Save"), ResponseType::Accept),
+            ],
+        );
+        dialog.add_filter(&get_pack_file_filter());
+        dialog.add_filter(&get_epd_file_filter());
+        dialog.set_current_name(&format!("{}.json", pack.name));
+        dialog.set_do_overwrite_confirmation(true);
+        let response = dialog.run();
+        let pack_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        if let Some(pack_path) = pack_path {
+            if let Err(err) = packs::export_pack(&pack, &pack_path) {
+                self.show_error(&format!("{:#}", err));
+            }
+        }
+    }
+
+    /// The imported game goes on against the computer, the player having the side to move.
+    fn import_game(&mut self) {
+        if self.model.game_in_progress {
+            let message = tr!("Do you want to quit current game and import another one ?");
+            let dialog = MessageDialog::new(
+                Some(&self.widgets.root),
+                DialogFlags::MODAL,
+                MessageType::Question,
+                ButtonsType::YesNo,
+                &message,
+            );
+            let response = dialog.run();
+            dialog.emit_close();
+
+            if response != ResponseType::Yes {
+                return;
+            }
+        }
+
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Import game")),
+            Some(&self.widgets.root),
+            FileChooserAction::Open,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Open"), ResponseType::Accept),
+            ],
+        );
+        dialog.add_filter(&get_pgn_file_filter());
+        let response = dialog.run();
+        let game_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        let game_tree = match game_path.map(|path| tree::read_game(&path)) {
+            Some(Ok(game_tree)) => game_tree,
+            Some(Err(err)) => {
+                self.show_error(&format!("{:#}", err));
+                return;
+            }
+            None => return,
+        };
+
+        if self.model.game_in_progress {
+            self.record_exercise_result(false);
+        }
+        self.model.exercise = None;
+        self.model.lesson = None;
+        self.widgets.lesson_label.set_text("");
+
+        self.components.history.emit(history::Msg::NewGame());
+        self.components
+            .board
+            .emit(BoardLoadGame(Box::new(game_tree)));
+    }
+
+    fn export_game(&self) {
+        let game_tree = match self.model.game_tree.as_ref() {
+            Some(game_tree) => game_tree,
+            None => {
+                self.show_error(&tr!("There is no game to export."));
+                return;
+            }
+        };
+
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Export game")),
+            Some(&self.widgets.root),
+            FileChooserAction::Save,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Save"), ResponseType::Accept),
+            ],
+        );
+        dialog.add_filter(&get_pgn_file_filter());
+        dialog.set_current_name("game.pgn");
+        dialog.set_do_overwrite_confirmation(true);
+        let response = dialog.run();
+        let game_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        if let Some(game_path) = game_path {
+            if let Err(err) = tree::write_game(
+                game_tree,
+                get_game_headers(game_tree, self.model.players.as_ref()),
+                &game_path,
+            ) {
+                self.show_error(&format!("{:#}", err));
+            }
+        }
+    }
+
+    fn open_lesson(&mut self) {
+        if self.model.game_in_progress {
+            let message = tr!("Do you want to quit current game and start a lesson ?");
+            let dialog = MessageDialog::new(
+                Some(&self.widgets.root),
+                DialogFlags::MODAL,
+                MessageType::Question,
+                ButtonsType::YesNo,
+                &message,
+            );
+            let response = dialog.run();
+            dialog.emit_close();
+
+            if response != ResponseType::Yes {
+                return;
+            }
+        }
+
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Open lesson")),
+            Some(&self.widgets.root),
+            FileChooserAction::Open,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Open"), ResponseType::Accept),
+            ],
+        );
+        let filter = FileFilter::new();
+        filter.set_name(Some(&tr!("Lessons")));
+        filter.add_pattern("*.pgn");
+        dialog.add_filter(&filter);
+        let response = dialog.run();
+        let lesson_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        let lesson = match lesson_path.map(|path| lessons::read_lesson(&path)) {
+            Some(Ok(lesson)) => lesson,
+            Some(Err(err)) => {
+                self.show_error(&format!("{:#}", err));
+                return;
+            }
+            None => return,
+        };
+
+        if self.model.game_in_progress {
+            self.record_exercise_result(false);
+            self.model.game_in_progress = false;
+        }
+
+        let mut text_parts: Vec<String> = vec![];
+        if !lesson.title.is_empty() {
+            text_parts.push(lesson.title.clone());
+        }
+        text_parts.extend(lesson.introduction.clone());
+        let start_position = lesson.start_position.clone();
+        let player = LessonPlayer::new(lesson);
+        text_parts.push(get_lesson_prompt(&player));
+        self.model.lesson = Some(player);
+        self.set_lesson_text(&text_parts);
+
+        self.components.history.emit(history::Msg::NewGame());
+        self.components
+            .board
+            .emit(BoardStartLesson(Box::new(start_position)));
+    }
+
+    fn play_next_lesson_step(&mut self) {
+        let waiting_answer = match self.model.lesson.as_ref() {
+            Some(player) => player.is_waiting_answer(),
+            None => return,
+        };
+
+        if waiting_answer {
+            self.set_lesson_text(&[tr!("Find the move played in the lesson.")]);
+        } else {
+            self.play_lesson_step(None);
+        }
+    }
+
+    fn check_lesson_answer(&mut self, attempted_move: Move) {
+        let player = match self.model.lesson.as_ref() {
+            Some(player) => player,
+            None => return,
+        };
+
+        if !player.is_waiting_answer() {
+            self.set_lesson_text(&[get_lesson_prompt(player)]);
+            self.components.board.emit(BoardRepaint);
+            return;
+        }
+
+        let expected_san = player
+            .next_step()
+            .map(|step| step.san.clone())
+            .unwrap_or_default();
+        match player.check_answer(attempted_move) {
+            Answer::Expected => self.play_lesson_step(Some(tr!("Correct !"))),
+            Answer::Alternative => self.play_lesson_step(Some(tr!(
+                "Good move too, but the lesson continues with {}.",
+                expected_san
+            ))),
+            Answer::Wrong => {
+                self.set_lesson_text(&[tr!("This is not the expected move, try again.")]);
+                self.components.board.emit(BoardRepaint);
+            }
+        }
+    }
+
+    /// Plays the next move of the lesson, then shows its explanation.
+    fn play_lesson_step(&mut self, feedback: Option<String>) {
+        let player = match self.model.lesson.as_mut() {
+            Some(player) => player,
+            None => return,
+        };
+        let (expected_move, comment) = match player.advance() {
+            Some(step) => (step.expected_move, step.comment.clone()),
+            None => return,
+        };
+
+        let mut text_parts: Vec<String> = feedback.into_iter().collect();
+        text_parts.extend(comment);
+        let finished = player.is_finished();
+        if finished {
+            text_parts.push(tr!("End of the lesson."));
+        } else {
+            text_parts.push(get_lesson_prompt(player));
+        }
+        self.set_lesson_text(&text_parts);
+
+        self.components.board.emit(BoardPlayMove(expected_move));
+        if finished {
+            self.model.lesson = None;
+            self.components.board.emit(BoardEndLesson);
+        }
+    }
+
+    fn set_lesson_text(&self, text_parts: &[String]) {
+        self.widgets.lesson_label.set_text(&text_parts.join("\n"));
+    }
+
+    fn select_engine(&mut self) {
+        let dialog = FileChooserDialog::with_buttons(
+            Some(&tr!("Select engine")),
+            Some(&self.widgets.root),
+            FileChooserAction::Open,
+            &[
+                (&tr!("Cancel"), ResponseType::Cancel),
+                (&tr!("Open"), ResponseType::Accept),
+            ],
+        );
+        let response = dialog.run();
+        let engine_path: Option<PathBuf> = dialog.filename();
+        dialog.emit_close();
+
+        if response != ResponseType::Accept {
+            return;
+        }
+
+        if let Some(engine_path) = engine_path {
+            self.model.settings.engine_path = Some(engine_path.clone());
+            self.save_settings();
+            self.components
+                .analysis
+                .emit(analysis::Msg::SetEngine(engine_path.clone()));
+            self.components.board.emit(BoardSetEngine(engine_path));
+        }
+    }
+
+    fn save_settings(&self) {
+        if let Err(err) = self.model.settings.save() {
+            self.show_error(&format!("{:#}", err));
+        }
+    }
+
+    fn show_message(&self, message: &str) {
+        let dialog = MessageDialog::new(
+            Some(&self.widgets.root),
+            DialogFlags::MODAL,
+            MessageType::Info,
+            ButtonsType::Ok,
+            message,
+        );
+        dialog.run();
+        dialog.emit_close();
+    }
+
+    fn show_error(&self, message: &str) {
+        let dialog = MessageDialog::new(
+            Some(&self.widgets.root),
+            DialogFlags::MODAL,
+            MessageType::Error,
+            ButtonsType::Ok,
+            message,
+        );
+        dialog.run();
+        dialog.emit_close();
     }
 }
 
@@ -225,12 +1327,90 @@ pub enum Msg {
     StopGame,
     GameStarted,
     GameStoppedByUser,
+    GameResumed,
     MovePlayed(String, bool),
+    SelectEngine,
+    ShowHint,
+    HintUsed,
+    OptimumMovesComputed(u32),
+    ShowStatistics,
+    ShowQueue,
+    ImportPack,
+    ExportPack,
+    EngineError(String),
+    OpenLesson,
+    LessonNextStep,
+    LessonMoveAttempted(Move),
+    PositionChanged(Box<MoveChain>),
+    HistoryChanged(Box<GameTree>, NodeId),
+    GoToNode(NodeId),
+    PromoteVariation(NodeId),
+    DeleteVariation(NodeId),
+    SetComment(NodeId, Option<String>),
+    ToggleNag(NodeId, u8),
+    ImportGame,
+    ExportGame,
+    ReviewFinished(Box<ReviewSummary>, Color),
+    ReviewCancelled,
+    ClaimDraw,
+    OfferDraw,
+    Resign,
+    DrawOffered,
+    DrawDeclined,
+    DrawNotClaimable,
+    OrientationChanged(bool),
+    Evaluated(Option<Evaluation>),
+    PlayerTimeChanged(Option<u64>),
+    TimeUp,
+    PlayersChanged(String, String),
+    ExerciseGenerated(Box<Result<Exercise, String>>),
+    CancelGeneration,
+}
+
+struct ExerciseProgress {
+    kind: Option<EndgameKind>,
+    player_side: Color,
+    goal: Goal,
+    text_hints: VecDeque<String>,
+    start_time: Instant,
+    player_moves: u32,
+    optimum_moves: Option<u32>,
+    best_moves: Vec<String>,
+    avoid_moves: Vec<String>,
+    difficulty_score: Option<u32>,
 }
 
 pub struct Model {
     relm: Relm<MainWindow>,
     game_in_progress: bool,
+    hints_used: u32,
+    /// The game is over because the player ran out of time.
+    time_up: bool,
+    /// When the current game started, not reset when a finished game is resumed.
+    game_start_time: Instant,
+    exercise: Option<ExerciseProgress>,
+    /// Exercise given on the command line, started once the window is shown.
+    startup_exercise: Option<Exercise>,
+    settings: Settings,
+    statistics: Statistics,
+    packs: Vec<ExercisePack>,
+    lesson: Option<LessonPlayer>,
+    /// The moves of the board, with their variations and annotations.
+    game_tree: Option<GameTree>,
+    /// The names of the white and black sides, as shown above the history.
+    players: Option<(String, String)>,
+    /// The files which could not be read at startup, reported once the window is shown.
+    load_errors: Vec<String>,
+    /// The exercise being generated by the engine, if any.
+    generation: Option<Generation>,
+}
+
+/// An exercise generation running on its own thread.
+struct Generation {
+    /// Gives the generated exercise to the GTK loop, as an `ExerciseGenerated` message.
+    _channel: relm::Channel<Result<Exercise, String>>,
+    cancelled: Arc<AtomicBool>,
+    dialog: MessageDialog,
 }
 
 use self::Msg::*;
@@ -252,3 +1432,160 @@ fn get_image_pixbuf_from(data: &[u8], size: i32) -> anyhow::Result<Pixbuf> {
 
     Ok(pixbuf)
 }
+
+/// Random position of the endgame, kept only if it passes the generation filter and,
+/// when given, has the difficulty asked.
+fn generate_endgame_exercise(
+    kind: EndgameKind,
+    difficulty: Option<Difficulty>,
+    filter: Option<GenerationFilter>,
+    engine_path: &Path,
+    cancelled: &AtomicBool,
+) -> anyhow::Result<Exercise> {
+    let mut engine = UciEngine::new(engine_path)?;
+    let filter = filter.unwrap_or_default();
+    match difficulty {
+        Some(difficulty) => {
+            generate_rated_exercise(kind, difficulty, &filter, &mut engine, cancelled)
+        }
+        None => generate_filtered_exercise(kind, &filter, &mut engine, cancelled),
+    }
+}
+
+/// No filter, then the mate bands, the filter of the settings being added if it is not
+/// one of them. A single winning move stays asked if the settings ask for it.
+fn get_proposed_filters(
+    settings_filter: Option<GenerationFilter>,
+) -> Vec<Option<GenerationFilter>> {
+    let unique_winning_move = settings_filter.is_some_and(|filter| filter.unique_winning_move);
+    let mut filters = vec![None];
+    filters.extend(
+        GenerationFilter::MATE_BANDS
+            .iter()
+            .map(|(min_mate_moves, max_mate_moves)| {
+                Some(GenerationFilter {
+                    min_mate_moves: *min_mate_moves,
+                    max_mate_moves: *max_mate_moves,
+                    unique_winning_move,
+                })
+            }),
+    );
+    if !filters.contains(&settings_filter) {
+        filters.push(settings_filter);
+    }
+    filters
+}
+
+/// The full strength, then some skill levels, the one of the settings being added
+/// if it is not one of them.
+fn get_proposed_skill_levels(settings_skill_level: Option<u8>) -> Vec<Option<u8>> {
+    let mut skill_levels = vec![None, Some(15), Some(10), Some(5), Some(0)];
+    if !skill_levels.contains(&settings_skill_level) {
+        skill_levels.push(settings_skill_level);
+    }
+    skill_levels
+}
+
+/// Unlimited, then some thinking times in seconds, the one of the settings being added
+/// if it is not one of them.
+fn get_proposed_player_times(settings_player_time: Option<u64>) -> Vec<Option<u64>> {
+    let mut player_times = vec![None, Some(60), Some(180), Some(300), Some(600), Some(900)];
+    if !player_times.contains(&settings_player_time) {
+        player_times.push(settings_player_time);
+    }
+    player_times
+}
+
+/// The default value when the file could not be read, its error being kept to be shown.
+fn load_or_default<T: Default>(loaded: anyhow::Result<T>, errors: &mut Vec<String>) -> T {
+    loaded.unwrap_or_else(|err| {
+        errors.push(format!("{:#}", err));
+        T::default()
+    })
+}
+
+fn get_themed_tool_button(icon_name: &str, tooltip: &str) -> ToolButton {
+    let image = gtk::Image::from_icon_name(Some(icon_name), IconSize::LargeToolbar);
+    let button = ToolButton::new(Some(&image), None);
+    button.set_tooltip_text(Some(tooltip));
+    button
+}
+
+fn get_lesson_prompt(player: &LessonPlayer) -> String {
+    if player.is_waiting_answer() {
+        tr!("Your turn : find the best move.")
+    } else {
+        tr!("Press next to continue the lesson.")
+    }
+}
+
+fn get_pack_file_filter() -> FileFilter {
+    let filter = FileFilter::new();
+    filter.set_name(Some(&tr!("Exercises packs")));
+    filter.add_pattern("*.json");
+    filter
+}
+
+fn get_pgn_file_filter() -> FileFilter {
+    let filter = FileFilter::new();
+    filter.set_name(Some(&tr!("PGN games")));
+    filter.add_pattern("*.pgn");
+    filter
+}
+
+/// The seven tags roster of the PGN standard, the sides being named as above the history
+/// and the unknown values being "?", then how the game ended.
+fn get_game_headers(
+    game_tree: &GameTree,
+    players: Option<&(String, String)>,
+) -> Vec<(String, String)> {
+    let date = gtk::glib::DateTime::now_local()
+        .ok()
+        .and_then(|now| now.format("%Y.%m.%d").ok())
+        .map(|date| date.to_string())
+        .unwrap_or_else(|| "????.??.??".to_string());
+    let (white, black) = players
+        .cloned()
+        .unwrap_or_else(|| ("?".to_string(), "?".to_string()));
+    [
+        ("Event", "Basic chess endgames".to_string()),
+        ("Site", "?".to_string()),
+        ("Date", date),
+        ("Round", "-".to_string()),
+        ("White", white),
+        ("Black", black),
+        ("Result", game_tree.pgn_result()),
+        ("Termination", game_tree.pgn_termination().to_string()),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Such as "Time left : 2:05".
+fn format_player_time(seconds: u64) -> String {
+    tr!(
+        "Time left : {}",
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    )
+}
+
+fn get_epd_file_filter() -> FileFilter {
+    let filter = FileFilter::new();
+    filter.set_name(Some(&tr!("EPD suites")));
+    filter.add_pattern("*.epd");
+    filter
+}
+
+/// Checks the first move of the player against the moves of the exercise, if any.
+fn get_first_move_feedback(exercise: &ExerciseProgress, move_played: &str) -> Option<String> {
+    if exercise.avoid_moves.iter().any(|san| san == move_played) {
+        Some(tr!("{} should be avoided.", move_played))
+    } else if exercise.best_moves.iter().any(|san| san == move_played) {
+        Some(tr!("{} is the best move.", move_played))
+    } else if !exercise.best_moves.is_empty() {
+        Some(tr!("The best move was {}.", exercise.best_moves.join(", ")))
+    } else {
+        None
+    }
+}
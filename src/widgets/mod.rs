@@ -1,3 +1,9 @@
+pub mod analysis;
 pub mod chessboard;
+pub mod evaluation_bar;
 pub mod mainwindow;
-pub mod history;
\ No newline at end of file
+pub mod history;
+pub mod choice_dialog;
+pub mod queue_dialog;
+pub mod statistics_dialog;
+pub mod text_table;
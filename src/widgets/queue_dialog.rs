@@ -0,0 +1,51 @@
+use gtk::prelude::*;
+use gtk::{Dialog, DialogFlags, ResponseType};
+use tr::tr;
+
+use super::text_table::build_text_table;
+use crate::scheduling::{schedule, SECONDS_PER_DAY};
+use crate::statistics::{current_timestamp, Statistics};
+
+pub fn show_queue_dialog(parent: &gtk::Window, statistics: &Statistics) {
+    let dialog = Dialog::with_buttons(
+        Some(&tr!("Drills queue")),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[(&tr!("Close"), ResponseType::Close)],
+    );
+
+    let titles = [
+        tr!("Endgame"),
+        tr!("Due"),
+        tr!("Interval (days)"),
+        tr!("Successful repetitions"),
+    ];
+    let now = current_timestamp();
+
+    let rows: Vec<Vec<String>> = schedule(statistics.results())
+        .iter()
+        .map(|state| {
+            let due = match state.due_date {
+                None => tr!("New"),
+                Some(_) if state.is_due(now) => tr!("Now"),
+                Some(due_date) => {
+                    let days = (due_date - now).div_ceil(SECONDS_PER_DAY);
+                    tr!("In {} day(s)", days)
+                }
+            };
+            vec![
+                state.kind.title(),
+                due,
+                state.interval_days.to_string(),
+                state.repetitions.to_string(),
+            ]
+        })
+        .collect();
+
+    let tree_view = build_text_table(&titles, &rows);
+
+    dialog.content_area().add(&tree_view);
+    dialog.show_all();
+    dialog.run();
+    dialog.emit_close();
+}
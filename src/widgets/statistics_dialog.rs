@@ -0,0 +1,79 @@
+use gtk::prelude::*;
+use gtk::{Dialog, DialogFlags, ResponseType};
+use tr::tr;
+
+use super::text_table::build_text_table;
+use crate::exercises::EndgameKind;
+use crate::statistics::Statistics;
+
+const TREND_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn show_statistics_dialog(parent: &gtk::Window, statistics: &Statistics) {
+    let dialog = Dialog::with_buttons(
+        Some(&tr!("Statistics")),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[(&tr!("Close"), ResponseType::Close)],
+    );
+
+    let titles = [
+        tr!("Endgame"),
+        tr!("Attempts"),
+        tr!("Success rate"),
+        tr!("Moves over optimum"),
+        tr!("Time spent"),
+        tr!("Hints used"),
+        tr!("Trend (weeks)"),
+    ];
+    let mut rows = vec![];
+
+    for kind in EndgameKind::ALL {
+        let summary = statistics.summary(kind);
+        let success_rate = match summary.success_rate() {
+            Some(rate) => format!(
+                "{:.0} % ({}/{})",
+                rate * 100.0,
+                summary.successes,
+                summary.attempts
+            ),
+            None => "-".to_string(),
+        };
+        let moves_over_optimum = match summary.average_moves_over_optimum {
+            Some(average) => format!("{:.1}", average),
+            None => "-".to_string(),
+        };
+        let trend: String = summary
+            .weekly_success_rates
+            .iter()
+            .map(|rate| match rate {
+                Some(rate) => TREND_BARS[((rate * 7.0).round() as usize).min(7)],
+                None => '·',
+            })
+            .collect();
+        rows.push(vec![
+            kind.title(),
+            summary.attempts.to_string(),
+            success_rate,
+            moves_over_optimum,
+            format_duration(summary.time_spent_secs),
+            summary.hints_used.to_string(),
+            trend,
+        ]);
+    }
+
+    let tree_view = build_text_table(&titles, &rows);
+
+    dialog.content_area().add(&tree_view);
+    dialog.show_all();
+    dialog.run();
+    dialog.emit_close();
+}
+
+pub(crate) fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}h{:02}m{:02}s",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
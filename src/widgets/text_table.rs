@@ -0,0 +1,28 @@
+use gtk::prelude::*;
+use gtk::{CellRendererText, ListStore, TreeView, TreeViewColumn};
+
+/// Builds a read-only table whose cells are all texts.
+pub fn build_text_table(titles: &[String], rows: &[Vec<String>]) -> TreeView {
+    let store = ListStore::new(&vec![String::static_type(); titles.len()]);
+
+    for row in rows {
+        let columns_values: Vec<(u32, &dyn ToValue)> = row
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index as u32, value as &dyn ToValue))
+            .collect();
+        store.insert_with_values(None, &columns_values);
+    }
+
+    let tree_view = TreeView::with_model(&store);
+    for (index, title) in titles.iter().enumerate() {
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        column.set_title(title);
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", index as i32);
+        tree_view.append_column(&column);
+    }
+
+    tree_view
+}
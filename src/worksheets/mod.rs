@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use anyhow::{self, Context as AnyhowContext};
+use gtk::cairo::{Context, PdfSurface};
+use owlchess::{Board, Color, MoveChain};
+use tr::tr;
+
+use crate::engine::UciEngine;
+use crate::rendering::{pieces_images::PiecesImages, Diagram, Theme};
+
+/// A4 page, in points.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 50.0;
+
+const TITLE_FONT_SIZE: f64 = 18.0;
+const TEXT_FONT_SIZE: f64 = 12.0;
+const LINE_HEIGHT: f64 = 18.0;
+
+const COLUMNS: usize = 2;
+const ROWS: usize = 3;
+const DIAGRAMS_TOP: f64 = 90.0;
+const DIAGRAM_SIZE: f64 = 190.0;
+const ROW_HEIGHT: f64 = 240.0;
+
+/// The pieces images are drawn at this scale, then shrunk, so that they stay sharp once printed.
+const PIECES_RESOLUTION: f64 = 4.0;
+
+pub struct WorksheetEntry {
+    pub position: Board,
+    /// In SAN, None if the position has no legal move.
+    pub best_move: Option<String>,
+}
+
+/// Asks the engine the best move of each position.
+pub fn build_entries(
+    positions: Vec<Board>,
+    engine: &mut UciEngine,
+) -> anyhow::Result<Vec<WorksheetEntry>> {
+    positions
+        .into_iter()
+        .map(|position| {
+            let best_move = if position.has_legal_moves() {
+                let chain = MoveChain::new(position.clone());
+                let best_move = engine.best_move(&chain, UciEngine::OPTIMUM_SEARCH_TIME_MS)?;
+                let san = best_move
+                    .san(&position)
+                    .map_err(|err| anyhow::anyhow!("Illegal move ({}).", err))?;
+                Some(san.to_string())
+            } else {
+                None
+            };
+            Ok(WorksheetEntry {
+                position,
+                best_move,
+            })
+        })
+        .collect()
+}
+
+/// Writes the diagrams, six per page, then the answer key.
+pub fn write_worksheet(
+    path: &Path,
+    title: &str,
+    entries: &[WorksheetEntry],
+    theme: Theme,
+) -> anyhow::Result<()> {
+    let surface = PdfSurface::new(PAGE_WIDTH, PAGE_HEIGHT, path)
+        .with_context(|| format!("Failed to create {}.", path.display()))?;
+    let cx = Context::new(&surface)?;
+    let cells_size = (DIAGRAM_SIZE * PIECES_RESOLUTION * 0.111) as i32;
+    let pieces_images = PiecesImages::new(cells_size)?;
+
+    for (index, entry) in entries.iter().enumerate() {
+        let (page, x, y) = get_diagram_location(index);
+        if page > 0 && index % (COLUMNS * ROWS) == 0 {
+            cx.show_page()?;
+        }
+        if index % (COLUMNS * ROWS) == 0 {
+            draw_page_title(&cx, &format!("{} ({})", title, page + 1))?;
+        }
+
+        let mut diagram = Diagram::new(entry.position.clone());
+        diagram.theme = theme;
+        diagram.reversed = entry.position.side() == Color::Black;
+
+        cx.save()?;
+        cx.translate(x, y);
+        cx.scale(1.0 / PIECES_RESOLUTION, 1.0 / PIECES_RESOLUTION);
+        diagram.draw(&cx, DIAGRAM_SIZE * PIECES_RESOLUTION, &pieces_images, None)?;
+        cx.restore()?;
+
+        let caption = if entry.position.side() == Color::White {
+            tr!("{}. White to move", index + 1)
+        } else {
+            tr!("{}. Black to move", index + 1)
+        };
+        draw_text(
+            &cx,
+            &caption,
+            TEXT_FONT_SIZE,
+            x,
+            y + DIAGRAM_SIZE + LINE_HEIGHT,
+        )?;
+    }
+
+    if !entries.is_empty() {
+        cx.show_page()?;
+    }
+    draw_page_title(&cx, &tr!("Answers"))?;
+    let mut y = DIAGRAMS_TOP;
+    for (index, entry) in entries.iter().enumerate() {
+        if y > PAGE_HEIGHT - MARGIN {
+            cx.show_page()?;
+            draw_page_title(&cx, &tr!("Answers"))?;
+            y = DIAGRAMS_TOP;
+        }
+        let answer = match &entry.best_move {
+            Some(best_move) => format!("{}. {}", index + 1, best_move),
+            None => tr!("{}. No legal move", index + 1),
+        };
+        draw_text(&cx, &answer, TEXT_FONT_SIZE, MARGIN, y)?;
+        y += LINE_HEIGHT;
+    }
+
+    cx.show_page()?;
+    drop(cx);
+    surface.finish();
+    Ok(())
+}
+
+/// Page index, then top left corner of the diagram of the given index.
+fn get_diagram_location(index: usize) -> (usize, f64, f64) {
+    let page = index / (COLUMNS * ROWS);
+    let index_in_page = index % (COLUMNS * ROWS);
+    let row = index_in_page / COLUMNS;
+    let col = index_in_page % COLUMNS;
+
+    let column_width = (PAGE_WIDTH - 2.0 * MARGIN) / COLUMNS as f64;
+    let x = MARGIN + column_width * col as f64 + (column_width - DIAGRAM_SIZE) / 2.0;
+    let y = DIAGRAMS_TOP + ROW_HEIGHT * row as f64;
+    (page, x, y)
+}
+
+fn draw_page_title(cx: &Context, title: &str) -> anyhow::Result<()> {
+    draw_text(cx, title, TITLE_FONT_SIZE, MARGIN, MARGIN)
+}
+
+fn draw_text(cx: &Context, text: &str, font_size: f64, x: f64, y: f64) -> anyhow::Result<()> {
+    cx.set_source_rgb(0.0, 0.0, 0.0);
+    cx.set_font_size(font_size);
+    cx.move_to(x, y);
+    cx.show_text(text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagrams_fill_the_pages_row_by_row() {
+        let (page, first_x, first_y) = get_diagram_location(0);
+        assert_eq!(page, 0);
+
+        let (page, second_x, second_y) = get_diagram_location(1);
+        assert_eq!(page, 0);
+        assert!(second_x > first_x);
+        assert_eq!(second_y, first_y);
+
+        let (page, third_x, third_y) = get_diagram_location(2);
+        assert_eq!(page, 0);
+        assert_eq!(third_x, first_x);
+        assert!(third_y > first_y);
+
+        assert_eq!(get_diagram_location(6), (1, first_x, first_y));
+        assert_eq!(get_diagram_location(13).0, 2);
+    }
+
+    #[test]
+    fn diagrams_and_captions_stay_inside_the_page() {
+        for index in 0..(COLUMNS * ROWS) {
+            let (_, x, y) = get_diagram_location(index);
+            assert!(x >= MARGIN);
+            assert!(x + DIAGRAM_SIZE <= PAGE_WIDTH - MARGIN);
+            assert!(y + DIAGRAM_SIZE + LINE_HEIGHT <= PAGE_HEIGHT - MARGIN);
+        }
+    }
+}